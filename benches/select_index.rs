@@ -0,0 +1,115 @@
+// Compares routing inserts through the predicate indexed SelectIndex against evaluating every
+// select on the relation, run with `cargo bench --bench select_index`
+use std::time::{Duration, Instant};
+
+use sqlparser::dialect::GenericDialect;
+use sqlparser::parser::Parser;
+
+use turnip_rs::db::data::TypeValue;
+use turnip_rs::db::models::number_value::NumberValueType;
+use turnip_rs::db::select_index::SelectIndex;
+use turnip_rs::models::expression::Expression;
+use turnip_rs::models::insert_query::InsertQuery;
+use turnip_rs::models::projection::ProjectionItem;
+use turnip_rs::models::select_query::SelectQuery;
+
+fn select(constraint: &str) -> SelectQuery {
+    let dialect = GenericDialect {};
+
+    let expr = Parser::new(&dialect)
+        .try_with_sql(constraint)
+        .and_then(|mut parser| parser.parse_expr())
+        .expect("Error with parsing the sql");
+
+    SelectQuery {
+        into: "customer_cache".to_string(),
+        projection: vec![ProjectionItem::Wildcard],
+        from: "customer".to_string(),
+        constraints: Some(Expression::try_from(&expr).expect("Could not convert expression")),
+        aggregates: vec![],
+        group_by: vec![],
+        join: None,
+    }
+}
+
+fn number(value: usize) -> Option<TypeValue> {
+    Some(TypeValue::NumberValueType(NumberValueType {
+        value: value as f64,
+    }))
+}
+
+fn build_index(subscriptions: usize) -> SelectIndex {
+    let mut index = SelectIndex::new();
+
+    for i in 0..subscriptions {
+        let addr = format!("10.0.{}.{}:8080", i / 256, i % 256);
+
+        // mostly point lookups, with some ranges and a few selects that can not be indexed
+        let constraint = match i % 10 {
+            0..=6 => format!("id = {i} and age > 18"),
+            7 | 8 => format!("age > {} and region = {}", i % 100, i % 7),
+            _ => format!("id = {i} or region = {}", i % 7),
+        };
+
+        index
+            .insert_select(&addr, select(&constraint))
+            .expect("Could not insert select");
+    }
+
+    index
+}
+
+fn inserts(count: usize, subscriptions: usize) -> Vec<InsertQuery> {
+    (0..count)
+        .map(|i| InsertQuery {
+            table_name: "customer".to_string(),
+            columns: vec!["id".to_string(), "age".to_string(), "region".to_string()],
+            rows: vec![vec![
+                number((i * 7919) % subscriptions),
+                number(i % 90),
+                number(i % 11),
+            ]],
+        })
+        .collect()
+}
+
+fn time(
+    index: &SelectIndex,
+    inserts: &[InsertQuery],
+    route: fn(&SelectIndex, &InsertQuery) -> Vec<String>,
+) -> (Duration, usize) {
+    let start = Instant::now();
+
+    let routed = inserts
+        .iter()
+        .map(|insert| route(index, insert).len())
+        .sum();
+
+    (start.elapsed(), routed)
+}
+
+fn main() {
+    for subscriptions in [100, 1_000, 10_000] {
+        let index = build_index(subscriptions);
+        let inserts = inserts(1_000, subscriptions);
+
+        let (indexed, indexed_routed) = time(&index, &inserts, |index, insert| {
+            index.get_addr_for_insert(insert).expect("Could not route")
+        });
+        let (scan, scan_routed) = time(&index, &inserts, |index, insert| {
+            index
+                .get_addr_for_insert_scan(insert)
+                .expect("Could not route")
+        });
+
+        assert_eq!(indexed_routed, scan_routed);
+
+        println!(
+            "{subscriptions:>6} selects, {} inserts: indexed {:>10.3?} scan {:>10.3?} ({:.1}x)",
+            inserts.len(),
+            indexed,
+            scan,
+            scan.as_secs_f64() / indexed.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,190 @@
+// Compares the columnar Table against keeping every row as a HashMap, in the memory the rows take
+// and the time a filtering scan takes, and a scan of the Table with an index against one without.
+// Run with `cargo bench --bench table`
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use sqlparser::dialect::GenericDialect;
+use sqlparser::parser::Parser;
+
+use turnip_rs::db::data::TypeValue;
+use turnip_rs::db::models::{
+    integer_value::IntegerValueType, number_value::NumberValueType, string_value::StringTypeValue,
+};
+use turnip_rs::db::table::Table;
+use turnip_rs::db::table_index::TableIndex;
+use turnip_rs::models::create_index_query::IndexKind;
+use turnip_rs::models::expression::Expression;
+
+// counts the bytes allocated and not freed yet
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+// the bytes the value built by the closure holds on to
+fn allocated<T>(build: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let value = build();
+
+    (value, ALLOCATED.load(Ordering::Relaxed) - before)
+}
+
+fn expression(sql: &str) -> Expression {
+    let expr = Parser::new(&GenericDialect {})
+        .try_with_sql(sql)
+        .and_then(|mut parser| parser.parse_expr())
+        .expect("Error with parsing the sql");
+
+    Expression::try_from(&expr).expect("Could not convert expression")
+}
+
+fn columns() -> Vec<String> {
+    ["id", "region", "price", "quantity"]
+        .iter()
+        .map(|c| c.to_string())
+        .collect()
+}
+
+fn values(i: usize) -> Vec<Option<TypeValue>> {
+    vec![
+        Some(TypeValue::IntegerValueType(IntegerValueType {
+            value: i as i64,
+        })),
+        Some(TypeValue::StringTypeValue(StringTypeValue {
+            value: ["eu", "us", "apac"][i % 3].to_string(),
+        })),
+        Some(TypeValue::NumberValueType(NumberValueType {
+            value: (i % 1000) as f64 / 10.0,
+        })),
+        // every tenth quantity is unknown
+        if i.is_multiple_of(10) {
+            None
+        } else {
+            Some(TypeValue::IntegerValueType(IntegerValueType {
+                value: (i % 7) as i64,
+            }))
+        },
+    ]
+}
+
+fn time<T>(mut scan: impl FnMut() -> T) -> (Duration, T) {
+    let start = Instant::now();
+    let result = scan();
+
+    (start.elapsed(), result)
+}
+
+fn main() {
+    let columns = columns();
+
+    for count in [10_000, 100_000, 1_000_000] {
+        let (rows, row_bytes) = allocated(|| {
+            (0..count)
+                .map(|i| {
+                    columns
+                        .iter()
+                        .cloned()
+                        .zip(
+                            values(i)
+                                .into_iter()
+                                .map(|v| v.unwrap_or(TypeValue::NullValueType)),
+                        )
+                        .collect::<HashMap<String, TypeValue>>()
+                })
+                .collect::<Vec<HashMap<String, TypeValue>>>()
+        });
+
+        let (table, table_bytes) = allocated(|| {
+            let mut table = Table::new();
+
+            for i in 0..count {
+                table.push_values(&columns, &values(i));
+            }
+
+            table
+        });
+
+        println!(
+            "{count:>8} rows: rows {:>8} KiB table {:>8} KiB ({:.1}x)",
+            row_bytes / 1024,
+            table_bytes / 1024,
+            row_bytes as f64 / table_bytes as f64
+        );
+
+        for constraint in [
+            "price > 90 and region = 'eu'",
+            "quantity = 3 or region = 'apac'",
+        ] {
+            let constraint = expression(constraint);
+
+            let (row_scan, row_matches) = time(|| {
+                rows.iter()
+                    .filter(|row| constraint.evaluate(row).unwrap_or(false))
+                    .count()
+            });
+            let (table_scan, table_matches) = time(|| {
+                table
+                    .filter(Some(&constraint))
+                    .expect("Could not filter")
+                    .len()
+            });
+
+            assert_eq!(row_matches, table_matches);
+
+            println!(
+                "{:>8} matches: rows {:>10.3?} table {:>10.3?} ({:.1}x)",
+                table_matches,
+                row_scan,
+                table_scan,
+                row_scan.as_secs_f64() / table_scan.as_secs_f64()
+            );
+        }
+
+        let mut indexed = table.clone();
+        indexed.create_index(TableIndex::new("id_idx", "id", IndexKind::BTree));
+
+        for constraint in ["id = 4242", "id >= 9000 and id < 9100"] {
+            let constraint = expression(constraint);
+
+            let (table_scan, table_matches) = time(|| {
+                table
+                    .filter(Some(&constraint))
+                    .expect("Could not filter")
+                    .len()
+            });
+            let (index_scan, index_matches) = time(|| {
+                indexed
+                    .filter(Some(&constraint))
+                    .expect("Could not filter")
+                    .len()
+            });
+
+            assert_eq!(table_matches, index_matches);
+
+            println!(
+                "{:>8} matches: table {:>9.3?} indexed {:>8.3?} ({:.1}x)",
+                index_matches,
+                table_scan,
+                index_scan,
+                table_scan.as_secs_f64() / index_scan.as_secs_f64()
+            );
+        }
+    }
+}
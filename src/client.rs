@@ -3,6 +3,7 @@ use std::time::Duration;
 use tcp_client::make_client;
 use tokio::io::AsyncWriteExt;
 use tokio::time::sleep;
+use turnip_rs::messaging::codec::{encode_frame, DEFAULT_MAX_FRAME_SIZE};
 
 mod tcp_client;
 
@@ -10,10 +11,11 @@ mod tcp_client;
 async fn main() -> Result<(), Box<dyn Error>> {
     let mut stream = make_client("127.0.0.1:8080").await?;
 
+    // the runtime only accepts length-prefixed frames
+    let frame = encode_frame(b"hello world\n", DEFAULT_MAX_FRAME_SIZE)?;
+
     loop {
         sleep(Duration::from_millis(1000)).await;
-        let _result = stream.write_all(b"hello world\n").await;
+        let _result = stream.write_all(&frame).await;
     }
-
-    Ok(())
 }
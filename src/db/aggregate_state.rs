@@ -0,0 +1,367 @@
+// the running state of an aggregating view, kept per group so every inserted row only
+// updates its own group instead of the aggregates being recomputed over all the rows
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use super::data::{project_row, TypeValue};
+use super::models::decimal_value::DecimalValueType;
+use super::models::integer_value::IntegerValueType;
+use super::models::number_value::NumberValueType;
+use super::models::value_key::ValueKey;
+use crate::models::aggregate::{Aggregate, AggregateFunction};
+use crate::models::arithmetic;
+use crate::models::expression::ExpressionBinaryOperator;
+use crate::models::select_query::SelectQuery;
+
+// A running sum that stays exact for as long as every value in it is an integer or a decimal,
+// floats are summed as floats like everywhere else
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Total {
+    // the sum of the integers and decimals, None once it no longer fit
+    exact: Option<DecimalValueType>,
+    // the sum of every value as a float, for when the exact sum can't be used
+    float: f64,
+    // the number of values summed, and how many of them are floats and decimals
+    count: u64,
+    floats: u64,
+    decimals: u64,
+}
+
+impl Total {
+    fn new() -> Self {
+        Total {
+            exact: Some(DecimalValueType::new(0, 0)),
+            float: 0.0,
+            count: 0,
+            floats: 0,
+            decimals: 0,
+        }
+    }
+
+    // adds a value to the sum, or takes it back off, false if the value is not a number
+    fn change(&mut self, value: &TypeValue, added: bool) -> bool {
+        let Some(float) = value.as_f64() else {
+            return false;
+        };
+
+        let step = |n: u64| if added { n + 1 } else { n.saturating_sub(1) };
+
+        let exact = match value {
+            TypeValue::IntegerValueType(v) => DecimalValueType::new(v.value as i128, 0),
+            TypeValue::DecimalValueType(v) => {
+                self.decimals = step(self.decimals);
+                v.clone()
+            }
+            _ => {
+                self.floats = step(self.floats);
+                DecimalValueType::new(0, 0)
+            }
+        };
+
+        self.exact = self.exact.take().and_then(|sum| {
+            let scale = sum.scale.max(exact.scale);
+            let value = exact.rescaled(scale)?;
+            let value = if added { value } else { value.checked_neg()? };
+
+            Some(DecimalValueType::new(
+                sum.rescaled(scale)?.checked_add(value)?,
+                scale,
+            ))
+        });
+
+        self.float += if added { float } else { -float };
+        self.count = step(self.count);
+
+        true
+    }
+
+    // integers sum up to an integer, as long as the sum fits one, and decimals to a decimal
+    fn sum(&self) -> TypeValue {
+        match &self.exact {
+            Some(sum) if self.floats == 0 && self.decimals == 0 => {
+                // a decimal that was summed and taken back off may have left a scale behind
+                let whole = 10i128
+                    .checked_pow(sum.scale)
+                    .map(|factor| sum.mantissa / factor)
+                    .and_then(|whole| i64::try_from(whole).ok());
+
+                match whole {
+                    Some(value) => TypeValue::IntegerValueType(IntegerValueType { value }),
+                    None => TypeValue::DecimalValueType(sum.clone()),
+                }
+            }
+            Some(sum) if self.floats == 0 => TypeValue::DecimalValueType(sum.clone()),
+            _ => TypeValue::NumberValueType(NumberValueType { value: self.float }),
+        }
+    }
+
+    // the average of integers and decimals is a decimal, divided like decimals are
+    fn avg(&self) -> TypeValue {
+        let float = || {
+            TypeValue::NumberValueType(NumberValueType {
+                value: self.float / self.count as f64,
+            })
+        };
+
+        match &self.exact {
+            Some(sum) if self.floats == 0 => arithmetic::apply(
+                &ExpressionBinaryOperator::Divide,
+                &TypeValue::DecimalValueType(sum.clone()),
+                &TypeValue::IntegerValueType(IntegerValueType {
+                    value: self.count as i64,
+                }),
+            )
+            .unwrap_or_else(|_| float()),
+            _ => float(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Accumulator {
+    Count(u64),
+    Sum(Total),
+    Avg(Total),
+    // every value seen with the number of times it was seen, so the extremes are known
+    // without looking at the rows again
+    Min(BTreeMap<ValueKey, (TypeValue, u64)>),
+    Max(BTreeMap<ValueKey, (TypeValue, u64)>),
+}
+
+impl Accumulator {
+    pub fn new(function: AggregateFunction) -> Self {
+        match function {
+            AggregateFunction::Count => Accumulator::Count(0),
+            AggregateFunction::Sum => Accumulator::Sum(Total::new()),
+            AggregateFunction::Avg => Accumulator::Avg(Total::new()),
+            AggregateFunction::Min => Accumulator::Min(BTreeMap::new()),
+            AggregateFunction::Max => Accumulator::Max(BTreeMap::new()),
+        }
+    }
+
+    // adds the aggregated value of a row, None is COUNT(*) counting the row itself.
+    // Like in SQL, nulls are not aggregated.
+    pub fn add(&mut self, value: Option<TypeValue>) {
+        let value = match value {
+            Some(TypeValue::NullValueType) => return,
+            Some(value) => value,
+            None => {
+                if let Accumulator::Count(count) = self {
+                    *count += 1;
+                }
+                return;
+            }
+        };
+
+        match self {
+            Accumulator::Count(count) => *count += 1,
+            Accumulator::Sum(total) | Accumulator::Avg(total) => {
+                if !total.change(&value, true) {
+                    eprintln!("Can not aggregate a non numeric value: {:?}", value);
+                }
+            }
+            Accumulator::Min(values) | Accumulator::Max(values) => {
+                values.entry(ValueKey::from(&value)).or_insert((value, 0)).1 += 1;
+            }
+        }
+    }
+
+    // takes back a value added before, for rows that were updated or deleted
+    pub fn remove(&mut self, value: Option<TypeValue>) {
+        let value = match value {
+            Some(TypeValue::NullValueType) => return,
+            Some(value) => value,
+            None => {
+                if let Accumulator::Count(count) = self {
+                    *count = count.saturating_sub(1);
+                }
+                return;
+            }
+        };
+
+        match self {
+            Accumulator::Count(count) => *count = count.saturating_sub(1),
+            Accumulator::Sum(total) | Accumulator::Avg(total) => {
+                total.change(&value, false);
+            }
+            Accumulator::Min(values) | Accumulator::Max(values) => {
+                let key = ValueKey::from(&value);
+
+                if let Some((_, seen)) = values.get_mut(&key) {
+                    *seen -= 1;
+
+                    if *seen == 0 {
+                        values.remove(&key);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn value(&self) -> TypeValue {
+        match self {
+            Accumulator::Count(count) => TypeValue::IntegerValueType(IntegerValueType {
+                value: *count as i64,
+            }),
+            // the sum or average of no values is null
+            Accumulator::Sum(total) | Accumulator::Avg(total) if total.count == 0 => {
+                TypeValue::NullValueType
+            }
+            Accumulator::Sum(total) => total.sum(),
+            Accumulator::Avg(total) => total.avg(),
+            Accumulator::Min(values) => match values.values().next() {
+                Some((value, _)) => value.clone(),
+                None => TypeValue::NullValueType,
+            },
+            Accumulator::Max(values) => match values.values().next_back() {
+                Some((value, _)) => value.clone(),
+                None => TypeValue::NullValueType,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Group {
+    // the values of the group by columns
+    values: Vec<TypeValue>,
+    accumulators: Vec<Accumulator>,
+    // the number of rows in the group, a group without rows is removed from the view
+    rows: u64,
+    // position of the group's row in the view's table
+    row: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct AggregateState {
+    groups: HashMap<Vec<ValueKey>, Group>,
+}
+
+fn aggregated_value(aggregate: &Aggregate, row: &HashMap<String, TypeValue>) -> Option<TypeValue> {
+    aggregate
+        .argument
+        .as_ref()
+        .map(|argument| argument.resolve(row).unwrap_or(TypeValue::NullValueType))
+}
+
+impl AggregateState {
+    // An aggregate without a GROUP BY has exactly one row, even before any rows came in
+    pub fn new(query: &SelectQuery, table: &mut Vec<HashMap<String, TypeValue>>) -> Self {
+        let mut state = AggregateState::default();
+
+        if query.group_by.is_empty() {
+            state.group_mut(query, vec![], table);
+        }
+
+        state
+    }
+
+    fn group_mut(
+        &mut self,
+        query: &SelectQuery,
+        values: Vec<TypeValue>,
+        table: &mut Vec<HashMap<String, TypeValue>>,
+    ) -> &mut Group {
+        let key: Vec<ValueKey> = values.iter().map(ValueKey::from).collect();
+
+        self.groups.entry(key).or_insert_with(|| {
+            let group = Group {
+                values,
+                accumulators: query
+                    .aggregates
+                    .iter()
+                    .map(|aggregate| Accumulator::new(aggregate.function))
+                    .collect(),
+                rows: 0,
+                row: table.len(),
+            };
+
+            table.push(group_row(query, &group));
+
+            group
+        })
+    }
+
+    // folds a row that matches the select into its group, and rewrites the group's row in the table
+    pub fn add_row(
+        &mut self,
+        query: &SelectQuery,
+        row: &HashMap<String, TypeValue>,
+        table: &mut Vec<HashMap<String, TypeValue>>,
+    ) {
+        let group = self.group_mut(query, group_values(query, row), table);
+
+        group.rows += 1;
+
+        for (accumulator, aggregate) in group.accumulators.iter_mut().zip(query.aggregates.iter()) {
+            accumulator.add(aggregated_value(aggregate, row));
+        }
+
+        table[group.row] = group_row(query, group);
+    }
+
+    // takes a row that was folded into its group before back out of it
+    pub fn remove_row(
+        &mut self,
+        query: &SelectQuery,
+        row: &HashMap<String, TypeValue>,
+        table: &mut Vec<HashMap<String, TypeValue>>,
+    ) {
+        let key: Vec<ValueKey> = group_values(query, row)
+            .iter()
+            .map(ValueKey::from)
+            .collect();
+
+        let group = match self.groups.get_mut(&key) {
+            Some(group) => group,
+            None => return,
+        };
+
+        group.rows = group.rows.saturating_sub(1);
+
+        for (accumulator, aggregate) in group.accumulators.iter_mut().zip(query.aggregates.iter()) {
+            accumulator.remove(aggregated_value(aggregate, row));
+        }
+
+        // the single group of an aggregate without a GROUP BY stays, even without rows
+        if group.rows > 0 || query.group_by.is_empty() {
+            table[group.row] = group_row(query, group);
+            return;
+        }
+
+        let removed = group.row;
+        self.groups.remove(&key);
+        table.swap_remove(removed);
+
+        // the last row of the table took the place of the removed one
+        if let Some(moved) = self.groups.values_mut().find(|g| g.row == table.len()) {
+            moved.row = removed;
+        }
+    }
+}
+
+fn group_values(query: &SelectQuery, row: &HashMap<String, TypeValue>) -> Vec<TypeValue> {
+    query
+        .group_by
+        .iter()
+        .map(|column| row.get(column).cloned().unwrap_or(TypeValue::NullValueType))
+        .collect()
+}
+
+fn group_row(query: &SelectQuery, group: &Group) -> HashMap<String, TypeValue> {
+    // the projection only reads group by columns, which are only part of the view if selected
+    let values: HashMap<String, TypeValue> = query
+        .group_by
+        .iter()
+        .cloned()
+        .zip(group.values.iter().cloned())
+        .collect();
+
+    let mut row = project_row(&query.projection, &values);
+
+    for (accumulator, aggregate) in group.accumulators.iter().zip(query.aggregates.iter()) {
+        row.insert(aggregate.alias.clone(), accumulator.value());
+    }
+
+    row
+}
@@ -1,21 +1,171 @@
 // this is the in-memory(for now) DB for holding local data in the node
 use serde::{Deserialize, Serialize};
-use sqlparser::ast::Value;
+use sqlparser::ast::{DataType, Expr, UnaryOperator, Value};
 
 use super::errors::DatabaseError;
+use super::join_state::qualify_row;
+use super::schema::{coerce, TableSchema};
+use super::select_index::SelectIndex;
+use super::snapshot::Snapshot;
+use super::table::Table;
+use super::table_index::TableIndex;
+use super::view::View;
+use super::wal::{FsyncPolicy, Lsn, Wal, WalRecord};
+use crate::models::create_index_query::CreateIndexQuery;
+use crate::models::create_table_query::CreateTableQuery;
+use crate::models::delete_query::DeleteQuery;
+use crate::models::drop_view_query::DropViewQuery;
+use crate::models::expression::Expression;
+use crate::models::projection::ProjectionItem;
 use crate::models::select_query::SelectQuery;
+use crate::models::update_query::UpdateQuery;
 use crate::{db::errors::ValueParseError, models::insert_query::InsertQuery};
 
 use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
-use super::models::{number_value::NumberValueType, string_value::StringTypeValue};
+use super::models::{
+    boolean_value::BooleanValueType, bytes_value::BytesValueType, date_value::DateValueType,
+    decimal_value::DecimalValueType, integer_value::IntegerValueType,
+    number_value::NumberValueType, string_value::StringTypeValue,
+    timestamp_value::TimestampValueType,
+};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum TypeValue {
     StringTypeValue(StringTypeValue),
     NumberValueType(NumberValueType),
     NullValueType,
+    BooleanValueType(BooleanValueType),
+    IntegerValueType(IntegerValueType),
+    DecimalValueType(DecimalValueType),
+    TimestampValueType(TimestampValueType),
+    DateValueType(DateValueType),
+    BytesValueType(BytesValueType),
+}
+
+impl TypeValue {
+    // Parses a number literal: whole numbers that fit are integers, numbers with a point are exact
+    // decimals and anything else (exponents, huge numbers) is a float
+    pub fn parse_number(s: &str) -> Option<Self> {
+        if let Ok(value) = s.parse::<i64>() {
+            return Some(TypeValue::IntegerValueType(IntegerValueType { value }));
+        }
+
+        if let Some(decimal) = DecimalValueType::parse(s) {
+            return Some(TypeValue::DecimalValueType(decimal));
+        }
+
+        s.parse::<f64>()
+            .ok()
+            .map(|value| TypeValue::NumberValueType(NumberValueType { value }))
+    }
+
+    // parses the string of a typed literal like DATE '2023-01-31' into a value of the type
+    pub fn parse_typed(data_type: &DataType, s: &str) -> Option<Self> {
+        match data_type {
+            DataType::Date => DateValueType::parse(s).map(TypeValue::DateValueType),
+            DataType::Timestamp(..) | DataType::Datetime(_) => {
+                TimestampValueType::parse(s).map(TypeValue::TimestampValueType)
+            }
+            DataType::Boolean => match s.to_lowercase().as_str() {
+                "true" => Some(TypeValue::BooleanValueType(BooleanValueType {
+                    value: true,
+                })),
+                "false" => Some(TypeValue::BooleanValueType(BooleanValueType {
+                    value: false,
+                })),
+                _ => None,
+            },
+            DataType::Bytea | DataType::Binary(_) | DataType::Varbinary(_) | DataType::Blob(_) => {
+                BytesValueType::from_hex(s).map(TypeValue::BytesValueType)
+            }
+            DataType::TinyInt(_)
+            | DataType::SmallInt(_)
+            | DataType::MediumInt(_)
+            | DataType::Int(_)
+            | DataType::Integer(_)
+            | DataType::BigInt(_) => s
+                .trim()
+                .parse::<i64>()
+                .ok()
+                .map(|value| TypeValue::IntegerValueType(IntegerValueType { value })),
+            DataType::Numeric(_)
+            | DataType::Decimal(_)
+            | DataType::Dec(_)
+            | DataType::BigNumeric(_)
+            | DataType::BigDecimal(_) => {
+                DecimalValueType::parse(s.trim()).map(TypeValue::DecimalValueType)
+            }
+            DataType::Float(_) | DataType::Real | DataType::Double | DataType::DoublePrecision => s
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .map(|value| TypeValue::NumberValueType(NumberValueType { value })),
+            _ => Some(TypeValue::StringTypeValue(StringTypeValue {
+                value: s.to_string(),
+            })),
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            TypeValue::NumberValueType(_)
+                | TypeValue::IntegerValueType(_)
+                | TypeValue::DecimalValueType(_)
+        )
+    }
+
+    // the value of a number as a float, None for anything that is not a number
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            TypeValue::NumberValueType(v) => Some(v.value),
+            TypeValue::IntegerValueType(v) => Some(v.value as f64),
+            TypeValue::DecimalValueType(v) => Some(v.to_f64()),
+            _ => None,
+        }
+    }
+
+    // the value as text, for concatenating and string functions. None for null.
+    pub fn to_text(&self) -> Option<String> {
+        match self {
+            TypeValue::StringTypeValue(v) => Some(v.value.clone()),
+            TypeValue::NumberValueType(v) => Some(v.value.to_string()),
+            TypeValue::IntegerValueType(v) => Some(v.value.to_string()),
+            TypeValue::DecimalValueType(v) => Some(v.to_string()),
+            TypeValue::BooleanValueType(v) => Some(v.value.to_string()),
+            TypeValue::DateValueType(v) => Some(v.to_string()),
+            TypeValue::TimestampValueType(v) => Some(v.to_string()),
+            // like postgres, bytes are written out in hex
+            TypeValue::BytesValueType(v) => Some(format!(
+                "\\x{}",
+                v.value
+                    .iter()
+                    .map(|b| format!("{b:02x}"))
+                    .collect::<String>()
+            )),
+            TypeValue::NullValueType => None,
+        }
+    }
+
+    // the value of a number negated, for literals like `-5`
+    pub fn negated(&self) -> Option<TypeValue> {
+        match self {
+            TypeValue::NumberValueType(v) => Some(TypeValue::NumberValueType(NumberValueType {
+                value: -v.value,
+            })),
+            TypeValue::IntegerValueType(v) => v
+                .value
+                .checked_neg()
+                .map(|value| TypeValue::IntegerValueType(IntegerValueType { value })),
+            TypeValue::DecimalValueType(v) => v.mantissa.checked_neg().map(|mantissa| {
+                TypeValue::DecimalValueType(DecimalValueType::new(mantissa, v.scale))
+            }),
+            _ => None,
+        }
+    }
 }
 
 impl TryFrom<&Value> for TypeValue {
@@ -23,45 +173,98 @@ impl TryFrom<&Value> for TypeValue {
 
     fn try_from(v: &Value) -> Result<Self, Self::Error> {
         match v {
-            sqlparser::ast::Value::Number(s, _) => match s.parse::<f64>() {
-                Ok(v) => Ok(TypeValue::NumberValueType(NumberValueType { value: v })),
-                Err(e) => {
-                    eprintln!("Error with parsing float: {v} error: {:?}", e);
-                    Err(ValueParseError::IsNoneError())
-                }
+            Value::Number(s, _) => TypeValue::parse_number(s).ok_or_else(|| {
+                ValueParseError::InvalidLiteralError("number".to_string(), s.to_string())
+            }),
+            Value::SingleQuotedString(s)
+            | Value::DoubleQuotedString(s)
+            | Value::EscapedStringLiteral(s)
+            | Value::NationalStringLiteral(s)
+            | Value::RawStringLiteral(s) => Ok(TypeValue::StringTypeValue(StringTypeValue {
+                value: s.to_string(),
+            })),
+            Value::DollarQuotedString(s) => Ok(TypeValue::StringTypeValue(StringTypeValue {
+                value: s.value.to_string(),
+            })),
+            Value::Boolean(value) => Ok(TypeValue::BooleanValueType(BooleanValueType {
+                value: *value,
+            })),
+            Value::HexStringLiteral(s) => match BytesValueType::from_hex(s) {
+                Some(bytes) => Ok(TypeValue::BytesValueType(bytes)),
+                None => Err(ValueParseError::InvalidLiteralError(
+                    "hex".to_string(),
+                    s.to_string(),
+                )),
             },
-            sqlparser::ast::Value::DoubleQuotedString(s) => {
-                Ok(TypeValue::StringTypeValue(StringTypeValue {
-                    value: s.to_string(),
-                }))
-            }
-            sqlparser::ast::Value::SingleQuotedString(s) => {
-                Ok(TypeValue::StringTypeValue(StringTypeValue {
-                    value: s.to_string(),
+            Value::SingleQuotedByteStringLiteral(s) | Value::DoubleQuotedByteStringLiteral(s) => {
+                Ok(TypeValue::BytesValueType(BytesValueType {
+                    value: s.as_bytes().to_vec(),
                 }))
             }
+            Value::Null => Ok(TypeValue::NullValueType),
             _ => Err(ValueParseError::IsNoneError()),
         }
     }
 }
 
+impl TryFrom<&Expr> for TypeValue {
+    type Error = ValueParseError;
+
+    // the value of a literal, including typed ones like TIMESTAMP '2023-01-31 12:00:00' and negative numbers
+    fn try_from(expr: &Expr) -> Result<Self, Self::Error> {
+        match expr {
+            Expr::Value(value) => TypeValue::try_from(value),
+            Expr::TypedString { data_type, value } => TypeValue::parse_typed(data_type, value)
+                .ok_or_else(|| {
+                    ValueParseError::InvalidLiteralError(data_type.to_string(), value.to_string())
+                }),
+            Expr::UnaryOp {
+                op: UnaryOperator::Minus,
+                expr,
+            } => TypeValue::try_from(&**expr)?.negated().ok_or_else(|| {
+                ValueParseError::IsNotSupportedError(expr.as_ref().clone(), "-".to_string())
+            }),
+            Expr::UnaryOp {
+                op: UnaryOperator::Plus,
+                expr,
+            } => TypeValue::try_from(&**expr),
+            Expr::Nested(expr) => TypeValue::try_from(&**expr),
+            _ => Err(ValueParseError::IsNotSupportedError(
+                expr.clone(),
+                "not a literal".to_string(),
+            )),
+        }
+    }
+}
+
+// Orders two numbers, integers and decimals exactly and anything with a float as floats
+fn compare_numbers(a: &TypeValue, b: &TypeValue) -> Option<Ordering> {
+    match (a, b) {
+        (TypeValue::IntegerValueType(a), TypeValue::IntegerValueType(b)) => {
+            Some(a.value.cmp(&b.value))
+        }
+        (TypeValue::DecimalValueType(a), TypeValue::DecimalValueType(b)) => a.compare(b),
+        (TypeValue::DecimalValueType(a), TypeValue::IntegerValueType(b)) => {
+            a.compare(&DecimalValueType::new(b.value as i128, 0))
+        }
+        (TypeValue::IntegerValueType(a), TypeValue::DecimalValueType(b)) => {
+            DecimalValueType::new(a.value as i128, 0).compare(b)
+        }
+        _ => a.as_f64()?.partial_cmp(&b.as_f64()?),
+    }
+}
+
 impl PartialEq for TypeValue {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
-            (
-                &TypeValue::StringTypeValue(StringTypeValue { value: ref a }),
-                &TypeValue::StringTypeValue(StringTypeValue { value: ref b }),
-            ) => a == b,
-            (
-                &TypeValue::NumberValueType(NumberValueType { value: ref a }),
-                &TypeValue::NumberValueType(NumberValueType { value: ref b }),
-            ) => a == b,
             (&TypeValue::NullValueType, &TypeValue::NullValueType) => true,
-            _ => false,
+            _ => self.partial_cmp(other) == Some(Ordering::Equal),
         }
     }
 }
 
+// Values of the same type compare as expected. Numbers compare with numbers of any type, and dates
+// with timestamps as the start of their day. Anything else, and null, does not compare at all.
 impl PartialOrd for TypeValue {
     fn partial_cmp(&self, other: &TypeValue) -> Option<Ordering> {
         match (self, other) {
@@ -69,47 +272,667 @@ impl PartialOrd for TypeValue {
                 &TypeValue::StringTypeValue(StringTypeValue { value: ref a }),
                 &TypeValue::StringTypeValue(StringTypeValue { value: ref b }),
             ) => Some(a.cmp(b)),
-            (
-                &TypeValue::NumberValueType(NumberValueType { value: ref a }),
-                &TypeValue::NumberValueType(NumberValueType { value: ref b }),
-            ) => a.partial_cmp(b),
+            (a, b) if a.is_numeric() && b.is_numeric() => compare_numbers(a, b),
+            (TypeValue::BooleanValueType(a), TypeValue::BooleanValueType(b)) => {
+                Some(a.value.cmp(&b.value))
+            }
+            (TypeValue::BytesValueType(a), TypeValue::BytesValueType(b)) => {
+                Some(a.value.cmp(&b.value))
+            }
+            (TypeValue::DateValueType(a), TypeValue::DateValueType(b)) => Some(a.days.cmp(&b.days)),
+            (TypeValue::TimestampValueType(a), TypeValue::TimestampValueType(b)) => {
+                Some(a.micros.cmp(&b.micros))
+            }
+            // a date too far out to be a timestamp is past every timestamp on its side of 1970
+            (TypeValue::DateValueType(a), TypeValue::TimestampValueType(b)) => Some(
+                a.to_micros()
+                    .map_or(a.days.cmp(&0), |micros| micros.cmp(&b.micros)),
+            ),
+            (TypeValue::TimestampValueType(a), TypeValue::DateValueType(b)) => Some(
+                b.to_micros()
+                    .map_or(0.cmp(&b.days), |micros| a.micros.cmp(&micros)),
+            ),
             _ => None,
         }
     }
 }
 
+// a row as it was before and after an update
+pub type RowUpdate = (HashMap<String, TypeValue>, HashMap<String, TypeValue>);
+
 #[derive(Debug)]
 pub struct Db {
     // should include:
     // remote data -> this is data that is owned by another node, but is queried by this node.
     // local data -> this is data that is owned by this node but might be queried by another node.
 
-    // naive implementation
-    // structure:
-    //     {
-    //         table: [
-    //             {/*row*/}
-    //         ]
-    //     }
-    data: HashMap<String, Vec<HashMap<String, TypeValue>>>,
+    // the tables, stored by column
+    data: HashMap<String, Table>,
+
+    // the schemas of the tables made with CREATE TABLE, rows written into them are checked against
+    // these. Tables that were only ever inserted into have no schema and take any row.
+    schemas: HashMap<String, TableSchema>,
+
+    // materialized views, by the name of the table they are materialized into (the select's `into`).
+    views: HashMap<String, View>,
+
+    // the rows of the views. Views are kept up to date row by row, so they stay rows.
+    view_rows: HashMap<String, Vec<HashMap<String, TypeValue>>>,
+
+    // every change is logged here before it is applied, if the Db is persisted
+    wal: Option<Wal>,
+}
+
+impl Default for Db {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Db {
     pub fn new() -> Self {
         Db {
             data: HashMap::new(),
+            schemas: HashMap::new(),
+            views: HashMap::new(),
+            view_rows: HashMap::new(),
+            wal: None,
+        }
+    }
+
+    // Opens the Db persisted in the log at the path, its state is rebuilt by replaying the log
+    pub fn open<P: AsRef<Path>>(path: P, policy: FsyncPolicy) -> Result<Self, DatabaseError> {
+        Db::restore(path, policy, None)
+    }
+
+    // Rebuilds the Db from a snapshot and the records of the log at the path written after it
+    pub fn restore<P: AsRef<Path>>(
+        path: P,
+        policy: FsyncPolicy,
+        snapshot: Option<Snapshot>,
+    ) -> Result<Self, DatabaseError> {
+        let (mut wal, records) = Wal::open(path, policy)?;
+
+        let mut db = Db::new();
+        let mut snapshot_lsn = 0;
+
+        if let Some(snapshot) = snapshot {
+            db.data = snapshot.data;
+            db.schemas = snapshot.schemas;
+            db.views = snapshot.views;
+            db.view_rows = snapshot.view_rows;
+            snapshot_lsn = snapshot.lsn;
+
+            // the log may have been emptied when the snapshot was taken
+            wal.skip_to(snapshot_lsn + 1);
+        }
+
+        // a crash between writing a snapshot and emptying the log leaves records the snapshot already holds
+        let records: Vec<(Lsn, WalRecord)> = records
+            .into_iter()
+            .filter(|(lsn, _)| *lsn > snapshot_lsn)
+            .collect();
+
+        // the records are not logged again, the log is only attached afterwards
+        for (lsn, record) in records {
+            if let Err(e) = db.apply(record) {
+                eprintln!("Could not replay record {lsn} of the log: {:?}", e);
+            }
+        }
+
+        db.wal = Some(wal);
+
+        Ok(db)
+    }
+
+    // Writes a snapshot of the Db and the selects made against this node into the directory, after
+    // which the log only has to hold the changes made since.
+    pub fn take_snapshot<P: AsRef<Path>>(
+        &mut self,
+        dir: P,
+        select_index: &SelectIndex,
+    ) -> Result<PathBuf, DatabaseError> {
+        let lsn = match self.wal.as_mut() {
+            Some(wal) => {
+                wal.sync()?;
+                wal.last_lsn()
+            }
+            None => 0,
+        };
+
+        let snapshot = Snapshot {
+            lsn,
+            data: self.data.clone(),
+            schemas: self.schemas.clone(),
+            views: self.views.clone(),
+            view_rows: self.view_rows.clone(),
+            selects: select_index.selects().clone(),
+        };
+
+        let dir = dir.as_ref();
+        let path = snapshot.write(dir)?;
+
+        // the log is only cut back once the snapshot is known to read back as it was written
+        if Snapshot::read(&path)?.lsn != lsn {
+            return Err(DatabaseError::CorruptSnapshotError());
+        }
+
+        // the records after the snapshots kept to fall back on stay in the log, so the Db can
+        // still be restored from them when the latest can't be read
+        if let Some(wal) = self.wal.as_mut() {
+            let oldest = Snapshot::oldest_readable_lsn(dir)?.map_or(lsn, |oldest| oldest.min(lsn));
+
+            wal.truncate_through(oldest)?;
+        }
+
+        Ok(path)
+    }
+
+    // the lsn of the last change made to the Db, 0 if it is not persisted
+    pub fn last_lsn(&self) -> Lsn {
+        self.wal.as_ref().map(|wal| wal.last_lsn()).unwrap_or(0)
+    }
+
+    // applies a logged change by calling the method that logged it
+    pub fn apply(&mut self, record: WalRecord) -> Result<(), DatabaseError> {
+        match record {
+            WalRecord::CreateTable(query) => self.create_table(query),
+            WalRecord::CreateIndex(query) => self.create_index(query),
+            WalRecord::Insert(query) => self.insert(query),
+            WalRecord::Update(query) => self.update(&query).map(|_| ()),
+            WalRecord::Delete(query) => self.delete(&query).map(|_| ()),
+            WalRecord::CreateView(query) => self.create_view(query),
+            WalRecord::DropView(query) => self.drop_view(query),
+            WalRecord::InsertIntoView(view, source, rows) => {
+                self.insert_into_view(&view, &source, rows)
+            }
+            WalRecord::ClearViewResults(view, source) => self.clear_view_results(&view, &source),
+            WalRecord::MaterializeInsert(source, query) => {
+                self.materialize_insert(&source, &query).map(|_| ())
+            }
+            WalRecord::MaterializeUpdate(source, table_name, changes) => self
+                .materialize_update(&source, &table_name, &changes)
+                .map(|_| ()),
+            WalRecord::MaterializeDelete(source, table_name, rows) => self
+                .materialize_delete(&source, &table_name, &rows)
+                .map(|_| ()),
+        }
+    }
+
+    fn log(&mut self, record: WalRecord) -> Result<(), DatabaseError> {
+        if let Some(wal) = self.wal.as_mut() {
+            wal.append(&record)?;
+        }
+
+        Ok(())
+    }
+
+    // the rows of a table or a view
+    pub fn get_table(&self, name: &str) -> Option<Vec<HashMap<String, TypeValue>>> {
+        match self.data.get(name) {
+            Some(table) => Some(table.rows().collect()),
+            None => self.view_rows.get(name).cloned(),
+        }
+    }
+
+    pub fn is_view(&self, name: &str) -> bool {
+        self.views.contains_key(name)
+    }
+
+    pub fn get_schema(&self, name: &str) -> Option<&TableSchema> {
+        self.schemas.get(name)
+    }
+
+    // Creates an empty table with a schema. A table that was already inserted into or is a view
+    // can not be given one.
+    pub fn create_table(&mut self, query: CreateTableQuery) -> Result<(), DatabaseError> {
+        if self.schemas.contains_key(&query.table_name)
+            || self.data.contains_key(&query.table_name)
+            || self.views.contains_key(&query.table_name)
+        {
+            if query.if_not_exists {
+                return Ok(());
+            }
+
+            return Err(DatabaseError::TableAlreadyExistsError(query.table_name));
+        }
+
+        self.log(WalRecord::CreateTable(query.clone()))?;
+
+        self.data.insert(query.table_name.clone(), Table::new());
+        self.schemas
+            .insert(query.table_name.clone(), TableSchema::from(query));
+
+        Ok(())
+    }
+
+    // Creates an index on a column of a table, which filtering the table then uses by itself
+    pub fn create_index(&mut self, query: CreateIndexQuery) -> Result<(), DatabaseError> {
+        let exists = self
+            .data
+            .values()
+            .flat_map(|table| table.indexes())
+            .any(|index| index.name == query.name);
+
+        if exists {
+            if query.if_not_exists {
+                return Ok(());
+            }
+
+            return Err(DatabaseError::IndexAlreadyExistsError(query.name));
+        }
+
+        if !self.data.contains_key(&query.table_name) {
+            return Err(DatabaseError::TableNotFoundError(query.table_name));
+        }
+
+        if let Some(schema) = self.schemas.get(&query.table_name) {
+            if schema.column(&query.column).is_none() {
+                return Err(DatabaseError::UnknownColumnError(
+                    query.table_name,
+                    query.column,
+                ));
+            }
+        }
+
+        self.log(WalRecord::CreateIndex(query.clone()))?;
+
+        if let Some(table) = self.data.get_mut(&query.table_name) {
+            table.create_index(TableIndex::new(&query.name, &query.column, query.kind));
+        }
+
+        Ok(())
+    }
+
+    // Creates (or recreates) the view a select is materialized into, seeded with the data this node
+    // owns itself. Data owned by other nodes is added through insert_into_view and materialize_insert.
+    pub fn create_view(&mut self, query: SelectQuery) -> Result<(), DatabaseError> {
+        if query.into == query.from
+            || self.schemas.contains_key(&query.into)
+            || self.data.contains_key(&query.into)
+        {
+            return Err(DatabaseError::TableAlreadyExistsError(query.into));
+        }
+
+        // the rows of a join are qualified, only a select of a single table reads its columns as is
+        if query.join.is_none() {
+            self.check_columns(
+                &query.from,
+                query
+                    .constraints
+                    .iter()
+                    .chain(query.projection.iter().filter_map(|item| match item {
+                        ProjectionItem::Expression(expression, _) => Some(expression),
+                        _ => None,
+                    }))
+                    .chain(
+                        query
+                            .aggregates
+                            .iter()
+                            .filter_map(|aggregate| aggregate.argument.as_ref()),
+                    ),
+            )?;
+        }
+
+        self.log(WalRecord::CreateView(query.clone()))?;
+
+        let rows = self.query_data_for_view(&query)?;
+        let name = query.into.clone();
+
+        let (mut view, mut table) = View::new(query);
+        view.add_results(None, rows, &mut table);
+
+        self.view_rows.insert(name.clone(), table);
+        self.views.insert(name, view);
+
+        Ok(())
+    }
+
+    // drops a view and its rows, the changes to the tables it selected from are not kept anymore
+    pub fn drop_view(&mut self, query: DropViewQuery) -> Result<(), DatabaseError> {
+        if !self.views.contains_key(&query.name) {
+            if query.if_exists {
+                return Ok(());
+            }
+
+            return Err(DatabaseError::ViewNotFoundError(query.name));
+        }
+
+        self.log(WalRecord::DropView(query.clone()))?;
+
+        self.views.remove(&query.name);
+        self.view_rows.remove(&query.name);
+
+        Ok(())
+    }
+
+    // adds rows that already went through the view's select (i.e. results from the node `source`) to the view
+    pub fn insert_into_view(
+        &mut self,
+        view: &str,
+        source: &str,
+        rows: Vec<HashMap<String, TypeValue>>,
+    ) -> Result<(), DatabaseError> {
+        if !self.views.contains_key(view) {
+            return Err(DatabaseError::ViewNotFoundError(view.to_string()));
+        }
+
+        self.log(WalRecord::InsertIntoView(
+            view.to_string(),
+            source.to_string(),
+            rows.clone(),
+        ))?;
+
+        match (self.views.get_mut(view), self.view_rows.get_mut(view)) {
+            (Some(v), Some(table)) => {
+                v.add_results(Some(source), rows, table);
+                Ok(())
+            }
+            _ => Err(DatabaseError::ViewNotFoundError(view.to_string())),
+        }
+    }
+
+    // Takes every row the node `source` gave the view back out of it, the results and the changes
+    // it pushed alike. Done before a node answers a select again, so its rows aren't in the view twice.
+    pub fn clear_view_results(&mut self, view: &str, source: &str) -> Result<(), DatabaseError> {
+        if !self.views.contains_key(view) {
+            return Err(DatabaseError::ViewNotFoundError(view.to_string()));
+        }
+
+        self.log(WalRecord::ClearViewResults(
+            view.to_string(),
+            source.to_string(),
+        ))?;
+
+        match (self.views.get_mut(view), self.view_rows.get_mut(view)) {
+            (Some(v), Some(table)) => {
+                v.clear(source, table);
+                Ok(())
+            }
+            _ => Err(DatabaseError::ViewNotFoundError(view.to_string())),
+        }
+    }
+
+    // Appends the rows of an insert to every view selecting from the inserted table, if they match the
+    // view's constraints. This is how inserts pushed to us by the owners of a table keep views up to date,
+    // `source` is the node that pushed them. Returns the names of the views that changed.
+    pub fn materialize_insert(
+        &mut self,
+        source: &str,
+        query: &InsertQuery,
+    ) -> Result<Vec<String>, DatabaseError> {
+        self.log(WalRecord::MaterializeInsert(
+            source.to_string(),
+            query.clone(),
+        ))?;
+
+        Ok(self.materialize_inserted(Some(source), query))
+    }
+
+    fn materialize_inserted(&mut self, source: Option<&str>, query: &InsertQuery) -> Vec<String> {
+        let rows: Vec<HashMap<String, TypeValue>> = query
+            .rows
+            .iter()
+            .map(|row| convert_row_to_hashmap(&query.columns, row))
+            .collect();
+
+        self.materialize(&query.table_name, |view, table| {
+            view.apply_insert(source, &query.table_name, &rows, table)
+        })
+    }
+
+    // Same as materialize_insert, for rows of a table that were updated from the first row of each pair to the second
+    pub fn materialize_update(
+        &mut self,
+        source: &str,
+        table_name: &str,
+        changes: &[RowUpdate],
+    ) -> Result<Vec<String>, DatabaseError> {
+        self.log(WalRecord::MaterializeUpdate(
+            source.to_string(),
+            table_name.to_string(),
+            changes.to_vec(),
+        ))?;
+
+        Ok(self.materialize(table_name, |view, table| {
+            view.apply_update(Some(source), table_name, changes, table)
+        }))
+    }
+
+    // Same as materialize_insert, for rows that were deleted from a table
+    pub fn materialize_delete(
+        &mut self,
+        source: &str,
+        table_name: &str,
+        rows: &[HashMap<String, TypeValue>],
+    ) -> Result<Vec<String>, DatabaseError> {
+        self.log(WalRecord::MaterializeDelete(
+            source.to_string(),
+            table_name.to_string(),
+            rows.to_vec(),
+        ))?;
+
+        Ok(self.materialize(table_name, |view, table| {
+            view.apply_delete(Some(source), table_name, rows, table)
+        }))
+    }
+
+    // applies a change of a table to every view selecting from it, returns the names of the views that changed
+    fn materialize<F>(&mut self, table_name: &str, mut apply: F) -> Vec<String>
+    where
+        F: FnMut(&mut View, &mut Vec<HashMap<String, TypeValue>>) -> bool,
+    {
+        let mut updated = vec![];
+
+        for (name, view) in self.views.iter_mut() {
+            if !view.query.relations().contains(&table_name) {
+                continue;
+            }
+
+            let table = self.view_rows.entry(name.clone()).or_default();
+
+            if apply(view, table) {
+                updated.push(name.clone());
+            }
+        }
+
+        updated
+    }
+
+    // the result of a select over this node's data: the (joined) rows that match the select's
+    // constraints projected onto the select's columns, or the select's groups if it aggregates.
+    // A table this node holds no data for just has no results.
+    pub fn query_data_by_select(
+        &self,
+        query: &SelectQuery,
+    ) -> Result<Vec<HashMap<String, TypeValue>>, DatabaseError> {
+        let (mut view, mut table) = View::new(query.clone());
+        view.add_results(None, self.query_data_for_view(query)?, &mut table);
+
+        Ok(table)
+    }
+
+    // What this node sends to the node materializing the select. Aggregates are computed by the view
+    // itself over the rows of every node, so an aggregating select gets the whole matching rows.
+    // Joins are too, a joining select gets every row of both relations qualified by their relation.
+    pub fn query_data_for_view(
+        &self,
+        query: &SelectQuery,
+    ) -> Result<Vec<HashMap<String, TypeValue>>, DatabaseError> {
+        if query.join.is_some() {
+            return Ok(query
+                .relations()
+                .into_iter()
+                .flat_map(|relation| {
+                    self.data
+                        .get(relation)
+                        .into_iter()
+                        .flat_map(|table| table.rows())
+                        .map(move |row| qualify_row(relation, &row))
+                })
+                .collect());
+        }
+
+        let table = match self.data.get(&query.from) {
+            Some(table) => table,
+            None => return Ok(vec![]),
+        };
+
+        let rows = filter(&query.from, table, query.constraints.as_ref())?.into_iter();
+
+        if query.is_aggregate() {
+            Ok(rows.map(|i| table.row(i)).collect())
+        } else {
+            Ok(rows.map(|i| table.project(i, &query.projection)).collect())
+        }
+    }
+
+    // Updates the rows of a table matching the update's constraints, returns every updated row as it was
+    // before and after the update. Nothing is updated if any of the rows can not be.
+    pub fn update(&mut self, query: &UpdateQuery) -> Result<Vec<RowUpdate>, DatabaseError> {
+        if self.views.contains_key(&query.table_name) {
+            return Err(DatabaseError::ViewIsReadOnlyError(query.table_name.clone()));
+        }
+
+        self.check_columns(
+            &query.table_name,
+            query
+                .constraints
+                .iter()
+                .chain(query.assignments.iter().map(|(_, expression)| expression)),
+        )?;
+
+        let mut updates = vec![];
+
+        if let Some(table) = self.data.get(&query.table_name) {
+            for i in filter(&query.table_name, table, query.constraints.as_ref())? {
+                let row = table.row(i);
+
+                match query.apply(&row) {
+                    Ok(updated) => updates.push((i, row, updated)),
+                    Err(e) => return Err(DatabaseError::UpdateError(query.table_name.clone(), e)),
+                }
+            }
+        }
+
+        if updates.is_empty() {
+            return Ok(vec![]);
+        }
+
+        if let Some(schema) = self.schemas.get(&query.table_name) {
+            for (_, _, updated) in updates.iter_mut() {
+                schema.coerce_row(updated);
+                schema.validate(updated)?;
+            }
+        }
+
+        self.log(WalRecord::Update(query.clone()))?;
+
+        let table = self.data.entry(query.table_name.clone()).or_default();
+
+        let changes: Vec<RowUpdate> = updates
+            .into_iter()
+            .map(|(i, row, updated)| {
+                table.set_row(i, &updated);
+                (row, updated)
+            })
+            .collect();
+
+        self.materialize(&query.table_name, |view, table| {
+            view.apply_update(None, &query.table_name, &changes, table)
+        });
+
+        Ok(changes)
+    }
+
+    // Checks that the expressions on a table with a schema only read its columns, so a misspelled
+    // column is an error rather than a null. Tables without a schema take any column.
+    fn check_columns<'a>(
+        &self,
+        table_name: &str,
+        expressions: impl Iterator<Item = &'a Expression>,
+    ) -> Result<(), DatabaseError> {
+        if let Some(schema) = self.schemas.get(table_name) {
+            for expression in expressions {
+                expression.check_columns(|column| schema.column(column).is_some())?;
+            }
         }
+
+        Ok(())
     }
 
-    pub fn query_data_by_select(_query: SelectQuery) -> Vec<HashMap<String, TypeValue>> {
-        // this shoud return a result from the DB depending on whether or not the query resulted in a data structure
-        vec![]
+    // deletes the rows of a table matching the delete's constraints, returns the deleted rows
+    pub fn delete(
+        &mut self,
+        query: &DeleteQuery,
+    ) -> Result<Vec<HashMap<String, TypeValue>>, DatabaseError> {
+        if self.views.contains_key(&query.table_name) {
+            return Err(DatabaseError::ViewIsReadOnlyError(query.table_name.clone()));
+        }
+
+        self.check_columns(&query.table_name, query.constraints.iter())?;
+
+        // nothing is logged for a delete whose constraints can't be evaluated
+        let positions = match self.data.get(&query.table_name) {
+            Some(table) => filter(&query.table_name, table, query.constraints.as_ref())?,
+            None => vec![],
+        };
+
+        self.log(WalRecord::Delete(query.clone()))?;
+
+        let table = match self.data.get_mut(&query.table_name) {
+            Some(table) => table,
+            None => return Ok(vec![]),
+        };
+
+        let deleted = table.remove(&positions);
+
+        self.materialize(&query.table_name, |view, table| {
+            view.apply_delete(None, &query.table_name, &deleted, table)
+        });
+
+        Ok(deleted)
     }
 
-    pub fn insert(&mut self, query: InsertQuery) -> Result<(), DatabaseError> {
+    pub fn insert(&mut self, mut query: InsertQuery) -> Result<(), DatabaseError> {
         // pub table_name: String,
         // pub columns: Vec<String>,
         // pub rows: Vec<Vec<String>>,
+        if self.views.contains_key(&query.table_name) {
+            return Err(DatabaseError::ViewIsReadOnlyError(query.table_name));
+        }
+
+        // every row is checked before anything is inserted, so an insert goes in whole or not at all
+        if let Some(schema) = self.schemas.get(&query.table_name) {
+            // `insert into t values (..)` fills the columns in the order they were created in
+            if query.columns.is_empty() {
+                query.columns = schema.column_names();
+            }
+
+            for row in query.rows.iter_mut() {
+                if row.len() != query.columns.len() {
+                    return Err(DatabaseError::ColumnCountError(
+                        query.table_name,
+                        query.columns.len(),
+                        row.len(),
+                    ));
+                }
+
+                for (column, value) in query.columns.iter().zip(row.iter_mut()) {
+                    if let (Some(definition), Some(v)) = (schema.column(column), value.take()) {
+                        *value = Some(coerce(v, definition.column_type));
+                    }
+                }
+
+                schema.validate(&convert_row_to_hashmap(&query.columns, row))?;
+            }
+        }
+
+        self.log(WalRecord::Insert(query.clone()))?;
+
+        // views over our own data are kept up to date the same way as views over remote data
+        self.materialize_inserted(None, &query);
+
         match self.data.get_mut(&query.table_name) {
             Some(table) => {
                 match insert_rows_into_table(table, &query.columns, &query.rows) {
@@ -123,7 +946,7 @@ impl Db {
                 Ok(())
             }
             None => {
-                let mut table: Vec<HashMap<String, TypeValue>> = vec![];
+                let mut table = Table::new();
 
                 match insert_rows_into_table(&mut table, &query.columns, &query.rows) {
                     Ok(_) => {
@@ -142,47 +965,97 @@ impl Db {
     }
 }
 
+// the positions of the rows of the table matching the constraints
+fn filter(
+    table_name: &str,
+    table: &Table,
+    constraints: Option<&Expression>,
+) -> Result<Vec<usize>, DatabaseError> {
+    table
+        .filter(constraints)
+        .map_err(|e| DatabaseError::ConstraintError(table_name.to_string(), e))
+}
+
 pub fn insert_rows_into_table(
-    table: &mut Vec<HashMap<String, TypeValue>>,
-    columns: &Vec<String>,
-    rows: &Vec<Vec<Option<TypeValue>>>,
+    table: &mut Table,
+    columns: &[String],
+    rows: &[Vec<Option<TypeValue>>],
 ) -> Result<(), DatabaseError> {
     for row in rows.iter() {
-        let new_row = convert_row_to_hashmap(columns, row);
-
-        table.push(new_row);
+        table.push_values(columns, row);
     }
 
     Ok(())
 }
 
-pub fn convert_row_to_hashmap(
-    columns: &Vec<String>,
-    row: &Vec<Option<TypeValue>>,
+// The row as the projection materializes it: wildcards keep the columns they cover as they are,
+// expressions are evaluated into their aliases. Expressions that can not be evaluated, like ones
+// reading columns missing from the row, are null.
+pub fn project_row(
+    projection: &[ProjectionItem],
+    row: &HashMap<String, TypeValue>,
 ) -> HashMap<String, TypeValue> {
-    let mut hmap = HashMap::new();
+    let mut projected = HashMap::new();
 
-    for (i, column_name) in columns.iter().enumerate() {
-        if let Some(tv) = row.get(i) {
-            if let Some(v) = tv.as_ref() {
-                hmap.insert(column_name.clone(), v.clone());
-            } else {
-                hmap.insert(column_name.clone(), TypeValue::NullValueType);
+    for item in projection.iter() {
+        match item {
+            ProjectionItem::Wildcard => {
+                projected.extend(row.iter().map(|(k, v)| (k.clone(), v.clone())))
             }
-        }
-    }
+            // the columns of a joined row are qualified by their relation
+            ProjectionItem::QualifiedWildcard(relation) => {
+                let prefix = format!("{relation}.");
 
-    hmap
+                projected.extend(
+                    row.iter()
+                        .filter(|(column, _)| column.starts_with(&prefix))
+                        .map(|(k, v)| (k.clone(), v.clone())),
+                )
+            }
+            ProjectionItem::Expression(expression, alias) => {
+                projected.insert(
+                    alias.clone(),
+                    expression.resolve(row).unwrap_or(TypeValue::NullValueType),
+                );
+            }
+        }
+    }
+
+    projected
+}
+
+pub fn convert_row_to_hashmap(
+    columns: &[String],
+    row: &[Option<TypeValue>],
+) -> HashMap<String, TypeValue> {
+    let mut hmap = HashMap::new();
+
+    for (i, column_name) in columns.iter().enumerate() {
+        if let Some(tv) = row.get(i) {
+            if let Some(v) = tv.as_ref() {
+                hmap.insert(column_name.clone(), v.clone());
+            } else {
+                hmap.insert(column_name.clone(), TypeValue::NullValueType);
+            }
+        }
+    }
+
+    hmap
 }
 
 #[cfg(test)]
 mod tests {
     // Note this useful idiom: importing names from outer (for mod tests) scope.
     use super::*;
+    use crate::models::create_table_query::ColumnType;
+    use crate::models::errors::StatementError;
+    use sqlparser::ast::Statement::Query;
+    use sqlparser::dialect::GenericDialect;
+    use sqlparser::parser::Parser;
 
     #[test]
     fn insert_rows_into_table_test() {
-        let mut table: Vec<HashMap<String, TypeValue>> = vec![];
+        let mut table = Table::new();
         let columns = vec![
             "id".to_string(),
             "first_name".to_string(),
@@ -198,10 +1071,10 @@ mod tests {
             })),
         ]];
 
-        insert_rows_into_table(&mut table, &columns, &rows);
+        insert_rows_into_table(&mut table, &columns, &rows).expect("Could not insert the rows");
 
         assert_eq!(
-            table,
+            table.rows().collect::<Vec<_>>(),
             vec![HashMap::from([
                 (
                     "id".to_string(),
@@ -264,4 +1137,1468 @@ mod tests {
             ])
         );
     }
+
+    fn customer_db() -> Db {
+        let mut db = Db::new();
+
+        db.insert(InsertQuery {
+            table_name: "customer".to_string(),
+            columns: vec!["id".to_string(), "first_name".to_string()],
+            rows: vec![
+                vec![
+                    Some(TypeValue::NumberValueType(NumberValueType { value: 1.0 })),
+                    Some(TypeValue::StringTypeValue(StringTypeValue {
+                        value: "Cameron".to_string(),
+                    })),
+                ],
+                vec![
+                    Some(TypeValue::NumberValueType(NumberValueType { value: 2.0 })),
+                    Some(TypeValue::StringTypeValue(StringTypeValue {
+                        value: "Harris".to_string(),
+                    })),
+                ],
+            ],
+        })
+        .expect("Could not insert");
+
+        db
+    }
+
+    fn parse_select(sql: &str) -> SelectQuery {
+        let dialect = GenericDialect {};
+
+        let ast = Parser::parse_sql(&dialect, sql).expect("Error with parsing the sql");
+
+        match ast.first() {
+            Some(Query(query)) => {
+                SelectQuery::try_from(&*query.body).expect("Error with getting the Statement")
+            }
+            _ => panic!("No Select Statement found."),
+        }
+    }
+
+    #[test]
+    fn query_data_by_select_filters_and_projects() {
+        let db = customer_db();
+
+        assert_eq!(
+            db.query_data_by_select(&parse_select(
+                "select first_name, last_name into c from customer where id > 1;"
+            ))
+            .expect("Could not query"),
+            vec![HashMap::from([
+                (
+                    "first_name".to_string(),
+                    TypeValue::StringTypeValue(StringTypeValue {
+                        value: "Harris".to_string()
+                    })
+                ),
+                ("last_name".to_string(), TypeValue::NullValueType)
+            ])]
+        );
+    }
+
+    #[test]
+    fn query_data_by_select_wildcard() {
+        let db = customer_db();
+
+        assert_eq!(
+            db.query_data_by_select(&parse_select("select * into c from customer;"))
+                .expect("Could not query"),
+            db.get_table("customer").expect("No customer table")
+        );
+        assert_eq!(
+            db.query_data_by_select(&parse_select("select * into c from orders;"))
+                .expect("Could not query"),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn create_view_is_seeded_with_local_data() {
+        let mut db = customer_db();
+
+        db.create_view(parse_select(
+            "select first_name into cache from customer where id = 1;",
+        ))
+        .expect("Could not create view");
+
+        assert!(db.is_view("cache"));
+        assert_eq!(
+            db.get_table("cache"),
+            Some(vec![HashMap::from([(
+                "first_name".to_string(),
+                TypeValue::StringTypeValue(StringTypeValue {
+                    value: "Cameron".to_string()
+                })
+            )])])
+        );
+
+        assert!(matches!(
+            db.create_view(parse_select("select * into customer from orders;")),
+            Err(DatabaseError::TableAlreadyExistsError(_))
+        ));
+    }
+
+    #[test]
+    fn view_is_maintained_incrementally() {
+        let mut db = customer_db();
+
+        db.create_view(parse_select(
+            "select id into cache from orders where id > 1;",
+        ))
+        .expect("Could not create view");
+
+        let insert = InsertQuery {
+            table_name: "orders".to_string(),
+            columns: vec!["id".to_string()],
+            rows: vec![
+                vec![Some(TypeValue::NumberValueType(NumberValueType {
+                    value: 1.0,
+                }))],
+                vec![Some(TypeValue::NumberValueType(NumberValueType {
+                    value: 2.0,
+                }))],
+            ],
+        };
+
+        // pushed by the node owning `orders`
+        assert_eq!(
+            db.materialize_insert("peer", &insert)
+                .expect("Could not materialize"),
+            vec!["cache".to_string()]
+        );
+
+        // results for the initial select from another node
+        db.insert_into_view(
+            "cache",
+            "peer",
+            vec![HashMap::from([(
+                "id".to_string(),
+                TypeValue::NumberValueType(NumberValueType { value: 5.0 }),
+            )])],
+        )
+        .expect("Could not insert into view");
+
+        // and an insert into orders on this node
+        db.insert(InsertQuery {
+            table_name: "orders".to_string(),
+            columns: vec!["id".to_string()],
+            rows: vec![vec![Some(TypeValue::NumberValueType(NumberValueType {
+                value: 3.0,
+            }))]],
+        })
+        .expect("Could not insert");
+
+        let ids: Vec<TypeValue> = db
+            .get_table("cache")
+            .unwrap()
+            .iter()
+            .map(|row| row["id"].clone())
+            .collect();
+
+        assert_eq!(
+            ids,
+            vec![
+                TypeValue::NumberValueType(NumberValueType { value: 2.0 }),
+                TypeValue::NumberValueType(NumberValueType { value: 5.0 }),
+                TypeValue::NumberValueType(NumberValueType { value: 3.0 }),
+            ]
+        );
+
+        assert!(matches!(
+            db.insert(InsertQuery {
+                table_name: "cache".to_string(),
+                ..insert
+            }),
+            Err(DatabaseError::ViewIsReadOnlyError(_))
+        ));
+    }
+
+    fn number(value: f64) -> TypeValue {
+        TypeValue::NumberValueType(NumberValueType { value })
+    }
+
+    fn string(value: &str) -> TypeValue {
+        TypeValue::StringTypeValue(StringTypeValue {
+            value: value.to_string(),
+        })
+    }
+
+    fn order_insert(rows: Vec<(&str, f64)>) -> InsertQuery {
+        InsertQuery {
+            table_name: "orders".to_string(),
+            columns: vec!["region".to_string(), "price".to_string()],
+            rows: rows
+                .into_iter()
+                .map(|(region, price)| vec![Some(string(region)), Some(number(price))])
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn aggregate_view_is_maintained_per_group() {
+        let mut db = Db::new();
+
+        db.insert(order_insert(vec![("eu", 10.0), ("us", 5.0)]))
+            .expect("Could not insert");
+
+        db.create_view(parse_select(
+            "select region, count(*) as n, sum(price) as total, avg(price) as mean, min(price) as low, \
+             max(price) as high into totals from orders where price > 1 group by region;",
+        ))
+        .expect("Could not create view");
+
+        db.materialize_insert("peer", &order_insert(vec![("eu", 20.0), ("eu", 0.5)]))
+            .expect("Could not materialize");
+
+        // the raw rows another node answers the select with
+        db.insert_into_view(
+            "totals",
+            "peer",
+            vec![HashMap::from([
+                ("region".to_string(), string("us")),
+                ("price".to_string(), number(7.0)),
+            ])],
+        )
+        .expect("Could not insert into view");
+
+        assert_eq!(
+            db.get_table("totals"),
+            Some(vec![
+                HashMap::from([
+                    ("region".to_string(), string("eu")),
+                    ("n".to_string(), number(2.0)),
+                    ("total".to_string(), number(30.0)),
+                    ("mean".to_string(), number(15.0)),
+                    ("low".to_string(), number(10.0)),
+                    ("high".to_string(), number(20.0)),
+                ]),
+                HashMap::from([
+                    ("region".to_string(), string("us")),
+                    ("n".to_string(), number(2.0)),
+                    ("total".to_string(), number(12.0)),
+                    ("mean".to_string(), number(6.0)),
+                    ("low".to_string(), number(5.0)),
+                    ("high".to_string(), number(7.0)),
+                ]),
+            ])
+        );
+    }
+
+    #[test]
+    fn answering_a_select_again_replaces_the_rows_of_the_node() {
+        let mut db = Db::new();
+
+        for sql in [
+            "select region, price into plain from orders;",
+            "select region, count(*) as n, sum(price) as total into totals from orders group by region;",
+            "select first_name, orders.price into order_names from orders join customer \
+             on orders.customer_id = customer.id;",
+        ] {
+            db.create_view(parse_select(sql))
+                .expect("Could not create view");
+        }
+
+        let order = |region: &str, price: f64| {
+            HashMap::from([
+                ("region".to_string(), string(region)),
+                ("price".to_string(), number(price)),
+            ])
+        };
+
+        let joined = vec![
+            HashMap::from([
+                ("customer.id".to_string(), number(1.0)),
+                ("customer.first_name".to_string(), string("Cameron")),
+            ]),
+            HashMap::from([
+                ("orders.customer_id".to_string(), number(1.0)),
+                ("orders.price".to_string(), number(10.0)),
+            ]),
+        ];
+
+        db.insert_into_view("plain", "other", vec![order("us", 5.0)])
+            .expect("Could not insert into view");
+
+        // the first answer of the peer, a change it pushed after and its answer after a reconnect
+        for view in ["plain", "totals"] {
+            db.clear_view_results(view, "peer")
+                .expect("Could not clear the view");
+            db.insert_into_view(view, "peer", vec![order("eu", 10.0)])
+                .expect("Could not insert into view");
+        }
+        db.clear_view_results("order_names", "peer")
+            .expect("Could not clear the view");
+        db.insert_into_view("order_names", "peer", joined.clone())
+            .expect("Could not insert into view");
+
+        db.materialize_insert("peer", &order_insert(vec![("eu", 20.0)]))
+            .expect("Could not materialize");
+
+        for view in ["plain", "totals"] {
+            db.clear_view_results(view, "peer")
+                .expect("Could not clear the view");
+            db.insert_into_view(view, "peer", vec![order("eu", 10.0), order("eu", 20.0)])
+                .expect("Could not insert into view");
+        }
+        db.clear_view_results("order_names", "peer")
+            .expect("Could not clear the view");
+        db.insert_into_view("order_names", "peer", joined)
+            .expect("Could not insert into view");
+
+        assert_eq!(
+            db.get_table("plain"),
+            Some(vec![order("us", 5.0), order("eu", 10.0), order("eu", 20.0)])
+        );
+        assert_eq!(
+            db.get_table("totals"),
+            Some(vec![HashMap::from([
+                ("region".to_string(), string("eu")),
+                ("n".to_string(), number(2.0)),
+                ("total".to_string(), number(30.0)),
+            ])])
+        );
+        assert_eq!(
+            db.get_table("order_names"),
+            Some(vec![HashMap::from([
+                ("first_name".to_string(), string("Cameron")),
+                ("orders.price".to_string(), number(10.0)),
+            ])])
+        );
+    }
+
+    #[test]
+    fn dropped_views_are_gone() {
+        let mut db = Db::new();
+
+        db.create_view(parse_select("select region into regions from orders;"))
+            .expect("Could not create view");
+
+        let drop_view = |sql: &str| {
+            DropViewQuery::try_from(&parse_statement(sql)).expect("Could not parse the drop")
+        };
+
+        db.drop_view(drop_view("drop view regions;"))
+            .expect("Could not drop the view");
+
+        assert!(!db.is_view("regions"));
+        assert_eq!(db.get_table("regions"), None);
+
+        // the changes to the table don't go anywhere anymore
+        assert!(db
+            .materialize_insert("peer", &order_insert(vec![("eu", 10.0)]))
+            .expect("Could not materialize")
+            .is_empty());
+
+        assert!(matches!(
+            db.drop_view(drop_view("drop view regions;")),
+            Err(DatabaseError::ViewNotFoundError(_))
+        ));
+        db.drop_view(drop_view("drop view if exists regions;"))
+            .expect("Could not drop the view");
+    }
+
+    #[test]
+    fn aggregate_without_group_by_has_one_row() {
+        let mut db = Db::new();
+
+        let query = parse_select("select count(*), sum(price) into totals from orders;");
+
+        assert_eq!(
+            db.query_data_by_select(&query).expect("Could not query"),
+            vec![HashMap::from([
+                ("count(*)".to_string(), number(0.0)),
+                ("sum(price)".to_string(), TypeValue::NullValueType),
+            ])]
+        );
+
+        db.create_view(query).expect("Could not create view");
+        db.insert(order_insert(vec![("eu", 10.0), ("us", 5.0)]))
+            .expect("Could not insert");
+
+        assert_eq!(
+            db.get_table("totals"),
+            Some(vec![HashMap::from([
+                ("count(*)".to_string(), number(2.0)),
+                ("sum(price)".to_string(), number(15.0)),
+            ])])
+        );
+    }
+
+    #[test]
+    fn aggregates_of_integers_and_decimals_stay_exact() {
+        let mut db = Db::new();
+
+        db.create_view(parse_select(
+            "select count(*) as n, sum(qty) as qty, sum(price) as total, avg(price) as mean \
+             into totals from items;",
+        ))
+        .expect("Could not create view");
+
+        // 2^53 + 1 is the first integer a float can't hold
+        db.insert(
+            InsertQuery::try_from(&parse_statement(
+                "insert into items (qty, price) values (9007199254740993, 0.10), (1, 0.25);",
+            ))
+            .expect("Could not parse the insert"),
+        )
+        .expect("Could not insert");
+
+        let row = db.get_table("totals").expect("No view")[0].clone();
+
+        assert!(matches!(
+            row["n"],
+            TypeValue::IntegerValueType(IntegerValueType { value: 2 })
+        ));
+        assert!(matches!(
+            row["qty"],
+            TypeValue::IntegerValueType(IntegerValueType {
+                value: 9007199254740994
+            })
+        ));
+        assert_eq!(row["total"].to_text(), Some("0.35".to_string()));
+        assert_eq!(row["mean"].to_text(), Some("0.175000".to_string()));
+
+        db.delete(
+            &DeleteQuery::try_from(&parse_statement("delete from items where qty = 1;"))
+                .expect("Could not parse the delete"),
+        )
+        .expect("Could not delete");
+
+        let row = db.get_table("totals").expect("No view")[0].clone();
+
+        assert!(matches!(
+            row["n"],
+            TypeValue::IntegerValueType(IntegerValueType { value: 1 })
+        ));
+        assert_eq!(row["total"].to_text(), Some("0.10".to_string()));
+    }
+
+    #[test]
+    fn aggregate_columns_must_be_grouped() {
+        let dialect = GenericDialect {};
+
+        let ast = Parser::parse_sql(&dialect, "select region, count(*) into totals from orders;")
+            .expect("Error with parsing the sql");
+
+        match ast.first() {
+            Some(Query(query)) => assert!(matches!(
+                SelectQuery::try_from(&*query.body),
+                Err(crate::models::errors::StatementError::ColumnNotGroupedError(_))
+            )),
+            _ => panic!("No Select Statement found."),
+        }
+    }
+
+    #[test]
+    fn join_view_is_maintained_from_both_sides() {
+        let mut db = customer_db();
+
+        db.insert(InsertQuery {
+            table_name: "orders".to_string(),
+            columns: vec!["customer_id".to_string(), "price".to_string()],
+            rows: vec![vec![Some(number(1.0)), Some(number(10.0))]],
+        })
+        .expect("Could not insert");
+
+        db.create_view(parse_select(
+            "select first_name, orders.price into order_names from orders join customer \
+             on orders.customer_id = customer.id where orders.price > 5;",
+        ))
+        .expect("Could not create view");
+
+        // an order for a customer we already have, and one that is too cheap
+        db.materialize_insert(
+            "peer",
+            &InsertQuery {
+                table_name: "orders".to_string(),
+                columns: vec!["customer_id".to_string(), "price".to_string()],
+                rows: vec![
+                    vec![Some(number(2.0)), Some(number(20.0))],
+                    vec![Some(number(2.0)), Some(number(1.0))],
+                    vec![Some(number(3.0)), Some(number(30.0))],
+                ],
+            },
+        )
+        .expect("Could not materialize");
+
+        // and the customer the last order was waiting for, as a result from another node
+        db.insert_into_view(
+            "order_names",
+            "peer",
+            vec![HashMap::from([
+                ("customer.id".to_string(), number(3.0)),
+                ("customer.first_name".to_string(), string("Pi")),
+            ])],
+        )
+        .expect("Could not insert into view");
+
+        let order_name = |name: &str, price: f64| {
+            HashMap::from([
+                ("first_name".to_string(), string(name)),
+                ("orders.price".to_string(), number(price)),
+            ])
+        };
+
+        assert_eq!(
+            db.get_table("order_names"),
+            Some(vec![
+                order_name("Cameron", 10.0),
+                order_name("Harris", 20.0),
+                order_name("Pi", 30.0),
+            ])
+        );
+    }
+
+    #[test]
+    fn aliased_joins_are_rejected_rather_than_left_empty() {
+        let select = |sql: &str| match parse_statement(sql) {
+            Query(query) => SelectQuery::try_from(&*query.body),
+            _ => panic!("No Select Statement found."),
+        };
+
+        assert_eq!(
+            select(
+                "select c.first_name, o.price into order_names from orders o join customer c \
+                 on o.customer_id = c.id;"
+            ),
+            Err(
+                crate::models::errors::StatementError::AliasNotSupportedError("orders".to_string())
+            )
+        );
+
+        // and so are columns of the where clause that aren't qualified by their relation
+        assert_eq!(
+            select(
+                "select first_name, price into order_names from orders join customer \
+                 on orders.customer_id = customer.id where price > 10;"
+            ),
+            Err(crate::models::errors::StatementError::UnqualifiedColumnError("price".to_string()))
+        );
+
+        // the same join by the names of the relations materializes the rows of both sides
+        let mut db = customer_db();
+
+        db.insert(InsertQuery {
+            table_name: "orders".to_string(),
+            columns: vec!["customer_id".to_string(), "price".to_string()],
+            rows: vec![
+                vec![Some(number(2.0)), Some(number(20.0))],
+                vec![Some(number(1.0)), Some(number(10.0))],
+                vec![Some(number(4.0)), Some(number(40.0))],
+            ],
+        })
+        .expect("Could not insert");
+
+        db.create_view(
+            select(
+                "select customer.first_name, orders.price into order_names from orders \
+                 join customer on orders.customer_id = customer.id;",
+            )
+            .expect("Could not parse the select"),
+        )
+        .expect("Could not create view");
+
+        let mut rows = db.get_table("order_names").expect("No view");
+        rows.sort_by(|a, b| {
+            a["orders.price"]
+                .partial_cmp(&b["orders.price"])
+                .expect("Prices are comparable")
+        });
+
+        assert_eq!(
+            rows,
+            vec![
+                HashMap::from([
+                    ("customer.first_name".to_string(), string("Cameron")),
+                    ("orders.price".to_string(), number(10.0)),
+                ]),
+                HashMap::from([
+                    ("customer.first_name".to_string(), string("Harris")),
+                    ("orders.price".to_string(), number(20.0)),
+                ]),
+            ]
+        );
+    }
+
+    fn parse_statement(sql: &str) -> sqlparser::ast::Statement {
+        let dialect = GenericDialect {};
+
+        Parser::parse_sql(&dialect, sql)
+            .expect("Error with parsing the sql")
+            .remove(0)
+    }
+
+    #[test]
+    fn update_and_delete_are_retracted_from_views() {
+        let mut db = Db::new();
+
+        db.insert(order_insert(vec![("eu", 10.0), ("us", 5.0), ("us", 7.0)]))
+            .expect("Could not insert");
+
+        db.create_view(parse_select(
+            "select region, price into expensive from orders where price > 6;",
+        ))
+        .expect("Could not create view");
+        db.create_view(parse_select(
+            "select region, count(*) as n, max(price) as high into totals from orders group by region;",
+        ))
+        .expect("Could not create view");
+
+        let update = UpdateQuery::try_from(&parse_statement(
+            "update orders set price = 1 where region = 'eu';",
+        ))
+        .expect("Could not parse the update");
+
+        let changes = db.update(&update).expect("Could not update");
+
+        assert_eq!(
+            changes,
+            vec![(
+                HashMap::from([
+                    ("region".to_string(), string("eu")),
+                    ("price".to_string(), number(10.0)),
+                ]),
+                HashMap::from([
+                    ("region".to_string(), string("eu")),
+                    ("price".to_string(), number(1.0)),
+                ]),
+            )]
+        );
+
+        let delete = DeleteQuery::try_from(&parse_statement(
+            "delete from orders where region = 'us' and price = 7;",
+        ))
+        .expect("Could not parse the delete");
+
+        assert_eq!(db.delete(&delete).expect("Could not delete").len(), 1);
+
+        assert_eq!(db.get_table("expensive"), Some(vec![]));
+        assert_eq!(
+            db.get_table("totals"),
+            // the update emptied the eu group before adding to it again
+            Some(vec![
+                HashMap::from([
+                    ("region".to_string(), string("us")),
+                    ("n".to_string(), number(1.0)),
+                    ("high".to_string(), number(5.0)),
+                ]),
+                HashMap::from([
+                    ("region".to_string(), string("eu")),
+                    ("n".to_string(), number(1.0)),
+                    ("high".to_string(), number(1.0)),
+                ]),
+            ])
+        );
+
+        // a group without rows is gone
+        db.delete(&DeleteQuery {
+            table_name: "orders".to_string(),
+            constraints: None,
+        })
+        .expect("Could not delete");
+
+        assert_eq!(db.get_table("totals"), Some(vec![]));
+        assert_eq!(db.get_table("orders"), Some(vec![]));
+    }
+
+    #[test]
+    fn deleted_rows_leave_joins() {
+        let mut db = customer_db();
+
+        db.insert(InsertQuery {
+            table_name: "orders".to_string(),
+            columns: vec!["customer_id".to_string(), "price".to_string()],
+            rows: vec![
+                vec![Some(number(1.0)), Some(number(10.0))],
+                vec![Some(number(2.0)), Some(number(20.0))],
+            ],
+        })
+        .expect("Could not insert");
+
+        db.create_view(parse_select(
+            "select first_name, price into order_names from orders join customer \
+             on orders.customer_id = customer.id;",
+        ))
+        .expect("Could not create view");
+
+        // pushed by the node owning `customer`
+        db.materialize_delete(
+            "peer",
+            "customer",
+            &[HashMap::from([
+                ("id".to_string(), number(1.0)),
+                ("first_name".to_string(), string("Cameron")),
+            ])],
+        )
+        .expect("Could not materialize");
+
+        assert_eq!(
+            db.get_table("order_names"),
+            Some(vec![HashMap::from([
+                ("first_name".to_string(), string("Harris")),
+                ("price".to_string(), number(20.0)),
+            ])])
+        );
+
+        assert!(matches!(
+            db.update(&UpdateQuery {
+                table_name: "order_names".to_string(),
+                assignments: vec![],
+                constraints: None,
+            }),
+            Err(DatabaseError::ViewIsReadOnlyError(_))
+        ));
+    }
+
+    #[test]
+    fn views_materialize_computed_and_renamed_columns() {
+        let mut db = Db::new();
+
+        db.create_view(parse_select(
+            "select region as area, price * 2 as doubled, upper(region) into priced from orders where price > 6;",
+        ))
+        .expect("Could not create view");
+        db.create_view(parse_select(
+            "select upper(region) as area, count(*) as n into counts from orders group by region;",
+        ))
+        .expect("Could not create view");
+
+        db.insert(order_insert(vec![("eu", 10.0), ("us", 5.0), ("us", 7.0)]))
+            .expect("Could not insert");
+
+        let priced = |region: &str, doubled: f64| {
+            HashMap::from([
+                ("area".to_string(), string(region)),
+                ("doubled".to_string(), number(doubled)),
+                ("upper(region)".to_string(), string(&region.to_uppercase())),
+            ])
+        };
+
+        assert_eq!(
+            db.get_table("priced"),
+            Some(vec![priced("eu", 20.0), priced("us", 14.0)])
+        );
+
+        let mut counts = db.get_table("counts").expect("No view");
+        counts.sort_by(|a, b| a["n"].partial_cmp(&b["n"]).unwrap());
+
+        assert_eq!(
+            counts,
+            vec![
+                HashMap::from([
+                    ("area".to_string(), string("EU")),
+                    ("n".to_string(), number(1.0)),
+                ]),
+                HashMap::from([
+                    ("area".to_string(), string("US")),
+                    ("n".to_string(), number(2.0)),
+                ]),
+            ]
+        );
+
+        // computed columns are retracted by what they were computed from
+        db.delete(
+            &DeleteQuery::try_from(&parse_statement("delete from orders where price = 5;"))
+                .expect("Could not parse the delete"),
+        )
+        .expect("Could not delete");
+        db.delete(
+            &DeleteQuery::try_from(&parse_statement("delete from orders where region = 'us';"))
+                .expect("Could not parse the delete"),
+        )
+        .expect("Could not delete");
+
+        assert_eq!(db.get_table("priced"), Some(vec![priced("eu", 20.0)]));
+
+        // the same rows selected straight from the table
+        assert_eq!(
+            db.query_data_by_select(&parse_select(
+                "select price * 2 as doubled, orders.* into v from orders where region = 'eu';"
+            ))
+            .expect("Could not query"),
+            vec![HashMap::from([
+                ("region".to_string(), string("eu")),
+                ("price".to_string(), number(10.0)),
+                ("doubled".to_string(), number(20.0)),
+            ])]
+        );
+    }
+
+    #[test]
+    fn qualified_wildcards_select_one_side_of_a_join() {
+        let mut db = customer_db();
+
+        db.insert(InsertQuery {
+            table_name: "orders".to_string(),
+            columns: vec!["customer_id".to_string(), "price".to_string()],
+            rows: vec![vec![Some(number(1.0)), Some(number(10.0))]],
+        })
+        .expect("Could not insert");
+
+        assert_eq!(
+            db.query_data_by_select(&parse_select(
+                "select customer.*, price as paid into v from orders join customer \
+                 on orders.customer_id = customer.id;"
+            ))
+            .expect("Could not query"),
+            vec![HashMap::from([
+                ("customer.id".to_string(), number(1.0)),
+                ("customer.first_name".to_string(), string("Cameron")),
+                ("paid".to_string(), number(10.0)),
+            ])]
+        );
+
+        let select = |sql: &str| match Parser::parse_sql(&GenericDialect {}, sql)
+            .expect("Error with parsing the sql")
+            .first()
+        {
+            Some(Query(query)) => SelectQuery::try_from(&*query.body),
+            _ => panic!("No Select Statement found."),
+        };
+
+        assert_eq!(
+            select("select price * 2 as doubled, count(*) into v from orders group by region;"),
+            Err(StatementError::ColumnNotGroupedError("price".to_string()))
+        );
+        assert_eq!(
+            select("select customer.* into v from orders;"),
+            Err(StatementError::UnknownRelationError("customer".to_string()))
+        );
+    }
+
+    fn create_orders_table(db: &mut Db) {
+        db.create_table(
+            CreateTableQuery::try_from(&parse_statement(
+                "create table orders (region varchar(10) not null, price double, note text);",
+            ))
+            .expect("Could not parse the create table"),
+        )
+        .expect("Could not create the table");
+    }
+
+    #[test]
+    fn inserts_and_updates_are_checked_against_the_schema() {
+        let mut db = Db::new();
+        create_orders_table(&mut db);
+
+        assert_eq!(
+            db.get_schema("orders").map(|schema| schema.column_names()),
+            Some(vec![
+                "region".to_string(),
+                "price".to_string(),
+                "note".to_string()
+            ])
+        );
+        assert_eq!(db.get_table("orders"), Some(vec![]));
+
+        // left out columns are null
+        db.insert(order_insert(vec![("eu", 10.0)]))
+            .expect("Could not insert");
+
+        assert!(matches!(
+            db.insert(InsertQuery {
+                table_name: "orders".to_string(),
+                columns: vec!["region".to_string(), "quantity".to_string()],
+                rows: vec![vec![Some(string("eu")), Some(number(1.0))]],
+            }),
+            Err(DatabaseError::UnknownColumnError(_, column)) if column == "quantity"
+        ));
+
+        // one bad row keeps the whole insert out
+        assert!(matches!(
+            db.insert(InsertQuery {
+                table_name: "orders".to_string(),
+                columns: vec!["region".to_string(), "price".to_string()],
+                rows: vec![
+                    vec![Some(string("us")), Some(number(5.0))],
+                    vec![Some(number(1.0)), Some(number(2.0))],
+                ],
+            }),
+            Err(DatabaseError::ColumnTypeError(_, column, ColumnType::String, ColumnType::Number)) if column == "region"
+        ));
+
+        assert!(matches!(
+            db.insert(InsertQuery {
+                table_name: "orders".to_string(),
+                columns: vec!["price".to_string()],
+                rows: vec![vec![Some(number(1.0))]],
+            }),
+            Err(DatabaseError::NotNullError(_, column)) if column == "region"
+        ));
+
+        assert!(matches!(
+            db.update(
+                &UpdateQuery::try_from(&parse_statement("update orders set price = region;"))
+                    .expect("Could not parse the update")
+            ),
+            Err(DatabaseError::ColumnTypeError(
+                _,
+                _,
+                ColumnType::Number,
+                ColumnType::String
+            ))
+        ));
+
+        assert_eq!(db.get_table("orders").map(|table| table.len()), Some(1));
+
+        assert!(matches!(
+            db.create_table(
+                CreateTableQuery::try_from(&parse_statement("create table orders (id int);"))
+                    .expect("Could not parse the create table")
+            ),
+            Err(DatabaseError::TableAlreadyExistsError(_))
+        ));
+        db.create_table(
+            CreateTableQuery::try_from(&parse_statement(
+                "create table if not exists orders (id int);",
+            ))
+            .expect("Could not parse the create table"),
+        )
+        .expect("Could not create the table");
+
+        assert!(matches!(
+            db.create_view(parse_select("select * into orders from customer;")),
+            Err(DatabaseError::TableAlreadyExistsError(_))
+        ));
+    }
+
+    #[test]
+    fn expressions_are_checked_against_the_schema() {
+        use crate::models::expression::{ExpressionConversionError, ExpressionEvaluationError};
+
+        let mut db = Db::new();
+        create_orders_table(&mut db);
+
+        db.insert(order_insert(vec![("eu", 10.0), ("us", 5.0)]))
+            .expect("Could not insert");
+
+        // a misspelled column is an error rather than a null that matches nothing
+        assert!(matches!(
+            db.create_view(parse_select(
+                "select region into cheap from orders where prise < 6;"
+            )),
+            Err(DatabaseError::ExpressionError(ExpressionConversionError::UnknownColumnError(column, expression)))
+                if column == "prise" && expression == "prise < 6"
+        ));
+        assert!(matches!(
+            db.create_view(parse_select(
+                "select upper(regoin) as r into regions from orders;"
+            )),
+            Err(DatabaseError::ExpressionError(ExpressionConversionError::UnknownColumnError(column, _)))
+                if column == "regoin"
+        ));
+        assert!(!db.is_view("cheap") && !db.is_view("regions"));
+
+        let update = UpdateQuery::try_from(&parse_statement(
+            "update orders set price = cost * 2 where region = 'eu';",
+        ))
+        .expect("Could not parse the update");
+
+        assert!(matches!(
+            db.update(&update),
+            Err(DatabaseError::ExpressionError(ExpressionConversionError::UnknownColumnError(column, _)))
+                if column == "cost"
+        ));
+
+        let delete =
+            DeleteQuery::try_from(&parse_statement("delete from orders where regon = 'us';"))
+                .expect("Could not parse the delete");
+
+        assert!(matches!(
+            db.delete(&delete),
+            Err(DatabaseError::ExpressionError(ExpressionConversionError::UnknownColumnError(column, _)))
+                if column == "regon"
+        ));
+
+        // an assignment that can't be evaluated says why
+        let update =
+            UpdateQuery::try_from(&parse_statement("update orders set price = price / 0;"))
+                .expect("Could not parse the update");
+
+        assert!(matches!(
+            db.update(&update),
+            Err(DatabaseError::UpdateError(table, ExpressionEvaluationError::DivisionByZeroError(expression)))
+                if table == "orders" && expression == "price / 0"
+        ));
+
+        assert_eq!(db.get_table("orders").map(|rows| rows.len()), Some(2));
+    }
+
+    #[test]
+    fn unsupported_column_types_are_rejected() {
+        assert_eq!(
+            CreateTableQuery::try_from(&parse_statement("create table t (doc json);")),
+            Err(StatementError::UnsupportedDataTypeError("JSON".to_string()))
+        );
+    }
+
+    fn literal(sql: &str) -> TypeValue {
+        match parse_statement(&format!("select {sql} into v from t;")) {
+            Query(query) => match &*query.body {
+                sqlparser::ast::SetExpr::Select(select) => match &select.projection[0] {
+                    sqlparser::ast::SelectItem::UnnamedExpr(expr) => {
+                        TypeValue::try_from(expr).expect("Could not parse the literal")
+                    }
+                    _ => panic!("No expression found."),
+                },
+                _ => panic!("No select found."),
+            },
+            _ => panic!("No query found."),
+        }
+    }
+
+    #[test]
+    fn literals_are_parsed_into_typed_values() {
+        assert!(matches!(
+            literal("42"),
+            TypeValue::IntegerValueType(IntegerValueType { value: 42 })
+        ));
+        assert!(matches!(
+            literal("-42"),
+            TypeValue::IntegerValueType(IntegerValueType { value: -42 })
+        ));
+        assert!(matches!(
+            literal("12.50"),
+            TypeValue::DecimalValueType(DecimalValueType {
+                mantissa: 1250,
+                scale: 2
+            })
+        ));
+        assert!(matches!(literal("1e3"), TypeValue::NumberValueType(_)));
+        assert!(matches!(
+            literal("true"),
+            TypeValue::BooleanValueType(BooleanValueType { value: true })
+        ));
+        assert!(matches!(literal("null"), TypeValue::NullValueType));
+        assert!(matches!(
+            literal("X'CAFE'"),
+            TypeValue::BytesValueType(BytesValueType { ref value }) if value == &[0xCA, 0xFE]
+        ));
+        assert!(matches!(
+            literal("DATE '1970-01-02'"),
+            TypeValue::DateValueType(DateValueType { days: 1 })
+        ));
+        assert!(matches!(
+            literal("TIMESTAMP '1970-01-01 00:00:01'"),
+            TypeValue::TimestampValueType(TimestampValueType { micros: 1_000_000 })
+        ));
+        assert_eq!(
+            DecimalValueType::parse("-0.05").map(|d| d.to_string()),
+            Some("-0.05".to_string())
+        );
+    }
+
+    #[test]
+    fn values_compare_across_number_types() {
+        assert_eq!(literal("2"), number(2.0));
+        assert_eq!(literal("2.0"), literal("2"));
+        assert!(literal("0.1") < literal("0.10000000000000000001"));
+        assert!(literal("3") > literal("2.99"));
+        assert!(literal("DATE '2023-01-31'") < literal("TIMESTAMP '2023-01-31 00:00:01'"));
+        assert_eq!(
+            literal("DATE '2023-01-31'"),
+            literal("TIMESTAMP '2023-01-31 00:00:00'")
+        );
+        assert!(literal("false") < literal("true"));
+
+        // different types don't compare
+        assert_eq!(literal("'1'").partial_cmp(&literal("1")), None);
+        assert_ne!(literal("true"), literal("1"));
+    }
+
+    #[test]
+    fn inserted_values_are_coerced_into_the_column_types() {
+        let mut db = Db::new();
+
+        db.create_table(
+            CreateTableQuery::try_from(&parse_statement(
+                "create table events (id bigint, amount numeric(10, 2), ratio real, at timestamp, on_day date, seen boolean, payload bytea);",
+            ))
+            .expect("Could not parse the create table"),
+        )
+        .expect("Could not create the table");
+
+        db.insert(
+            InsertQuery::try_from(&parse_statement(
+                "insert into events values (1, 12, 3, '2023-01-31 10:00:00', '2023-01-31', false, X'01');",
+            ))
+            .expect("Could not parse the insert"),
+        )
+        .expect("Could not insert");
+
+        let row = &db.get_table("events").expect("No table")[0];
+
+        assert!(matches!(row["id"], TypeValue::IntegerValueType(_)));
+        assert!(matches!(row["amount"], TypeValue::DecimalValueType(_)));
+        assert!(matches!(row["ratio"], TypeValue::NumberValueType(_)));
+        assert!(matches!(row["at"], TypeValue::TimestampValueType(_)));
+        assert!(matches!(row["on_day"], TypeValue::DateValueType(_)));
+        assert!(matches!(row["seen"], TypeValue::BooleanValueType(_)));
+        assert!(matches!(row["payload"], TypeValue::BytesValueType(_)));
+
+        assert!(matches!(
+            db.insert(
+                InsertQuery::try_from(&parse_statement("insert into events (id) values (1.5);"))
+                    .expect("Could not parse the insert"),
+            ),
+            Err(DatabaseError::ColumnTypeError(
+                _,
+                _,
+                ColumnType::Integer,
+                ColumnType::Decimal
+            ))
+        ));
+
+        assert_eq!(
+            db.query_data_by_select(&parse_select(
+                "select id into v from events where at > DATE '2023-01-31' and amount = 12.00;"
+            ))
+            .expect("Could not query"),
+            vec![HashMap::from([("id".to_string(), literal("1"))])]
+        );
+    }
+
+    #[test]
+    fn selects_filter_with_null_aware_conditions() {
+        let mut db = Db::new();
+        create_orders_table(&mut db);
+
+        db.insert(
+            InsertQuery::try_from(&parse_statement(
+                "insert into orders values ('eu', 10, 'gift wrap'), ('us', 5, null), ('za', null, 'Gift card');",
+            ))
+            .expect("Could not parse the insert"),
+        )
+        .expect("Could not insert");
+
+        let regions = |sql: &str| {
+            db.query_data_by_select(&parse_select(&format!(
+                "select region into v from orders where {sql};"
+            )))
+            .expect("Could not query")
+            .into_iter()
+            .map(|row| match &row["region"] {
+                TypeValue::StringTypeValue(s) => s.value.clone(),
+                value => panic!("Not a region: {value:?}"),
+            })
+            .collect::<Vec<String>>()
+        };
+
+        assert_eq!(regions("region in ('eu', 'za')"), vec!["eu", "za"]);
+        assert_eq!(regions("price not in (10, null)"), Vec::<String>::new());
+        assert_eq!(regions("price between 5 and 10"), vec!["eu", "us"]);
+        assert_eq!(regions("price <> 10"), vec!["us"]);
+        assert_eq!(regions("not price = 10"), vec!["us"]);
+        assert_eq!(regions("note ilike 'gift%'"), vec!["eu", "za"]);
+        assert_eq!(regions("note is null or price is null"), vec!["us", "za"]);
+        assert_eq!(
+            regions("(region = 'us' or region = 'za') and price > 1"),
+            vec!["us"]
+        );
+    }
+
+    #[test]
+    fn selects_aggregates_and_updates_compute_values() {
+        let mut db = Db::new();
+
+        db.insert(order_insert(vec![("eu", 10.0), ("us", 5.0), ("us", 7.0)]))
+            .expect("Could not insert");
+
+        assert_eq!(
+            db.query_data_by_select(&parse_select(
+                "select region into v from orders where price * 2 > 12 and upper(region) = 'US';"
+            ))
+            .expect("Could not query"),
+            vec![HashMap::from([("region".to_string(), string("us"))])]
+        );
+
+        db.create_view(parse_select(
+            "select region, sum(price * 2) as doubled into totals from orders group by region;",
+        ))
+        .expect("Could not create view");
+
+        db.update(
+            &UpdateQuery::try_from(&parse_statement(
+                "update orders set price = price + 1 where region = 'eu';",
+            ))
+            .expect("Could not parse the update"),
+        )
+        .expect("Could not update");
+
+        let mut totals = db.get_table("totals").expect("No view");
+        totals.sort_by(|a, b| a["doubled"].partial_cmp(&b["doubled"]).unwrap());
+
+        assert_eq!(
+            totals,
+            vec![
+                HashMap::from([
+                    ("region".to_string(), string("eu")),
+                    ("doubled".to_string(), number(22.0)),
+                ]),
+                HashMap::from([
+                    ("region".to_string(), string("us")),
+                    ("doubled".to_string(), number(24.0)),
+                ]),
+            ]
+        );
+    }
+
+    fn create_index(db: &mut Db, sql: &str) -> Result<(), DatabaseError> {
+        db.create_index(
+            CreateIndexQuery::try_from(&parse_statement(sql))
+                .expect("Could not parse the create index"),
+        )
+    }
+
+    #[test]
+    fn indexes_are_created_and_kept_up_to_date() {
+        let path =
+            std::env::temp_dir().join(format!("turnip-db-index-test-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let select = parse_select("select region, price into c from orders where price >= 7;");
+
+        let data = {
+            let mut db = Db::open(&path, FsyncPolicy::Always).expect("Could not open the db");
+
+            assert!(matches!(
+                create_index(&mut db, "create index price_idx on orders (price);"),
+                Err(DatabaseError::TableNotFoundError(_))
+            ));
+
+            create_orders_table(&mut db);
+            db.insert(order_insert(vec![("eu", 10.0), ("us", 5.0), ("us", 7.0)]))
+                .expect("Could not insert");
+
+            create_index(&mut db, "create index price_idx on orders (price);")
+                .expect("Could not create the index");
+            create_index(
+                &mut db,
+                "create index region_idx on orders using hash (region);",
+            )
+            .expect("Could not create the index");
+
+            assert!(matches!(
+                create_index(&mut db, "create index price_idx on orders (region);"),
+                Err(DatabaseError::IndexAlreadyExistsError(_))
+            ));
+            assert!(create_index(
+                &mut db,
+                "create index if not exists price_idx on orders (region);"
+            )
+            .is_ok());
+            assert!(matches!(
+                create_index(&mut db, "create index quantity_idx on orders (quantity);"),
+                Err(DatabaseError::UnknownColumnError(_, _))
+            ));
+
+            assert_eq!(
+                db.query_data_by_select(&select).expect("Could not query"),
+                vec![
+                    HashMap::from([
+                        ("region".to_string(), string("eu")),
+                        ("price".to_string(), number(10.0)),
+                    ]),
+                    HashMap::from([
+                        ("region".to_string(), string("us")),
+                        ("price".to_string(), number(7.0)),
+                    ]),
+                ]
+            );
+
+            db.update(
+                &UpdateQuery::try_from(&parse_statement(
+                    "update orders set price = 8 where region = 'us' and price = 5;",
+                ))
+                .expect("Could not parse the update"),
+            )
+            .expect("Could not update");
+            db.delete(
+                &DeleteQuery::try_from(&parse_statement("delete from orders where region = 'eu';"))
+                    .expect("Could not parse the delete"),
+            )
+            .expect("Could not delete");
+
+            assert_eq!(
+                db.query_data_by_select(&select).expect("Could not query"),
+                vec![
+                    HashMap::from([
+                        ("region".to_string(), string("us")),
+                        ("price".to_string(), number(8.0)),
+                    ]),
+                    HashMap::from([
+                        ("region".to_string(), string("us")),
+                        ("price".to_string(), number(7.0)),
+                    ]),
+                ]
+            );
+
+            db.data.clone()
+        };
+
+        // the indexes come back with the log
+        let db = Db::open(&path, FsyncPolicy::Always).expect("Could not open the db");
+
+        assert_eq!(db.data, data);
+        assert_eq!(db.data["orders"].indexes().len(), 2);
+
+        std::fs::remove_file(&path).expect("Could not remove the log");
+    }
+
+    #[test]
+    fn db_is_recovered_from_the_log() {
+        let path = std::env::temp_dir().join(format!("turnip-db-log-test-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let (data, views) = {
+            let mut db = Db::open(&path, FsyncPolicy::Always).expect("Could not open the db");
+
+            create_orders_table(&mut db);
+            db.insert(order_insert(vec![("eu", 10.0), ("us", 5.0)]))
+                .expect("Could not insert");
+            db.create_view(parse_select(
+                "select region, sum(price) as total into totals from orders group by region;",
+            ))
+            .expect("Could not create view");
+            db.delete(
+                &DeleteQuery::try_from(&parse_statement("delete from orders where region = 'us';"))
+                    .expect("Could not parse the delete"),
+            )
+            .expect("Could not delete");
+            // the peer answers twice, the second answer replaces the first
+            for _ in 0..2 {
+                db.clear_view_results("totals", "peer")
+                    .expect("Could not clear the view");
+                db.insert_into_view(
+                    "totals",
+                    "peer",
+                    vec![HashMap::from([
+                        ("region".to_string(), string("eu")),
+                        ("price".to_string(), number(1.0)),
+                    ])],
+                )
+                .expect("Could not insert into view");
+            }
+
+            (db.data.clone(), db.views.clone())
+        };
+
+        let db = Db::open(&path, FsyncPolicy::Always).expect("Could not open the db");
+
+        assert_eq!(db.data, data);
+        assert_eq!(db.views, views);
+        assert!(db.get_schema("orders").is_some());
+        assert_eq!(
+            db.get_table("totals"),
+            Some(vec![HashMap::from([
+                ("region".to_string(), string("eu")),
+                ("total".to_string(), number(11.0)),
+            ])])
+        );
+
+        std::fs::remove_file(&path).expect("Could not remove the log");
+    }
+
+    #[test]
+    fn db_is_restored_from_a_snapshot_and_the_log_after_it() {
+        let dir =
+            std::env::temp_dir().join(format!("turnip-db-snapshot-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("Could not create the directory");
+
+        let wal_path = dir.join("wal.log");
+
+        let mut select_index = SelectIndex::new();
+        select_index
+            .insert_select(
+                "127.0.0.1:8082",
+                parse_select("select * into c from orders where price > 6;"),
+            )
+            .expect("Could not insert select");
+
+        let data = {
+            let (mut db, _) = crate::db::snapshot::restore(&dir, &wal_path, FsyncPolicy::Always)
+                .expect("Could not restore");
+
+            db.insert(order_insert(vec![("eu", 10.0), ("us", 5.0)]))
+                .expect("Could not insert");
+
+            db.take_snapshot(&dir, &select_index)
+                .expect("Could not take the snapshot");
+
+            // the log only holds what came after the snapshot
+            assert_eq!(std::fs::metadata(&wal_path).expect("No log").len(), 0);
+
+            db.insert(order_insert(vec![("us", 7.0)]))
+                .expect("Could not insert");
+
+            assert_eq!(db.last_lsn(), 2);
+
+            db.data.clone()
+        };
+
+        let (db, restored_index) =
+            crate::db::snapshot::restore(&dir, &wal_path, FsyncPolicy::Always)
+                .expect("Could not restore");
+
+        assert_eq!(db.data, data);
+        assert_eq!(db.last_lsn(), 2);
+        assert_eq!(restored_index.selects(), select_index.selects());
+        assert_eq!(
+            restored_index
+                .get_addr_for_insert(&order_insert(vec![("eu", 8.0)]))
+                .unwrap(),
+            vec!["127.0.0.1:8082".to_string()]
+        );
+
+        std::fs::remove_dir_all(&dir).expect("Could not remove the directory");
+    }
+
+    #[test]
+    fn db_is_restored_from_the_previous_snapshot_when_the_latest_is_corrupt() {
+        let dir = std::env::temp_dir().join(format!(
+            "turnip-db-corrupt-snapshot-test-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("Could not create the directory");
+
+        let wal_path = dir.join("wal.log");
+        let select_index = SelectIndex::new();
+
+        let (data, latest) = {
+            let mut db = Db::open(&wal_path, FsyncPolicy::Always).expect("Could not open");
+
+            db.insert(order_insert(vec![("eu", 10.0)]))
+                .expect("Could not insert");
+            db.take_snapshot(&dir, &select_index)
+                .expect("Could not take the snapshot");
+
+            db.insert(order_insert(vec![("us", 5.0)]))
+                .expect("Could not insert");
+            let latest = db
+                .take_snapshot(&dir, &select_index)
+                .expect("Could not take the snapshot");
+
+            db.insert(order_insert(vec![("us", 7.0)]))
+                .expect("Could not insert");
+
+            (db.data.clone(), latest)
+        };
+
+        let mut bytes = std::fs::read(&latest).expect("Could not read");
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xFF;
+        std::fs::write(&latest, &bytes).expect("Could not write");
+
+        let (db, _) = crate::db::snapshot::restore(&dir, &wal_path, FsyncPolicy::Always)
+            .expect("Could not restore");
+
+        assert_eq!(db.data, data);
+        assert_eq!(db.last_lsn(), 3);
+
+        std::fs::remove_dir_all(&dir).expect("Could not remove the directory");
+    }
 }
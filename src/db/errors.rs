@@ -1,10 +1,61 @@
 use sqlparser::ast::Expr;
 use thiserror::Error;
 
+use crate::models::create_table_query::ColumnType;
+use crate::models::expression::{ExpressionConversionError, ExpressionEvaluationError};
+
 #[derive(Error, Debug)]
 pub enum DatabaseError {
     #[error("Could not insert the record")]
     InsertError(),
+
+    #[error("Could not update the records of `{0}`: {1}")]
+    UpdateError(String, ExpressionEvaluationError),
+
+    #[error("Could not evaluate the constraints on the records of `{0}`: {1}")]
+    ConstraintError(String, ExpressionEvaluationError),
+
+    #[error("Table `{0}` already exists.")]
+    TableAlreadyExistsError(String),
+
+    #[error("No table named `{0}` exists.")]
+    TableNotFoundError(String),
+
+    #[error("Index `{0}` already exists.")]
+    IndexAlreadyExistsError(String),
+
+    #[error("Table `{0}` has no column `{1}`.")]
+    UnknownColumnError(String, String),
+
+    #[error("{0}")]
+    ExpressionError(#[from] ExpressionConversionError),
+
+    #[error("Column `{1}` of `{0}` holds {2:?} values, not {3:?}.")]
+    ColumnTypeError(String, String, ColumnType, ColumnType),
+
+    #[error("Column `{1}` of `{0}` can not be null.")]
+    NotNullError(String, String),
+
+    #[error("An insert into `{0}` has {1} columns but a row with {2} values.")]
+    ColumnCountError(String, usize, usize),
+
+    #[error("No view named `{0}` exists.")]
+    ViewNotFoundError(String),
+
+    #[error("`{0}` is a view, its data can only come from the select it is made of.")]
+    ViewIsReadOnlyError(String),
+
+    #[error("Could not read or write the log: {0}")]
+    WalIoError(#[from] std::io::Error),
+
+    #[error("Could not encode the log record: {0}")]
+    WalEncodeError(#[from] postcard::Error),
+
+    #[error("The snapshot is corrupt.")]
+    CorruptSnapshotError(),
+
+    #[error("Version {0} snapshots are not supported.")]
+    UnsupportedSnapshotVersionError(u32),
 }
 
 #[derive(Error, Debug)]
@@ -14,4 +65,7 @@ pub enum ValueParseError {
 
     #[error("Expression is not supported")]
     IsNotSupportedError(Expr, String),
+
+    #[error("`{1}` is not a valid {0} literal.")]
+    InvalidLiteralError(String, String),
 }
@@ -0,0 +1,291 @@
+// the state of a joining view: the rows seen so far of both joined relations, so a row inserted
+// into either relation can be joined with the rows of the other one that came in before it
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use super::data::TypeValue;
+use super::models::value_key::ValueKey;
+use crate::models::join::Join;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct JoinSide {
+    relation: String,
+    // the column of this side in an equality of the join constraint, its rows are hashed by it
+    key: Option<String>,
+    // the rows by an id that stays the same when other rows are removed, so the index stays valid
+    rows: BTreeMap<usize, HashMap<String, TypeValue>>,
+    next_id: usize,
+    index: HashMap<ValueKey, Vec<usize>>,
+}
+
+impl JoinSide {
+    fn new(relation: &str, key: Option<String>) -> Self {
+        JoinSide {
+            relation: relation.to_string(),
+            key,
+            rows: BTreeMap::new(),
+            next_id: 0,
+            index: HashMap::new(),
+        }
+    }
+
+    fn push(&mut self, row: HashMap<String, TypeValue>) {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        if let Some(key) = &self.key {
+            let value = row.get(key).unwrap_or(&TypeValue::NullValueType);
+
+            self.index
+                .entry(ValueKey::from(value))
+                .or_default()
+                .push(id);
+        }
+
+        self.rows.insert(id, row);
+    }
+
+    // removes one row equal to the given row, returns whether there was one
+    fn remove(&mut self, row: &HashMap<String, TypeValue>) -> bool {
+        let ids: Vec<usize> = match &self.key {
+            Some(key) => {
+                let value = row.get(key).unwrap_or(&TypeValue::NullValueType);
+
+                self.index
+                    .get(&ValueKey::from(value))
+                    .cloned()
+                    .unwrap_or_default()
+            }
+            None => self.rows.keys().copied().collect(),
+        };
+
+        for id in ids {
+            if self.rows.get(&id) == Some(row) {
+                self.rows.remove(&id);
+
+                if let Some(key) = &self.key {
+                    let key = ValueKey::from(row.get(key).unwrap_or(&TypeValue::NullValueType));
+
+                    if let Some(ids) = self.index.get_mut(&key) {
+                        ids.retain(|i| *i != id);
+
+                        if ids.is_empty() {
+                            self.index.remove(&key);
+                        }
+                    }
+                }
+
+                return true;
+            }
+        }
+
+        false
+    }
+
+    // the rows that could join with a row of the other side, by the value of the other side's key
+    fn candidates(&self, value: Option<&TypeValue>) -> Vec<&HashMap<String, TypeValue>> {
+        match (&self.key, value) {
+            // null never equals anything
+            (Some(_), Some(TypeValue::NullValueType) | None) => vec![],
+            (Some(_), Some(value)) => match self.index.get(&ValueKey::from(value)) {
+                Some(ids) => ids.iter().filter_map(|id| self.rows.get(id)).collect(),
+                None => vec![],
+            },
+            (None, _) => self.rows.values().collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JoinState {
+    join: Join,
+    left: JoinSide,
+    right: JoinSide,
+}
+
+impl JoinState {
+    pub fn new(from: &str, join: &Join) -> Self {
+        let left_prefix = format!("{from}.");
+        let right_prefix = format!("{}.", join.relation);
+
+        // the first equality between a column of each side becomes the hash key of the join
+        let keys = join
+            .constraint
+            .column_equalities()
+            .into_iter()
+            .find_map(|(a, b)| {
+                match (
+                    a.strip_prefix(&left_prefix),
+                    b.strip_prefix(&right_prefix),
+                    a.strip_prefix(&right_prefix),
+                    b.strip_prefix(&left_prefix),
+                ) {
+                    (Some(l), Some(r), _, _) | (_, _, Some(r), Some(l)) => {
+                        Some((l.to_string(), r.to_string()))
+                    }
+                    _ => None,
+                }
+            });
+
+        let (left_key, right_key) = match keys {
+            Some((l, r)) => (Some(l), Some(r)),
+            None => (None, None),
+        };
+
+        JoinState {
+            join: join.clone(),
+            left: JoinSide::new(from, left_key),
+            right: JoinSide::new(&join.relation, right_key),
+        }
+    }
+
+    // the side a relation is on and the other side, None if the relation is not joined
+    fn sides(&mut self, relation: &str) -> Option<(&Join, &mut JoinSide, &JoinSide, bool)> {
+        let JoinState { join, left, right } = self;
+
+        if relation == left.relation {
+            Some((join, left, right, true))
+        } else if relation == right.relation {
+            Some((join, right, left, false))
+        } else {
+            None
+        }
+    }
+
+    // the rows a row of one side joins into with the rows of the other side
+    fn joined(
+        join: &Join,
+        side: &JoinSide,
+        other: &JoinSide,
+        is_left: bool,
+        row: &HashMap<String, TypeValue>,
+    ) -> Vec<HashMap<String, TypeValue>> {
+        let value = side.key.as_ref().and_then(|key| row.get(key));
+
+        other
+            .candidates(value)
+            .into_iter()
+            .map(|other_row| {
+                if is_left {
+                    joined_row(&side.relation, row, &other.relation, other_row)
+                } else {
+                    joined_row(&other.relation, other_row, &side.relation, row)
+                }
+            })
+            .filter(|joined| join.constraint.evaluate(joined).unwrap_or(false))
+            .collect()
+    }
+
+    // Adds a row of one of the joined relations, returns the joined rows it produced
+    pub fn add(
+        &mut self,
+        relation: &str,
+        row: HashMap<String, TypeValue>,
+    ) -> Vec<HashMap<String, TypeValue>> {
+        match self.sides(relation) {
+            Some((join, side, other, is_left)) => {
+                let joined = JoinState::joined(join, side, other, is_left, &row);
+                side.push(row);
+                joined
+            }
+            None => vec![],
+        }
+    }
+
+    // Removes a row of one of the joined relations, returns the joined rows it was part of
+    pub fn remove(
+        &mut self,
+        relation: &str,
+        row: &HashMap<String, TypeValue>,
+    ) -> Vec<HashMap<String, TypeValue>> {
+        match self.sides(relation) {
+            Some((join, side, other, is_left)) => {
+                if side.remove(row) {
+                    JoinState::joined(join, side, other, is_left, row)
+                } else {
+                    vec![]
+                }
+            }
+            None => vec![],
+        }
+    }
+
+    // Adds a row qualified by its relation, the way rows of a join are sent between nodes
+    pub fn add_qualified(
+        &mut self,
+        row: HashMap<String, TypeValue>,
+    ) -> Vec<HashMap<String, TypeValue>> {
+        for relation in [self.left.relation.clone(), self.right.relation.clone()] {
+            if let Some(row) = unqualify_row(&relation, &row) {
+                return self.add(&relation, row);
+            }
+        }
+
+        eprintln!("Row does not belong to either side of the join: {:?}", row);
+
+        vec![]
+    }
+
+    // Removes a row qualified by its relation, the inverse of add_qualified
+    pub fn remove_qualified(
+        &mut self,
+        row: &HashMap<String, TypeValue>,
+    ) -> Vec<HashMap<String, TypeValue>> {
+        for relation in [self.left.relation.clone(), self.right.relation.clone()] {
+            if let Some(row) = unqualify_row(&relation, row) {
+                return self.remove(&relation, &row);
+            }
+        }
+
+        vec![]
+    }
+}
+
+// The row of a relation with every column prefixed by the relation, i.e. `id` becomes `orders.id`
+pub fn qualify_row(relation: &str, row: &HashMap<String, TypeValue>) -> HashMap<String, TypeValue> {
+    row.iter()
+        .map(|(column, value)| (format!("{relation}.{column}"), value.clone()))
+        .collect()
+}
+
+// the inverse of qualify_row, None if not every column of the row is qualified by the relation
+fn unqualify_row(
+    relation: &str,
+    row: &HashMap<String, TypeValue>,
+) -> Option<HashMap<String, TypeValue>> {
+    let prefix = format!("{relation}.");
+
+    row.iter()
+        .map(|(column, value)| {
+            column
+                .strip_prefix(&prefix)
+                .map(|column| (column.to_string(), value.clone()))
+        })
+        .collect()
+}
+
+// Two joined rows as one. Every column is there by its qualified name, and by its plain name
+// as long as the other relation does not have a column with the same name.
+fn joined_row(
+    left_relation: &str,
+    left: &HashMap<String, TypeValue>,
+    right_relation: &str,
+    right: &HashMap<String, TypeValue>,
+) -> HashMap<String, TypeValue> {
+    let mut row = qualify_row(left_relation, left);
+    row.extend(qualify_row(right_relation, right));
+
+    for (column, value) in left.iter() {
+        if !right.contains_key(column) {
+            row.insert(column.clone(), value.clone());
+        }
+    }
+
+    for (column, value) in right.iter() {
+        if !left.contains_key(column) {
+            row.insert(column.clone(), value.clone());
+        }
+    }
+
+    row
+}
@@ -1,4 +1,13 @@
+pub mod aggregate_state;
 pub mod data;
 pub mod errors;
+pub mod join_state;
 pub mod models;
+pub mod predicate_index;
+pub mod schema;
 pub mod select_index;
+pub mod snapshot;
+pub mod table;
+pub mod table_index;
+pub mod view;
+pub mod wal;
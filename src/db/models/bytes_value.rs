@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct BytesValueType {
+    pub value: Vec<u8>,
+}
+
+impl BytesValueType {
+    // the bytes of a hex literal like the `DEADBEEF` of X'DEADBEEF'
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        if !hex.len().is_multiple_of(2) {
+            return None;
+        }
+
+        let value = (0..hex.len())
+            .step_by(2)
+            .map(|i| {
+                hex.get(i..i + 2)
+                    .and_then(|b| u8::from_str_radix(b, 16).ok())
+            })
+            .collect::<Option<Vec<u8>>>()?;
+
+        Some(BytesValueType { value })
+    }
+}
@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// a calendar date, as days since 1970-01-01
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct DateValueType {
+    pub days: i32,
+}
+
+pub const MICROS_PER_DAY: i64 = 86_400_000_000;
+
+// the years dates can be in
+pub const MIN_YEAR: i64 = 1;
+pub const MAX_YEAR: i64 = 9999;
+
+// the days since 1970-01-01 of 0001-01-01 and 9999-12-31
+const MIN_DAYS: i64 = -719_162;
+const MAX_DAYS: i64 = 2_932_896;
+
+// the days since 1970-01-01 of a date of the proleptic gregorian calendar, None if that is too far
+// out to be counted
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> Option<i64> {
+    let year = if month <= 2 {
+        year.checked_sub(1)?
+    } else {
+        year
+    };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era.checked_mul(146_097)?.checked_add(day_of_era - 719_468)
+}
+
+// the year, month and day of the days since 1970-01-01
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl DateValueType {
+    // parses a `YYYY-MM-DD` date
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut parts = s.trim().splitn(3, '-');
+
+        let year = parts.next()?.parse::<i64>().ok()?;
+        let month = parts.next()?.parse::<u32>().ok()?;
+        let day = parts.next()?.parse::<u32>().ok()?;
+
+        if !(MIN_YEAR..=MAX_YEAR).contains(&year)
+            || !(1..=12).contains(&month)
+            || day == 0
+            || day > days_in_month(year, month)
+        {
+            return None;
+        }
+
+        DateValueType::from_days(days_from_civil(year, month, day)?)
+    }
+
+    // the date the days since 1970-01-01 fall on, None if it is not in a year dates can be in
+    pub fn from_days(days: i64) -> Option<Self> {
+        if !(MIN_DAYS..=MAX_DAYS).contains(&days) {
+            return None;
+        }
+
+        Some(DateValueType {
+            days: i32::try_from(days).ok()?,
+        })
+    }
+
+    // the start of the day, None if it is too far out to be a timestamp
+    pub fn to_micros(&self) -> Option<i64> {
+        (self.days as i64).checked_mul(MICROS_PER_DAY)
+    }
+}
+
+impl fmt::Display for DateValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day) = civil_from_days(self.days as i64);
+
+        write!(f, "{year:04}-{month:02}-{day:02}")
+    }
+}
@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+
+// the most digits a decimal keeps after the point
+pub const MAX_DECIMAL_SCALE: u32 = 28;
+
+// An exact decimal, `mantissa / 10^scale`. 12.50 is a mantissa of 1250 with a scale of 2.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct DecimalValueType {
+    pub mantissa: i128,
+    pub scale: u32,
+}
+
+impl DecimalValueType {
+    pub fn new(mantissa: i128, scale: u32) -> Self {
+        DecimalValueType { mantissa, scale }
+    }
+
+    // parses a plain decimal literal like `-12.50`, exponents are left to floats
+    pub fn parse(s: &str) -> Option<Self> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+
+        if (whole.is_empty() && fraction.is_empty())
+            || !whole
+                .chars()
+                .chain(fraction.chars())
+                .all(|c| c.is_ascii_digit())
+            || fraction.len() > MAX_DECIMAL_SCALE as usize
+        {
+            return None;
+        }
+
+        let mantissa = format!("{whole}{fraction}").parse::<i128>().ok()?;
+
+        Some(DecimalValueType {
+            mantissa: if negative { -mantissa } else { mantissa },
+            scale: fraction.len() as u32,
+        })
+    }
+
+    // the mantissa of the same value with a bigger scale, None if it does not fit
+    pub fn rescaled(&self, scale: u32) -> Option<i128> {
+        10i128
+            .checked_pow(scale.checked_sub(self.scale)?)
+            .and_then(|factor| self.mantissa.checked_mul(factor))
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.mantissa as f64 / 10f64.powi(self.scale as i32)
+    }
+
+    // exact where both fit the bigger scale, otherwise as floats
+    pub fn compare(&self, other: &DecimalValueType) -> Option<Ordering> {
+        let scale = self.scale.max(other.scale);
+
+        match (self.rescaled(scale), other.rescaled(scale)) {
+            (Some(a), Some(b)) => Some(a.cmp(&b)),
+            _ => self.to_f64().partial_cmp(&other.to_f64()),
+        }
+    }
+}
+
+impl fmt::Display for DecimalValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let scale = self.scale as usize;
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+
+        if scale == 0 {
+            return write!(f, "{sign}{digits}");
+        }
+
+        let digits = format!("{digits:0>width$}", width = scale + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - scale);
+
+        write!(f, "{sign}{whole}.{fraction}")
+    }
+}
@@ -1,2 +1,9 @@
+pub mod boolean_value;
+pub mod bytes_value;
+pub mod date_value;
+pub mod decimal_value;
+pub mod integer_value;
 pub mod number_value;
 pub mod string_value;
+pub mod timestamp_value;
+pub mod value_key;
@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use super::date_value::{civil_from_days, DateValueType, MICROS_PER_DAY};
+
+// a point in time, as microseconds since 1970-01-01 00:00:00 UTC
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct TimestampValueType {
+    pub micros: i64,
+}
+
+impl TimestampValueType {
+    // Parses a `YYYY-MM-DD HH:MM:SS[.ffffff]` timestamp, a `T` can stand in for the space and a
+    // trailing `Z` is allowed. Timestamps without a time are at midnight, all of them are UTC.
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let s = s.strip_suffix('Z').unwrap_or(s);
+
+        let (date, time) = match s.split_once(['T', ' ']) {
+            Some((date, time)) => (date, Some(time)),
+            None => (s, None),
+        };
+
+        let date = DateValueType::parse(date)?;
+
+        let time = match time {
+            Some(time) => {
+                let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+                let mut parts = time.splitn(3, ':');
+
+                let hours = parts.next()?.parse::<u32>().ok()? as i64;
+                let minutes = parts.next()?.parse::<u32>().ok()? as i64;
+                let seconds = parts.next().map_or(Some(0), |s| s.parse::<u32>().ok())? as i64;
+
+                if hours > 23 || minutes > 59 || seconds > 59 {
+                    return None;
+                }
+
+                if fraction.len() > 6 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+                    return None;
+                }
+
+                let micros = match fraction {
+                    "" => 0,
+                    fraction => format!("{fraction:0<6}").parse::<i64>().ok()?,
+                };
+
+                ((hours * 60 + minutes) * 60 + seconds) * 1_000_000 + micros
+            }
+            None => 0,
+        };
+
+        Some(TimestampValueType {
+            micros: date.to_micros()?.checked_add(time)?,
+        })
+    }
+}
+
+impl fmt::Display for TimestampValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let days = self.micros.div_euclid(MICROS_PER_DAY);
+        let time = self.micros.rem_euclid(MICROS_PER_DAY);
+
+        let (year, month, day) = civil_from_days(days);
+        let seconds = time / 1_000_000;
+
+        write!(
+            f,
+            "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )?;
+
+        match time % 1_000_000 {
+            0 => Ok(()),
+            micros => write!(f, ".{micros:06}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_are_parsed_and_printed() {
+        let timestamp =
+            TimestampValueType::parse("2024-02-29T13:45:07.25Z").expect("Could not parse");
+
+        assert_eq!(timestamp.micros, 1_709_214_307_250_000);
+        assert_eq!(timestamp.to_string(), "2024-02-29 13:45:07.250000");
+
+        assert_eq!(
+            TimestampValueType::parse("1969-12-31 23:59:59").map(|t| t.micros),
+            Some(-1_000_000)
+        );
+        assert_eq!(
+            TimestampValueType::parse("1900-03-01").map(|t| t.to_string()),
+            Some("1900-03-01 00:00:00".to_string())
+        );
+
+        assert_eq!(TimestampValueType::parse("2023-02-29 00:00:00"), None);
+        assert_eq!(TimestampValueType::parse("2023-01-01 24:00:00"), None);
+        assert_eq!(DateValueType::parse("2023-13-01"), None);
+    }
+
+    #[test]
+    fn dates_and_times_out_of_range_are_rejected() {
+        assert_eq!(
+            DateValueType::parse("0001-01-01").map(|d| d.to_string()),
+            Some("0001-01-01".to_string())
+        );
+        assert_eq!(
+            DateValueType::parse("9999-12-31").map(|d| d.to_string()),
+            Some("9999-12-31".to_string())
+        );
+        assert_eq!(DateValueType::parse("0000-12-31"), None);
+        assert_eq!(DateValueType::parse("10000-01-01"), None);
+        assert_eq!(DateValueType::parse("9223372036854775807-01-01"), None);
+        assert_eq!(DateValueType::from_days(i32::MAX as i64), None);
+
+        // dates made some other way may still be too far out to be timestamps
+        assert_eq!(DateValueType { days: i32::MAX }.to_micros(), None);
+
+        assert_eq!(TimestampValueType::parse("2020-01-01 -1:-5"), None);
+        assert_eq!(TimestampValueType::parse("2020-01-01 00:00:-1"), None);
+    }
+}
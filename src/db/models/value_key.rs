@@ -0,0 +1,105 @@
+use crate::db::data::TypeValue;
+use serde::{Deserialize, Serialize};
+
+// A hashable, totally ordered stand-in for a TypeValue, used as the key of
+// hash and interval indexes. Keys of different types never compare equal and
+// are ordered Null < Boolean < Number < String < Time < Bytes.
+//
+// Values that compare equal need the same key, so every number is keyed by its
+// float value and dates by the timestamp of the start of their day. Integers
+// beyond 2^53 can share a key with their neighbours, which only makes an index
+// return a few rows too many.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ValueKey {
+    Null,
+    Boolean(bool),
+    Number(u64),
+    String(String),
+    Time(i64),
+    Bytes(Vec<u8>),
+}
+
+// maps a float onto a u64 that sorts in the same order as the float
+fn ordered_bits(value: f64) -> u64 {
+    // -0.0 == 0.0, so they need the same key
+    let value = if value == 0.0 { 0.0 } else { value };
+
+    let bits = value.to_bits();
+
+    if bits >> 63 == 1 {
+        !bits
+    } else {
+        bits | (1 << 63)
+    }
+}
+
+impl From<&TypeValue> for ValueKey {
+    fn from(value: &TypeValue) -> Self {
+        match value {
+            TypeValue::StringTypeValue(v) => ValueKey::String(v.value.clone()),
+            TypeValue::NumberValueType(v) => ValueKey::Number(ordered_bits(v.value)),
+            TypeValue::IntegerValueType(v) => ValueKey::Number(ordered_bits(v.value as f64)),
+            TypeValue::DecimalValueType(v) => ValueKey::Number(ordered_bits(v.to_f64())),
+            TypeValue::BooleanValueType(v) => ValueKey::Boolean(v.value),
+            TypeValue::TimestampValueType(v) => ValueKey::Time(v.micros),
+            // dates too far out to be timestamps are kept at either end
+            TypeValue::DateValueType(v) => ValueKey::Time(v.to_micros().unwrap_or(if v.days < 0 {
+                i64::MIN
+            } else {
+                i64::MAX
+            })),
+            TypeValue::BytesValueType(v) => ValueKey::Bytes(v.value.clone()),
+            TypeValue::NullValueType => ValueKey::Null,
+        }
+    }
+}
+
+impl ValueKey {
+    // whether both keys hold the same type of value
+    pub fn same_type(&self, other: &ValueKey) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    // the smallest key of the same type as this key
+    pub fn type_minimum(&self) -> ValueKey {
+        match self {
+            ValueKey::Null => ValueKey::Null,
+            ValueKey::Boolean(_) => ValueKey::Boolean(false),
+            ValueKey::Number(_) => ValueKey::Number(0),
+            ValueKey::String(_) => ValueKey::String(String::new()),
+            ValueKey::Time(_) => ValueKey::Time(i64::MIN),
+            ValueKey::Bytes(_) => ValueKey::Bytes(vec![]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::number_value::NumberValueType;
+
+    fn number(value: f64) -> ValueKey {
+        ValueKey::from(&TypeValue::NumberValueType(NumberValueType { value }))
+    }
+
+    #[test]
+    fn number_keys_keep_float_ordering() {
+        let values = [
+            f64::NEG_INFINITY,
+            -10.5,
+            -1.0,
+            0.0,
+            0.5,
+            1.0,
+            30.0,
+            f64::INFINITY,
+        ];
+
+        for pair in values.windows(2) {
+            assert!(number(pair[0]) < number(pair[1]));
+        }
+
+        assert_eq!(number(-0.0), number(0.0));
+        assert!(number(f64::NEG_INFINITY) >= number(0.0).type_minimum());
+    }
+}
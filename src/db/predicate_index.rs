@@ -0,0 +1,187 @@
+// Narrows down which selects on a relation can possibly match a row, so only those have their
+// full constraints evaluated. Each select is indexed by a single atom of its constraints (all
+// atoms of an AND chain have to hold, so any one of them will do):
+//     equality atoms (`id = 1`) go into a per column hash index,
+//     range atoms (`age > 30`) go into per column ordered indexes of lower and upper bounds,
+//     selects without a usable atom are checked against every row.
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::db::data::TypeValue;
+use crate::db::models::value_key::ValueKey;
+use crate::models::expression::{Expression, ExpressionAtom, ExpressionBinaryOperator};
+
+#[derive(Debug, Default)]
+pub struct PredicateIndex {
+    // column -> value -> select ids
+    equality: HashMap<String, HashMap<ValueKey, Vec<usize>>>,
+    // column -> bound -> select ids, for `column > bound` and `column >= bound`
+    lower_bounds: HashMap<String, BTreeMap<ValueKey, Vec<usize>>>,
+    // column -> bound -> select ids, for `column < bound` and `column <= bound`
+    upper_bounds: HashMap<String, BTreeMap<ValueKey, Vec<usize>>>,
+    unindexed: Vec<usize>,
+}
+
+// picks the most selective atom to index a select by, equalities before ranges
+fn choose_atom(constraints: Option<&Expression>) -> Option<ExpressionAtom> {
+    let atoms = constraints?.conjunctive_atoms();
+
+    let equality = atoms
+        .iter()
+        .find(|atom| atom.operator == ExpressionBinaryOperator::Eq);
+
+    let range = atoms.iter().find(|atom| {
+        matches!(
+            atom.operator,
+            ExpressionBinaryOperator::Gt
+                | ExpressionBinaryOperator::GtEq
+                | ExpressionBinaryOperator::Lt
+                | ExpressionBinaryOperator::LtEq
+        )
+    });
+
+    // null never compares, so it is no use as a key
+    equality
+        .or(range)
+        .filter(|atom| atom.value != TypeValue::NullValueType)
+        .cloned()
+}
+
+impl PredicateIndex {
+    pub fn new() -> Self {
+        PredicateIndex::default()
+    }
+
+    pub fn insert(&mut self, id: usize, constraints: Option<&Expression>) {
+        let atom = match choose_atom(constraints) {
+            Some(atom) => atom,
+            None => {
+                self.unindexed.push(id);
+                return;
+            }
+        };
+
+        let key = ValueKey::from(&atom.value);
+
+        match atom.operator {
+            ExpressionBinaryOperator::Eq => self
+                .equality
+                .entry(atom.column)
+                .or_default()
+                .entry(key)
+                .or_default()
+                .push(id),
+            ExpressionBinaryOperator::Gt | ExpressionBinaryOperator::GtEq => self
+                .lower_bounds
+                .entry(atom.column)
+                .or_default()
+                .entry(key)
+                .or_default()
+                .push(id),
+            _ => self
+                .upper_bounds
+                .entry(atom.column)
+                .or_default()
+                .entry(key)
+                .or_default()
+                .push(id),
+        }
+    }
+
+    // the ids of every select that could match the row, a superset of the actual matches
+    pub fn candidates(&self, row: &HashMap<String, TypeValue>) -> HashSet<usize> {
+        let mut candidates: HashSet<usize> = self.unindexed.iter().copied().collect();
+
+        for (column, value) in row.iter() {
+            if *value == TypeValue::NullValueType {
+                continue;
+            }
+
+            let key = ValueKey::from(value);
+
+            if let Some(ids) = self
+                .equality
+                .get(column)
+                .and_then(|values| values.get(&key))
+            {
+                candidates.extend(ids);
+            }
+
+            // every lower bound below the value, of the same type
+            if let Some(bounds) = self.lower_bounds.get(column) {
+                for (_, ids) in bounds.range(key.type_minimum()..=key.clone()) {
+                    candidates.extend(ids);
+                }
+            }
+
+            // every upper bound above the value, of the same type
+            if let Some(bounds) = self.upper_bounds.get(column) {
+                for (_, ids) in bounds
+                    .range(key.clone()..)
+                    .take_while(|(bound, _)| bound.same_type(&key))
+                {
+                    candidates.extend(ids);
+                }
+            }
+        }
+
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::number_value::NumberValueType;
+    use crate::db::models::string_value::StringTypeValue;
+    use sqlparser::dialect::GenericDialect;
+    use sqlparser::parser::Parser;
+
+    fn parse_expression(sql: &str) -> Expression {
+        let dialect = GenericDialect {};
+
+        let expr = Parser::new(&dialect)
+            .try_with_sql(sql)
+            .and_then(|mut parser| parser.parse_expr())
+            .expect("Error with parsing the sql");
+
+        Expression::try_from(&expr).expect("Error with converting the expression")
+    }
+
+    fn row(id: f64, name: &str) -> HashMap<String, TypeValue> {
+        HashMap::from([
+            (
+                "id".to_string(),
+                TypeValue::NumberValueType(NumberValueType { value: id }),
+            ),
+            (
+                "name".to_string(),
+                TypeValue::StringTypeValue(StringTypeValue {
+                    value: name.to_string(),
+                }),
+            ),
+        ])
+    }
+
+    #[test]
+    fn candidates_by_equality_and_range() {
+        let mut index = PredicateIndex::new();
+
+        index.insert(0, Some(&parse_expression("id = 1")));
+        index.insert(1, Some(&parse_expression("id = 2 and name = 'b'")));
+        index.insert(2, Some(&parse_expression("id > 5")));
+        index.insert(3, Some(&parse_expression("10 > id")));
+        index.insert(4, Some(&parse_expression("name >= 'm'")));
+        index.insert(5, Some(&parse_expression("id = 1 or id = 2")));
+        index.insert(6, None);
+
+        assert_eq!(
+            index.candidates(&row(1.0, "a")),
+            HashSet::from([0, 3, 5, 6])
+        );
+        assert_eq!(
+            index.candidates(&row(2.0, "z")),
+            HashSet::from([1, 3, 4, 5, 6])
+        );
+        assert_eq!(index.candidates(&row(20.0, "a")), HashSet::from([2, 5, 6]));
+    }
+}
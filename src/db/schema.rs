@@ -0,0 +1,133 @@
+// the schema of a table created with CREATE TABLE, rows of the table are checked against it
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::data::TypeValue;
+use super::errors::DatabaseError;
+use super::models::{
+    date_value::DateValueType, decimal_value::DecimalValueType, number_value::NumberValueType,
+    timestamp_value::TimestampValueType,
+};
+use crate::models::create_table_query::{ColumnDefinition, ColumnType, CreateTableQuery};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct TableSchema {
+    pub table_name: String,
+    pub columns: Vec<ColumnDefinition>,
+}
+
+impl From<CreateTableQuery> for TableSchema {
+    fn from(query: CreateTableQuery) -> Self {
+        TableSchema {
+            table_name: query.table_name,
+            columns: query.columns,
+        }
+    }
+}
+
+// the type of a value, None for null which fits a column of any type
+pub fn value_type(value: &TypeValue) -> Option<ColumnType> {
+    match value {
+        TypeValue::NumberValueType(_) => Some(ColumnType::Number),
+        TypeValue::IntegerValueType(_) => Some(ColumnType::Integer),
+        TypeValue::DecimalValueType(_) => Some(ColumnType::Decimal),
+        TypeValue::BooleanValueType(_) => Some(ColumnType::Boolean),
+        TypeValue::StringTypeValue(_) => Some(ColumnType::String),
+        TypeValue::TimestampValueType(_) => Some(ColumnType::Timestamp),
+        TypeValue::DateValueType(_) => Some(ColumnType::Date),
+        TypeValue::BytesValueType(_) => Some(ColumnType::Bytes),
+        TypeValue::NullValueType => None,
+    }
+}
+
+// Converts a value into the type of a column where nothing is lost on the way: integers into
+// decimals and floats, decimals into floats, dates into timestamps and strings into dates and
+// timestamps they spell out. Anything else is left as it is.
+pub fn coerce(value: TypeValue, column_type: ColumnType) -> TypeValue {
+    let coerced = match (&value, column_type) {
+        (TypeValue::IntegerValueType(v), ColumnType::Decimal) => Some(TypeValue::DecimalValueType(
+            DecimalValueType::new(v.value as i128, 0),
+        )),
+        (TypeValue::IntegerValueType(_) | TypeValue::DecimalValueType(_), ColumnType::Number) => {
+            value
+                .as_f64()
+                .map(|value| TypeValue::NumberValueType(NumberValueType { value }))
+        }
+        (TypeValue::DateValueType(v), ColumnType::Timestamp) => v
+            .to_micros()
+            .map(|micros| TypeValue::TimestampValueType(TimestampValueType { micros })),
+        (TypeValue::StringTypeValue(v), ColumnType::Date) => {
+            DateValueType::parse(&v.value).map(TypeValue::DateValueType)
+        }
+        (TypeValue::StringTypeValue(v), ColumnType::Timestamp) => {
+            TimestampValueType::parse(&v.value).map(TypeValue::TimestampValueType)
+        }
+        _ => None,
+    };
+
+    coerced.unwrap_or(value)
+}
+
+impl TableSchema {
+    pub fn column(&self, name: &str) -> Option<&ColumnDefinition> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    pub fn column_names(&self) -> Vec<String> {
+        self.columns
+            .iter()
+            .map(|column| column.name.clone())
+            .collect()
+    }
+
+    // converts the values of a row into the types of their columns, see coerce
+    pub fn coerce_row(&self, row: &mut HashMap<String, TypeValue>) {
+        for (name, value) in row.iter_mut() {
+            if let Some(column) = self.column(name) {
+                *value = coerce(
+                    std::mem::replace(value, TypeValue::NullValueType),
+                    column.column_type,
+                );
+            }
+        }
+    }
+
+    // Checks a row against the schema, columns the row leaves out are null
+    pub fn validate(&self, row: &HashMap<String, TypeValue>) -> Result<(), DatabaseError> {
+        for (name, value) in row.iter() {
+            let column = match self.column(name) {
+                Some(column) => column,
+                None => {
+                    return Err(DatabaseError::UnknownColumnError(
+                        self.table_name.clone(),
+                        name.clone(),
+                    ))
+                }
+            };
+
+            if let Some(found) = value_type(value) {
+                if found != column.column_type {
+                    return Err(DatabaseError::ColumnTypeError(
+                        self.table_name.clone(),
+                        name.clone(),
+                        column.column_type,
+                        found,
+                    ));
+                }
+            }
+        }
+
+        for column in self.columns.iter() {
+            let is_null = matches!(row.get(&column.name), None | Some(TypeValue::NullValueType));
+
+            if column.not_null && is_null {
+                return Err(DatabaseError::NotNullError(
+                    self.table_name.clone(),
+                    column.name.clone(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
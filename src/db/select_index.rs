@@ -1,13 +1,11 @@
 // this file will hold all of the selects that have been made by other nodes
-use std::{collections::HashMap, sync::mpsc::Sender};
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 
-use crate::models::{
-    insert_query::{self, InsertQuery},
-    select_query::SelectQuery,
-};
+use crate::models::{insert_query::InsertQuery, select_query::SelectQuery};
 
-use super::data::convert_row_to_hashmap;
+use super::data::{convert_row_to_hashmap, TypeValue};
+use super::predicate_index::PredicateIndex;
 
 // pub struct SelectIndexMessage{
 //     pub query: SelectQuery,
@@ -36,34 +34,125 @@ pub struct SelectIndex {
 
     // data model is => HashMap<Relation, Vec<(SelectQuery, Addr)>>
     selects: HashMap<String, Vec<(SelectQuery, String)>>,
+
+    // per relation index over the constraints of the selects above, by position in the Vec
+    predicates: HashMap<String, PredicateIndex>,
+}
+
+impl Default for SelectIndex {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SelectIndex {
+    pub fn new() -> Self {
+        SelectIndex {
+            selects: HashMap::new(),
+            predicates: HashMap::new(),
+        }
+    }
+
+    // rebuilds the index from the selects of another index, i.e. the selects of a snapshot
+    pub fn from_selects(selects: HashMap<String, Vec<(SelectQuery, String)>>) -> Self {
+        let mut index = SelectIndex::new();
 
-    pub fn new() -> Self{
-        SelectIndex{
-            selects: HashMap::new()
+        for (query, addr) in selects.into_values().flatten() {
+            if let Err(e) = index.insert_select(&addr, query) {
+                eprintln!("Error with indexing the select: {:?}", e);
+            }
         }
+
+        index
     }
 
-    // access pattern to get all the addresses subscribed to a
+    // forgets the selects made by a node, i.e. one that has died, its link won't take the changes
+    pub fn remove_addr(&mut self, addr: &str) {
+        let mut selects = std::mem::take(&mut self.selects);
+
+        for select in selects.values_mut() {
+            select.retain(|(_, address)| address != addr);
+        }
+
+        // the predicate index is by position, so it is simplest to build it all again
+        *self = SelectIndex::from_selects(selects);
+    }
+
+    pub fn selects(&self) -> &HashMap<String, Vec<(SelectQuery, String)>> {
+        &self.selects
+    }
+
+    // access pattern to get all the addresses subscribed to a relation, that are interested in
+    // at least one of the inserted rows. Each address is only returned once.
     pub fn get_addr_for_insert(
         &self,
-        insert_query: InsertQuery,
+        insert_query: &InsertQuery,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        let rows: Vec<HashMap<String, TypeValue>> = insert_query
+            .rows
+            .iter()
+            .map(|row| convert_row_to_hashmap(&insert_query.columns, row))
+            .collect();
+
+        self.get_addr_for_rows(&insert_query.table_name, &rows)
+    }
+
+    // Same as get_addr_for_insert, for any rows of a relation. An update is sent to the addresses
+    // interested in the rows before the update as well as after it, so views can retract them.
+    pub fn get_addr_for_rows(
+        &self,
+        relation: &str,
+        rows: &[HashMap<String, TypeValue>],
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        let (selects, predicates) =
+            match (self.selects.get(relation), self.predicates.get(relation)) {
+                (Some(selects), Some(predicates)) => (selects, predicates),
+                _ => return Ok(vec![]),
+            };
+
+        let mut result_vec: Vec<String> = vec![];
+        let mut seen: HashSet<&String> = HashSet::new();
+
+        for row in rows.iter() {
+            // only the selects the predicate index could not rule out get evaluated
+            let mut candidates: Vec<usize> = predicates.candidates(row).into_iter().collect();
+
+            // keeps the result in the order the selects were made
+            candidates.sort_unstable();
+
+            for id in candidates {
+                let (query, addr) = &selects[id];
+
+                if !seen.contains(addr) && query.matches_relation(relation, row) {
+                    seen.insert(addr);
+                    result_vec.push(addr.clone());
+                }
+            }
+        }
+
+        Ok(result_vec)
+    }
+
+    // same as get_addr_for_insert, but evaluates every select on the relation for every row
+    pub fn get_addr_for_insert_scan(
+        &self,
+        insert_query: &InsertQuery,
     ) -> Result<Vec<String>, Box<dyn Error>> {
         let selects = self.selects.get(&insert_query.table_name);
 
         if let Some(select) = selects {
             // super naive algorithm yo...
-            let result_vec = vec![];
+            let mut result_vec: Vec<String> = vec![];
 
             for row in insert_query.rows.iter() {
-                let hash_row = convert_row_to_hashmap(&insert_query.columns, &row);
+                let hash_row = convert_row_to_hashmap(&insert_query.columns, row);
 
-                for sel in select.iter() {
-                    // if sel.0.evaluate(hash_row) {
-                    //     result_vec.push(sel.1);
-                    // }
+                for (query, addr) in select.iter() {
+                    if !result_vec.contains(addr)
+                        && query.matches_relation(&insert_query.table_name, &hash_row)
+                    {
+                        result_vec.push(addr.clone());
+                    }
                 }
             }
 
@@ -73,23 +162,225 @@ impl SelectIndex {
         }
     }
 
-    // insert a select statement, happens when either this node or another node asks to query a subset of data
+    // insert a select statement, happens when either this node or another node asks to query a subset of data.
+    // A joining select is subscribed to both of its relations.
     pub fn insert_select(
         &mut self,
         addr: &str,
         select_query: SelectQuery,
     ) -> Result<(), Box<dyn Error>> {
-        // let current_select = self.selects.get_mut(&select_query.from);
+        for relation in select_query.relations() {
+            let select = self.selects.entry(relation.to_string()).or_default();
+
+            // the same node re-sending the same select should not be routed twice
+            if select
+                .iter()
+                .any(|(query, address)| address == addr && *query == select_query)
+            {
+                continue;
+            }
+
+            // the constraints of a join are over the joined rows, not the rows of a single relation
+            let constraints = match select_query.join {
+                Some(_) => None,
+                None => select_query.constraints.as_ref(),
+            };
+
+            self.predicates
+                .entry(relation.to_string())
+                .or_default()
+                .insert(select.len(), constraints);
 
-        // if let Some(select) = current_select {
-        //     select.push((select_query, addr.to_string()));
-        // } else {
-        //     self.selects.insert(
-        //         select_query.from.to_string(),
-        //         vec![(select_query, addr.to_string())],
-        //     );
-        // }
+            select.push((select_query.clone(), addr.to_string()));
+        }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::number_value::NumberValueType;
+    use sqlparser::ast::Statement::Query;
+    use sqlparser::dialect::GenericDialect;
+    use sqlparser::parser::Parser;
+
+    fn parse_select(sql: &str) -> SelectQuery {
+        let dialect = GenericDialect {};
+
+        let ast = Parser::parse_sql(&dialect, sql).expect("Error with parsing the sql");
+
+        match ast.first() {
+            Some(Query(query)) => {
+                SelectQuery::try_from(&*query.body).expect("Error with getting the Statement")
+            }
+            _ => panic!("No Select Statement found."),
+        }
+    }
+
+    fn insert_ids(ids: &[f64]) -> InsertQuery {
+        InsertQuery {
+            table_name: "customer".to_string(),
+            columns: vec!["id".to_string()],
+            rows: ids
+                .iter()
+                .map(|id| {
+                    vec![Some(TypeValue::NumberValueType(NumberValueType {
+                        value: *id,
+                    }))]
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn get_addr_for_insert_evaluates_constraints() {
+        let mut index = SelectIndex::new();
+
+        index
+            .insert_select(
+                "127.0.0.1:8081",
+                parse_select("select * into c from customer where id = 1;"),
+            )
+            .expect("Could not insert select");
+        index
+            .insert_select(
+                "127.0.0.1:8082",
+                parse_select("select * into c from customer where id > 1;"),
+            )
+            .expect("Could not insert select");
+        index
+            .insert_select(
+                "127.0.0.1:8083",
+                parse_select("select * into c from orders;"),
+            )
+            .expect("Could not insert select");
+
+        assert_eq!(
+            index.get_addr_for_insert(&insert_ids(&[1.0])).unwrap(),
+            vec!["127.0.0.1:8081".to_string()]
+        );
+        assert_eq!(
+            index.get_addr_for_insert(&insert_ids(&[2.0])).unwrap(),
+            vec!["127.0.0.1:8082".to_string()]
+        );
+        assert_eq!(
+            index.get_addr_for_insert(&insert_ids(&[0.0])).unwrap(),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn get_addr_for_insert_deduplicates_addresses() {
+        let mut index = SelectIndex::new();
+
+        index
+            .insert_select(
+                "127.0.0.1:8081",
+                parse_select("select * into c from customer;"),
+            )
+            .expect("Could not insert select");
+        index
+            .insert_select(
+                "127.0.0.1:8081",
+                parse_select("select * into d from customer where id = 2;"),
+            )
+            .expect("Could not insert select");
+
+        assert_eq!(
+            index
+                .get_addr_for_insert(&insert_ids(&[1.0, 2.0, 3.0]))
+                .unwrap(),
+            vec!["127.0.0.1:8081".to_string()]
+        );
+    }
+
+    #[test]
+    fn get_addr_for_insert_agrees_with_scan() {
+        let mut index = SelectIndex::new();
+
+        let selects = [
+            "select * into c from customer where id = 1;",
+            "select * into c from customer where id = 3 and id > 2;",
+            "select * into c from customer where id >= 2;",
+            "select * into c from customer where 2 > id;",
+            "select * into c from customer where id = 1 or id = 4;",
+            "select * into c from customer where id <> 1;",
+            "select * into c from customer;",
+        ];
+
+        for (i, sql) in selects.iter().enumerate() {
+            index
+                .insert_select(&format!("127.0.0.1:{}", 8081 + i), parse_select(sql))
+                .expect("Could not insert select");
+        }
+
+        for id in 0..6 {
+            let insert = insert_ids(&[id as f64]);
+
+            assert_eq!(
+                index.get_addr_for_insert(&insert).unwrap(),
+                index.get_addr_for_insert_scan(&insert).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn removed_addresses_are_not_routed_to() {
+        let mut index = SelectIndex::new();
+
+        for (addr, sql) in [
+            (
+                "127.0.0.1:8081",
+                "select * into c from customer where id = 1;",
+            ),
+            ("127.0.0.1:8082", "select * into c from customer;"),
+            (
+                "127.0.0.1:8081",
+                "select * into c from orders join customer on orders.customer_id = customer.id;",
+            ),
+        ] {
+            index
+                .insert_select(addr, parse_select(sql))
+                .expect("Could not insert select");
+        }
+
+        index.remove_addr("127.0.0.1:8081");
+
+        assert_eq!(
+            index.get_addr_for_insert(&insert_ids(&[1.0])).unwrap(),
+            vec!["127.0.0.1:8082".to_string()]
+        );
+        assert!(!index.selects().contains_key("orders"));
+    }
+
+    #[test]
+    fn join_is_routed_from_both_relations() {
+        let mut index = SelectIndex::new();
+
+        index
+            .insert_select(
+                "127.0.0.1:8081",
+                parse_select(
+                    "select * into c from orders join customer on orders.customer_id = customer.id where customer.id = 5;",
+                ),
+            )
+            .expect("Could not insert select");
+
+        // the where clause is over joined rows, so every customer could join with an order
+        assert_eq!(
+            index.get_addr_for_insert(&insert_ids(&[1.0])).unwrap(),
+            vec!["127.0.0.1:8081".to_string()]
+        );
+        assert_eq!(
+            index
+                .get_addr_for_insert(&InsertQuery {
+                    table_name: "orders".to_string(),
+                    ..insert_ids(&[2.0])
+                })
+                .unwrap(),
+            vec!["127.0.0.1:8081".to_string()]
+        );
+    }
+}
@@ -0,0 +1,261 @@
+// compact snapshots of the state of a node, so a restart only has to replay the log written
+// since the last snapshot instead of every change ever made
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::data::{Db, TypeValue};
+use super::errors::DatabaseError;
+use super::schema::TableSchema;
+use super::select_index::SelectIndex;
+use super::table::Table;
+use super::view::View;
+use super::wal::{crc32, FsyncPolicy, Lsn};
+use crate::models::select_query::SelectQuery;
+
+// the version of the snapshot format, bumped whenever the layout of Snapshot changes
+pub const SNAPSHOT_VERSION: u32 = 9;
+
+// how many snapshots are kept, the older ones are there to fall back on when a newer one can't
+// be read
+const SNAPSHOTS_KEPT: usize = 2;
+
+const SNAPSHOT_MAGIC: &[u8; 4] = b"TRNP";
+
+// magic, version and checksum
+const SNAPSHOT_HEADER_LENGTH: usize = 12;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct Snapshot {
+    // the lsn of the last record of the log the snapshot holds
+    pub lsn: Lsn,
+    pub data: HashMap<String, Table>,
+    pub schemas: HashMap<String, TableSchema>,
+    pub views: HashMap<String, View>,
+    pub view_rows: HashMap<String, Vec<HashMap<String, TypeValue>>>,
+    // the selects other nodes have made against this node, by relation
+    pub selects: HashMap<String, Vec<(SelectQuery, String)>>,
+}
+
+impl Snapshot {
+    pub fn encode(&self) -> Result<Vec<u8>, DatabaseError> {
+        let payload = postcard::to_allocvec(self)?;
+
+        let mut bytes = Vec::with_capacity(SNAPSHOT_HEADER_LENGTH + payload.len());
+        bytes.extend_from_slice(SNAPSHOT_MAGIC);
+        bytes.extend_from_slice(&SNAPSHOT_VERSION.to_be_bytes());
+        bytes.extend_from_slice(&crc32(&payload).to_be_bytes());
+        bytes.extend_from_slice(&payload);
+
+        Ok(bytes)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, DatabaseError> {
+        if bytes.len() < SNAPSHOT_HEADER_LENGTH || &bytes[0..4] != SNAPSHOT_MAGIC {
+            return Err(DatabaseError::CorruptSnapshotError());
+        }
+
+        let version = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+
+        if version != SNAPSHOT_VERSION {
+            return Err(DatabaseError::UnsupportedSnapshotVersionError(version));
+        }
+
+        let checksum = u32::from_be_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]);
+        let payload = &bytes[SNAPSHOT_HEADER_LENGTH..];
+
+        if crc32(payload) != checksum {
+            return Err(DatabaseError::CorruptSnapshotError());
+        }
+
+        postcard::from_bytes(payload).map_err(|_| DatabaseError::CorruptSnapshotError())
+    }
+
+    // Writes the snapshot into the directory and removes the snapshots it replaces, apart from the
+    // ones before it that are kept to fall back on. The snapshot is written next to its final
+    // name first, so a crash never leaves half a snapshot behind.
+    pub fn write<P: AsRef<Path>>(&self, dir: P) -> Result<PathBuf, DatabaseError> {
+        let dir = dir.as_ref();
+
+        fs::create_dir_all(dir)?;
+
+        let path = dir.join(snapshot_file_name(self.lsn));
+        let temporary = path.with_extension("tmp");
+
+        let mut file = File::create(&temporary)?;
+        file.write_all(&self.encode()?)?;
+        file.sync_all()?;
+
+        fs::rename(&temporary, &path)?;
+
+        // makes the rename itself durable
+        File::open(dir)?.sync_all()?;
+
+        let files = snapshot_files(dir)?;
+
+        for (_, older) in files.iter().rev().skip(SNAPSHOTS_KEPT) {
+            if let Err(e) = fs::remove_file(older) {
+                eprintln!("Could not remove the old snapshot {:?}: {:?}", older, e);
+            }
+        }
+
+        Ok(path)
+    }
+
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, DatabaseError> {
+        Snapshot::decode(&fs::read(path)?)
+    }
+
+    // the newest snapshot in the directory that can be read, None if there is none
+    pub fn read_latest<P: AsRef<Path>>(dir: P) -> Result<Option<Self>, DatabaseError> {
+        let dir = dir.as_ref();
+
+        if !dir.exists() {
+            return Ok(None);
+        }
+
+        for (_, path) in snapshot_files(dir)?.into_iter().rev() {
+            match Snapshot::read(&path) {
+                Ok(snapshot) => return Ok(Some(snapshot)),
+                Err(e) => eprintln!("Skipping the snapshot {:?}: {}", path, e),
+            }
+        }
+
+        Ok(None)
+    }
+
+    // The lsn of the oldest snapshot in the directory that can be read, the log has to hold
+    // everything after it for the Db to be restored from any snapshot that is left.
+    pub fn oldest_readable_lsn<P: AsRef<Path>>(dir: P) -> Result<Option<Lsn>, DatabaseError> {
+        for (_, path) in snapshot_files(dir.as_ref())? {
+            match Snapshot::read(&path) {
+                Ok(snapshot) => return Ok(Some(snapshot.lsn)),
+                Err(e) => eprintln!("Skipping the snapshot {:?}: {}", path, e),
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+// Restores the Db and SelectIndex of a node from the latest snapshot in the directory and the log
+// written after it. Selects made against the node after the snapshot are not in the log, the nodes
+// that made them send them again when they reconnect.
+pub fn restore<P: AsRef<Path>, Q: AsRef<Path>>(
+    dir: P,
+    wal_path: Q,
+    policy: FsyncPolicy,
+) -> Result<(Db, SelectIndex), DatabaseError> {
+    let mut snapshot = Snapshot::read_latest(dir)?;
+
+    let selects = snapshot
+        .as_mut()
+        .map(|snapshot| std::mem::take(&mut snapshot.selects))
+        .unwrap_or_default();
+
+    Ok((
+        Db::restore(wal_path, policy, snapshot)?,
+        SelectIndex::from_selects(selects),
+    ))
+}
+
+fn snapshot_file_name(lsn: Lsn) -> String {
+    // zero padded, so the names sort by lsn
+    format!("snapshot-{lsn:020}.snap")
+}
+
+// the snapshots in a directory by their lsn, oldest first
+fn snapshot_files(dir: &Path) -> Result<Vec<(Lsn, PathBuf)>, DatabaseError> {
+    let mut files = vec![];
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        let lsn = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix("snapshot-"))
+            .and_then(|name| name.strip_suffix(".snap"))
+            .and_then(|lsn| lsn.parse::<Lsn>().ok());
+
+        if let Some(lsn) = lsn {
+            files.push((lsn, path));
+        }
+    }
+
+    files.sort();
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::number_value::NumberValueType;
+
+    fn snapshot(lsn: Lsn) -> Snapshot {
+        Snapshot {
+            lsn,
+            data: HashMap::from([("customer".to_string(), {
+                let mut table = Table::new();
+                table.push(&HashMap::from([(
+                    "id".to_string(),
+                    TypeValue::NumberValueType(NumberValueType { value: lsn as f64 }),
+                )]));
+                table
+            })]),
+            schemas: HashMap::new(),
+            views: HashMap::new(),
+            view_rows: HashMap::new(),
+            selects: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn latest_readable_snapshot_is_restored() {
+        let dir = std::env::temp_dir().join(format!("turnip-snapshot-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(Snapshot::read_latest(&dir).expect("Could not read"), None);
+
+        snapshot(1).write(&dir).expect("Could not write");
+        snapshot(3).write(&dir).expect("Could not write");
+        let path = snapshot(7).write(&dir).expect("Could not write");
+
+        // the oldest snapshot was replaced, the one before the latest is kept to fall back on
+        assert_eq!(
+            snapshot_files(&dir)
+                .expect("Could not list")
+                .into_iter()
+                .map(|(lsn, _)| lsn)
+                .collect::<Vec<Lsn>>(),
+            vec![3, 7]
+        );
+        assert_eq!(
+            Snapshot::oldest_readable_lsn(&dir).expect("Could not read"),
+            Some(3)
+        );
+        assert_eq!(
+            Snapshot::read_latest(&dir).expect("Could not read"),
+            Some(snapshot(7))
+        );
+
+        // a snapshot from a newer version of the format is skipped
+        let mut bytes = fs::read(&path).expect("Could not read");
+        bytes[7] += 1;
+        fs::write(dir.join(snapshot_file_name(9)), &bytes).expect("Could not write");
+
+        assert!(matches!(
+            Snapshot::decode(&bytes),
+            Err(DatabaseError::UnsupportedSnapshotVersionError(v)) if v == SNAPSHOT_VERSION + 1
+        ));
+        assert_eq!(
+            Snapshot::read_latest(&dir).expect("Could not read"),
+            Some(snapshot(7))
+        );
+
+        fs::remove_dir_all(&dir).expect("Could not remove the snapshots");
+    }
+}
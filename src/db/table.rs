@@ -0,0 +1,791 @@
+// The columnar storage of a table: every column keeps its values in one typed vector next to a
+// bitmap of which rows are null, instead of every row repeating its column names in a HashMap.
+// Scans only touch the columns a select reads, and comparisons against literals run on the typed
+// vectors directly.
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use super::data::{project_row, TypeValue};
+use super::models::{
+    boolean_value::BooleanValueType, bytes_value::BytesValueType, date_value::DateValueType,
+    decimal_value::DecimalValueType, integer_value::IntegerValueType,
+    number_value::NumberValueType, string_value::StringTypeValue,
+    timestamp_value::TimestampValueType,
+};
+use super::table_index::TableIndex;
+use crate::models::expression::{
+    Expression, ExpressionAtom, ExpressionBinaryOperator, ExpressionEvaluationError,
+};
+use crate::models::projection::ProjectionItem;
+
+// a growable vector of bits
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct Bitmap {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl Bitmap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn filled(len: usize, value: bool) -> Self {
+        let mut bitmap = Bitmap::new();
+
+        for _ in 0..len {
+            bitmap.push(value);
+        }
+
+        bitmap
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn push(&mut self, value: bool) {
+        if self.len.is_multiple_of(64) {
+            self.words.push(0);
+        }
+
+        self.len += 1;
+        self.set(self.len - 1, value);
+    }
+
+    pub fn get(&self, i: usize) -> bool {
+        i < self.len && self.words[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    pub fn set(&mut self, i: usize, value: bool) {
+        if value {
+            self.words[i / 64] |= 1 << (i % 64);
+        } else {
+            self.words[i / 64] &= !(1 << (i % 64));
+        }
+    }
+
+    // keeps the bits whose entry in `keep` is true
+    fn retain(&mut self, keep: &[bool]) {
+        let mut kept = Bitmap::new();
+
+        for (i, _) in keep.iter().enumerate().filter(|(_, keep)| **keep) {
+            kept.push(self.get(i));
+        }
+
+        *self = kept;
+    }
+}
+
+// The values of a column. A column gets the type of the first value put into it, a later value
+// of another type turns it into a column of TypeValues. Null rows hold a placeholder.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+enum ColumnValues {
+    // only nulls so far
+    Empty,
+    Number(Vec<f64>),
+    Integer(Vec<i64>),
+    Decimal(Vec<DecimalValueType>),
+    Boolean(Bitmap),
+    String(Vec<String>),
+    Timestamp(Vec<i64>),
+    Date(Vec<i32>),
+    Bytes(Vec<Vec<u8>>),
+    Mixed(Vec<TypeValue>),
+}
+
+fn retain<T>(values: &mut Vec<T>, keep: &[bool]) {
+    let mut i = 0;
+
+    values.retain(|_| {
+        i += 1;
+        keep[i - 1]
+    });
+}
+
+impl ColumnValues {
+    // a column of the type of the value, with `len` null rows
+    fn typed(value: &TypeValue, len: usize) -> Self {
+        match value {
+            TypeValue::NumberValueType(_) => ColumnValues::Number(vec![0.0; len]),
+            TypeValue::IntegerValueType(_) => ColumnValues::Integer(vec![0; len]),
+            TypeValue::DecimalValueType(_) => {
+                ColumnValues::Decimal(vec![DecimalValueType::new(0, 0); len])
+            }
+            TypeValue::BooleanValueType(_) => ColumnValues::Boolean(Bitmap::filled(len, false)),
+            TypeValue::StringTypeValue(_) => ColumnValues::String(vec![String::new(); len]),
+            TypeValue::TimestampValueType(_) => ColumnValues::Timestamp(vec![0; len]),
+            TypeValue::DateValueType(_) => ColumnValues::Date(vec![0; len]),
+            TypeValue::BytesValueType(_) => ColumnValues::Bytes(vec![vec![]; len]),
+            TypeValue::NullValueType => ColumnValues::Empty,
+        }
+    }
+
+    // whether the value can be kept in this column as it is, nulls always can
+    fn accepts(&self, value: &TypeValue) -> bool {
+        matches!(
+            (self, value),
+            (_, TypeValue::NullValueType)
+                | (ColumnValues::Mixed(_), _)
+                | (ColumnValues::Number(_), TypeValue::NumberValueType(_))
+                | (ColumnValues::Integer(_), TypeValue::IntegerValueType(_))
+                | (ColumnValues::Decimal(_), TypeValue::DecimalValueType(_))
+                | (ColumnValues::Boolean(_), TypeValue::BooleanValueType(_))
+                | (ColumnValues::String(_), TypeValue::StringTypeValue(_))
+                | (ColumnValues::Timestamp(_), TypeValue::TimestampValueType(_))
+                | (ColumnValues::Date(_), TypeValue::DateValueType(_))
+                | (ColumnValues::Bytes(_), TypeValue::BytesValueType(_))
+        )
+    }
+
+    // appends a value the column accepts, nulls append a placeholder
+    fn push(&mut self, value: &TypeValue) {
+        match (self, value) {
+            (ColumnValues::Empty, _) => {}
+            (ColumnValues::Mixed(v), value) => v.push(value.clone()),
+            (ColumnValues::Number(v), TypeValue::NumberValueType(x)) => v.push(x.value),
+            (ColumnValues::Integer(v), TypeValue::IntegerValueType(x)) => v.push(x.value),
+            (ColumnValues::Decimal(v), TypeValue::DecimalValueType(x)) => v.push(x.clone()),
+            (ColumnValues::Boolean(v), TypeValue::BooleanValueType(x)) => v.push(x.value),
+            (ColumnValues::String(v), TypeValue::StringTypeValue(x)) => v.push(x.value.clone()),
+            (ColumnValues::Timestamp(v), TypeValue::TimestampValueType(x)) => v.push(x.micros),
+            (ColumnValues::Date(v), TypeValue::DateValueType(x)) => v.push(x.days),
+            (ColumnValues::Bytes(v), TypeValue::BytesValueType(x)) => v.push(x.value.clone()),
+            (ColumnValues::Number(v), _) => v.push(0.0),
+            (ColumnValues::Integer(v), _) => v.push(0),
+            (ColumnValues::Decimal(v), _) => v.push(DecimalValueType::new(0, 0)),
+            (ColumnValues::Boolean(v), _) => v.push(false),
+            (ColumnValues::String(v), _) => v.push(String::new()),
+            (ColumnValues::Timestamp(v), _) => v.push(0),
+            (ColumnValues::Date(v), _) => v.push(0),
+            (ColumnValues::Bytes(v), _) => v.push(vec![]),
+        }
+    }
+
+    // overwrites the value of a row with a non null value the column accepts
+    fn set(&mut self, i: usize, value: &TypeValue) {
+        match (self, value) {
+            (ColumnValues::Mixed(v), value) => v[i] = value.clone(),
+            (ColumnValues::Number(v), TypeValue::NumberValueType(x)) => v[i] = x.value,
+            (ColumnValues::Integer(v), TypeValue::IntegerValueType(x)) => v[i] = x.value,
+            (ColumnValues::Decimal(v), TypeValue::DecimalValueType(x)) => v[i] = x.clone(),
+            (ColumnValues::Boolean(v), TypeValue::BooleanValueType(x)) => v.set(i, x.value),
+            (ColumnValues::String(v), TypeValue::StringTypeValue(x)) => v[i] = x.value.clone(),
+            (ColumnValues::Timestamp(v), TypeValue::TimestampValueType(x)) => v[i] = x.micros,
+            (ColumnValues::Date(v), TypeValue::DateValueType(x)) => v[i] = x.days,
+            (ColumnValues::Bytes(v), TypeValue::BytesValueType(x)) => v[i] = x.value.clone(),
+            _ => {}
+        }
+    }
+
+    // the value of a row that is not null
+    fn get(&self, i: usize) -> TypeValue {
+        match self {
+            ColumnValues::Empty => TypeValue::NullValueType,
+            ColumnValues::Mixed(v) => v[i].clone(),
+            ColumnValues::Number(v) => TypeValue::NumberValueType(NumberValueType { value: v[i] }),
+            ColumnValues::Integer(v) => {
+                TypeValue::IntegerValueType(IntegerValueType { value: v[i] })
+            }
+            ColumnValues::Decimal(v) => TypeValue::DecimalValueType(v[i].clone()),
+            ColumnValues::Boolean(v) => {
+                TypeValue::BooleanValueType(BooleanValueType { value: v.get(i) })
+            }
+            ColumnValues::String(v) => TypeValue::StringTypeValue(StringTypeValue {
+                value: v[i].clone(),
+            }),
+            ColumnValues::Timestamp(v) => {
+                TypeValue::TimestampValueType(TimestampValueType { micros: v[i] })
+            }
+            ColumnValues::Date(v) => TypeValue::DateValueType(DateValueType { days: v[i] }),
+            ColumnValues::Bytes(v) => TypeValue::BytesValueType(BytesValueType {
+                value: v[i].clone(),
+            }),
+        }
+    }
+
+    fn retain(&mut self, keep: &[bool]) {
+        match self {
+            ColumnValues::Empty => {}
+            ColumnValues::Mixed(v) => retain(v, keep),
+            ColumnValues::Number(v) => retain(v, keep),
+            ColumnValues::Integer(v) => retain(v, keep),
+            ColumnValues::Decimal(v) => retain(v, keep),
+            ColumnValues::Boolean(v) => v.retain(keep),
+            ColumnValues::String(v) => retain(v, keep),
+            ColumnValues::Timestamp(v) => retain(v, keep),
+            ColumnValues::Date(v) => retain(v, keep),
+            ColumnValues::Bytes(v) => retain(v, keep),
+        }
+    }
+
+    // Compares the value of a row against a value without building a TypeValue for the common
+    // cases, anything else is compared as TypeValues
+    fn compare(&self, i: usize, value: &TypeValue) -> Option<Ordering> {
+        match (self, value) {
+            (ColumnValues::Number(v), TypeValue::NumberValueType(x)) => v[i].partial_cmp(&x.value),
+            (ColumnValues::Integer(v), TypeValue::IntegerValueType(x)) => Some(v[i].cmp(&x.value)),
+            (ColumnValues::String(v), TypeValue::StringTypeValue(x)) => Some(v[i].cmp(&x.value)),
+            (ColumnValues::Timestamp(v), TypeValue::TimestampValueType(x)) => {
+                Some(v[i].cmp(&x.micros))
+            }
+            (ColumnValues::Date(v), TypeValue::DateValueType(x)) => Some(v[i].cmp(&x.days)),
+            _ => self.get(i).partial_cmp(value),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+struct Column {
+    name: String,
+    values: ColumnValues,
+    // the rows that are not null
+    valid: Bitmap,
+}
+
+impl Column {
+    // a column of `len` nulls
+    fn new(name: &str, len: usize) -> Self {
+        Column {
+            name: name.to_string(),
+            values: ColumnValues::Empty,
+            valid: Bitmap::filled(len, false),
+        }
+    }
+
+    // makes room for a non null value of another type than the column has
+    fn widen(&mut self, value: &TypeValue) {
+        if self.values.accepts(value) {
+            return;
+        }
+
+        self.values = match self.values {
+            ColumnValues::Empty => ColumnValues::typed(value, self.valid.len()),
+            _ => ColumnValues::Mixed((0..self.valid.len()).map(|i| self.get(i)).collect()),
+        };
+    }
+
+    fn push(&mut self, value: &TypeValue) {
+        self.widen(value);
+        self.values.push(value);
+        self.valid.push(!matches!(value, TypeValue::NullValueType));
+    }
+
+    fn set(&mut self, i: usize, value: &TypeValue) {
+        self.widen(value);
+
+        if !matches!(value, TypeValue::NullValueType) {
+            self.values.set(i, value);
+        }
+
+        self.valid
+            .set(i, !matches!(value, TypeValue::NullValueType));
+    }
+
+    fn get(&self, i: usize) -> TypeValue {
+        if self.valid.get(i) {
+            self.values.get(i)
+        } else {
+            TypeValue::NullValueType
+        }
+    }
+
+    fn retain(&mut self, keep: &[bool]) {
+        self.values.retain(keep);
+        self.valid.retain(keep);
+    }
+
+    // whether the value of a row passes a comparison, null passes nothing but `!=`
+    fn passes(&self, i: usize, atom: &ExpressionAtom) -> bool {
+        // a comparison with null is never true, `!=` included
+        if !self.valid.get(i) {
+            return false;
+        }
+
+        let ordering = self.values.compare(i, &atom.value);
+
+        match atom.operator {
+            ExpressionBinaryOperator::Eq => ordering == Some(Ordering::Equal),
+            ExpressionBinaryOperator::NotEq => ordering != Some(Ordering::Equal),
+            ExpressionBinaryOperator::Gt => ordering == Some(Ordering::Greater),
+            ExpressionBinaryOperator::Lt => ordering == Some(Ordering::Less),
+            ExpressionBinaryOperator::GtEq => {
+                matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+            }
+            ExpressionBinaryOperator::LtEq => {
+                matches!(ordering, Some(Ordering::Less | Ordering::Equal))
+            }
+            _ => true,
+        }
+    }
+}
+
+// The number of comparisons of the expression if it is nothing but a chain of ANDs of comparisons
+// between a column and a value, in which case the atoms of the expression are all there is to it
+fn atom_count(expression: &Expression) -> Option<usize> {
+    match expression {
+        Expression::BinaryOp(left, right, ExpressionBinaryOperator::And) => {
+            Some(atom_count(left)? + atom_count(right)?)
+        }
+        Expression::BinaryOp(left, right, op) if op.is_comparison() => match (&**left, &**right) {
+            (Expression::Identifier(_), Expression::Value(_))
+            | (Expression::Value(_), Expression::Identifier(_)) => Some(1),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct Table {
+    columns: Vec<Column>,
+    len: usize,
+    indexes: Vec<TableIndex>,
+}
+
+impl Table {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn column_names(&self) -> Vec<&str> {
+        self.columns.iter().map(|c| c.name.as_str()).collect()
+    }
+
+    fn column(&self, name: &str) -> Option<&Column> {
+        self.columns.iter().find(|c| c.name == name)
+    }
+
+    // the value of a column in the row at the position, null if the table has no such column
+    fn value(&self, column: &str, i: usize) -> TypeValue {
+        self.column(column)
+            .map(|column| column.get(i))
+            .unwrap_or(TypeValue::NullValueType)
+    }
+
+    pub fn indexes(&self) -> &[TableIndex] {
+        &self.indexes
+    }
+
+    // adds an index, filled with the rows the table already has
+    pub fn create_index(&mut self, mut index: TableIndex) {
+        for i in 0..self.len {
+            index.insert(i, &self.value(&index.column, i));
+        }
+
+        self.indexes.push(index);
+    }
+
+    // the position of a column, which is added (null in every row so far) if the table has none by the name
+    fn column_position(&mut self, name: &str) -> usize {
+        match self.columns.iter().position(|c| c.name == name) {
+            Some(i) => i,
+            None => {
+                self.columns.push(Column::new(name, self.len));
+                self.columns.len() - 1
+            }
+        }
+    }
+
+    // appends a row of the values of the columns, columns the row leaves out are null
+    pub fn push_values(&mut self, columns: &[String], values: &[Option<TypeValue>]) {
+        let positions: Vec<usize> = columns
+            .iter()
+            .take(values.len())
+            .map(|name| self.column_position(name))
+            .collect();
+
+        let mut row = vec![TypeValue::NullValueType; self.columns.len()];
+
+        for (position, value) in positions.into_iter().zip(values) {
+            if let Some(value) = value {
+                row[position] = value.clone();
+            }
+        }
+
+        for (column, value) in self.columns.iter_mut().zip(row.iter()) {
+            column.push(value);
+        }
+
+        for index in self.indexes.iter_mut() {
+            if let Some(position) = self.columns.iter().position(|c| c.name == index.column) {
+                index.insert(self.len, &row[position]);
+            }
+        }
+
+        self.len += 1;
+    }
+
+    pub fn push(&mut self, row: &HashMap<String, TypeValue>) {
+        // sorted, so the columns a row adds are added in the same order every time
+        let mut columns: Vec<&String> = row.keys().collect();
+        columns.sort();
+
+        let columns: Vec<String> = columns.into_iter().cloned().collect();
+        let values: Vec<Option<TypeValue>> = columns.iter().map(|c| row.get(c).cloned()).collect();
+
+        self.push_values(&columns, &values);
+    }
+
+    // the row at the position, with every column of the table
+    pub fn row(&self, i: usize) -> HashMap<String, TypeValue> {
+        self.columns
+            .iter()
+            .map(|column| (column.name.clone(), column.get(i)))
+            .collect()
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = HashMap<String, TypeValue>> + '_ {
+        (0..self.len).map(|i| self.row(i))
+    }
+
+    // The row at the position as the projection materializes it, see project_row. Only the
+    // columns the projection reads are taken out of the table, columns it does not have are null.
+    pub fn project(&self, i: usize, projection: &[ProjectionItem]) -> HashMap<String, TypeValue> {
+        let mut columns = vec![];
+
+        for item in projection.iter() {
+            match item.columns() {
+                Some(read) => columns.extend(read),
+                None => return project_row(projection, &self.row(i)),
+            }
+        }
+
+        let row = columns
+            .into_iter()
+            .map(|name| (name.to_string(), self.value(name, i)))
+            .collect();
+
+        project_row(projection, &row)
+    }
+
+    // Replaces the row at the position
+    pub fn set_row(&mut self, i: usize, row: &HashMap<String, TypeValue>) {
+        let mut names: Vec<&String> = row.keys().collect();
+        names.sort();
+
+        for name in names {
+            self.column_position(name);
+        }
+
+        let old: Vec<TypeValue> = self
+            .indexes
+            .iter()
+            .map(|index| self.value(&index.column, i))
+            .collect();
+
+        for (index, old) in self.indexes.iter_mut().zip(old.iter()) {
+            index.remove(i, old);
+            index.insert(
+                i,
+                row.get(&index.column).unwrap_or(&TypeValue::NullValueType),
+            );
+        }
+
+        for column in self.columns.iter_mut() {
+            column.set(
+                i,
+                row.get(&column.name).unwrap_or(&TypeValue::NullValueType),
+            );
+        }
+    }
+
+    // The positions of the rows matching the constraints. The comparisons of columns against
+    // values that have to hold are checked on the columns first, only the rows left after them
+    // are put together (out of the columns the constraints read) to evaluate the whole constraints.
+    // Constraints that can't be evaluated on a row, e.g. comparing a string with a number, are an
+    // error rather than a row that doesn't match.
+    pub fn filter(
+        &self,
+        constraints: Option<&Expression>,
+    ) -> Result<Vec<usize>, ExpressionEvaluationError> {
+        let constraints = match constraints {
+            Some(constraints) => constraints,
+            None => return Ok((0..self.len).collect()),
+        };
+
+        let atoms = constraints.conjunctive_atoms();
+
+        let mut positions = self
+            .indexed_candidates(&atoms)
+            .unwrap_or_else(|| (0..self.len).collect());
+
+        for atom in atoms.iter() {
+            match self.column(&atom.column) {
+                Some(column) => positions.retain(|i| column.passes(*i, atom)),
+                // a constraint on a column the table doesn't have matches nothing
+                None => return Ok(vec![]),
+            }
+        }
+
+        if atom_count(constraints) == Some(atoms.len()) {
+            return Ok(positions);
+        }
+
+        // Only the columns the constraints read go into the row, columns the table does not have
+        // are left out. The row is reused for every position, only its values change.
+        let columns: Vec<&Column> = constraints
+            .columns()
+            .into_iter()
+            .filter_map(|name| self.column(name))
+            .collect();
+
+        let mut row: HashMap<String, TypeValue> = columns
+            .iter()
+            .map(|column| (column.name.clone(), TypeValue::NullValueType))
+            .collect();
+
+        let mut matching = vec![];
+
+        for i in positions {
+            for column in columns.iter() {
+                if let Some(value) = row.get_mut(&column.name) {
+                    *value = column.get(i);
+                }
+            }
+
+            if constraints.evaluate(&row)? {
+                matching.push(i);
+            }
+        }
+
+        Ok(matching)
+    }
+
+    // The fewest candidates any index on the table has for the atoms, None if no index can
+    // narrow them down
+    fn indexed_candidates(&self, atoms: &[ExpressionAtom]) -> Option<Vec<usize>> {
+        self.indexes
+            .iter()
+            .filter_map(|index| index.lookup(atoms))
+            .min_by_key(|positions| positions.len())
+    }
+
+    // removes the rows at the positions and returns them
+    pub fn remove(&mut self, positions: &[usize]) -> Vec<HashMap<String, TypeValue>> {
+        let mut keep = vec![true; self.len];
+
+        for i in positions {
+            keep[*i] = false;
+        }
+
+        let removed = positions.iter().map(|i| self.row(*i)).collect();
+
+        for column in self.columns.iter_mut() {
+            column.retain(&keep);
+        }
+
+        self.len = keep.iter().filter(|keep| **keep).count();
+
+        let mut removed_positions = positions.to_vec();
+        removed_positions.sort_unstable();
+        removed_positions.dedup();
+
+        for index in self.indexes.iter_mut() {
+            index.remove_positions(&removed_positions);
+        }
+
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::create_index_query::IndexKind;
+    use sqlparser::dialect::GenericDialect;
+    use sqlparser::parser::Parser;
+
+    fn expression(sql: &str) -> Expression {
+        let expr = Parser::new(&GenericDialect {})
+            .try_with_sql(sql)
+            .and_then(|mut parser| parser.parse_expr())
+            .expect("Error with parsing the sql");
+
+        Expression::try_from(&expr).expect("Could not convert expression")
+    }
+
+    fn integer(value: i64) -> TypeValue {
+        TypeValue::IntegerValueType(IntegerValueType { value })
+    }
+
+    fn string(value: &str) -> TypeValue {
+        TypeValue::StringTypeValue(StringTypeValue {
+            value: value.to_string(),
+        })
+    }
+
+    #[test]
+    fn rows_go_in_and_come_out_of_the_columns() {
+        let mut table = Table::new();
+        let columns = vec!["id".to_string(), "region".to_string()];
+
+        table.push_values(&columns, &[Some(integer(1)), Some(string("eu"))]);
+        table.push_values(&columns, &[Some(integer(2)), None]);
+        // a column first seen later is null in the rows before, a value of another type
+        // than the column has so far is kept as it is
+        table.push_values(
+            &["id".to_string(), "price".to_string()],
+            &[Some(string("three")), Some(integer(30))],
+        );
+
+        assert_eq!(table.len(), 3);
+        assert_eq!(table.column_names(), vec!["id", "region", "price"]);
+        assert_eq!(
+            table.row(1),
+            HashMap::from([
+                ("id".to_string(), integer(2)),
+                ("region".to_string(), TypeValue::NullValueType),
+                ("price".to_string(), TypeValue::NullValueType),
+            ])
+        );
+        assert_eq!(table.row(2)["id"], string("three"));
+
+        table.set_row(
+            1,
+            &HashMap::from([
+                ("id".to_string(), integer(2)),
+                ("region".to_string(), string("us")),
+            ]),
+        );
+
+        assert_eq!(
+            table
+                .filter(Some(&expression("region = 'us'")))
+                .expect("Could not filter"),
+            vec![1]
+        );
+        assert_eq!(
+            table
+                .filter(Some(&expression("id > 0 and region = 'eu' or price = 30")))
+                .expect("Could not filter"),
+            vec![0, 2]
+        );
+        assert_eq!(
+            table
+                .filter(Some(&expression("quantity = 1")))
+                .expect("Could not filter"),
+            vec![]
+        );
+
+        // a string can't be added to, the row is not just left out
+        assert!(matches!(
+            table.filter(Some(&expression("id + 1 = 2 or region = 'us'"))),
+            Err(ExpressionEvaluationError::TypeMismatchError(_, _))
+        ));
+
+        assert_eq!(
+            table.remove(&[0, 2]),
+            vec![
+                HashMap::from([
+                    ("id".to_string(), integer(1)),
+                    ("region".to_string(), string("eu")),
+                    ("price".to_string(), TypeValue::NullValueType),
+                ]),
+                HashMap::from([
+                    ("id".to_string(), string("three")),
+                    ("region".to_string(), TypeValue::NullValueType),
+                    ("price".to_string(), integer(30)),
+                ]),
+            ]
+        );
+        assert_eq!(table.len(), 1);
+        assert_eq!(
+            table.project(
+                0,
+                &[
+                    ProjectionItem::Expression(expression("region"), "region".to_string()),
+                    ProjectionItem::Expression(expression("note"), "note".to_string()),
+                    ProjectionItem::Expression(expression("upper(region)"), "loud".to_string()),
+                ]
+            ),
+            HashMap::from([
+                ("region".to_string(), string("us")),
+                ("note".to_string(), TypeValue::NullValueType),
+                ("loud".to_string(), string("US")),
+            ])
+        );
+    }
+
+    #[test]
+    fn indexes_narrow_down_the_rows_and_follow_the_changes() {
+        let columns = vec!["id".to_string(), "region".to_string()];
+        let mut plain = Table::new();
+
+        for (id, region) in [(1, "eu"), (2, "us"), (3, "eu"), (4, "za"), (5, "us")] {
+            plain.push_values(&columns, &[Some(integer(id)), Some(string(region))]);
+        }
+
+        let mut indexed = plain.clone();
+        indexed.create_index(TableIndex::new("id_idx", "id", IndexKind::BTree));
+        indexed.create_index(TableIndex::new("region_idx", "region", IndexKind::Hash));
+
+        let atoms = expression("id >= 5 and region = 'us'").conjunctive_atoms();
+        assert_eq!(indexed.indexed_candidates(&atoms), Some(vec![4]));
+        // bounds on the same column make one range, the bounds themselves are kept
+        let atoms = expression("id > 1 and id < 4").conjunctive_atoms();
+        assert_eq!(indexed.indexed_candidates(&atoms), Some(vec![0, 1, 2, 3]));
+        // a hash index can't answer a range
+        let atoms = expression("region > 'eu'").conjunctive_atoms();
+        assert_eq!(indexed.indexed_candidates(&atoms), None);
+
+        let check = |plain: &Table, indexed: &Table| {
+            for sql in [
+                "id = 3",
+                "id > 2",
+                "id <= 2 and region = 'eu'",
+                "region = 'us' or id = 1",
+                "region != 'us'",
+            ] {
+                assert_eq!(
+                    indexed
+                        .filter(Some(&expression(sql)))
+                        .expect("Could not filter"),
+                    plain
+                        .filter(Some(&expression(sql)))
+                        .expect("Could not filter"),
+                    "{sql}"
+                );
+            }
+        };
+
+        check(&plain, &indexed);
+
+        let updated = HashMap::from([
+            ("id".to_string(), integer(6)),
+            ("region".to_string(), string("us")),
+        ]);
+        plain.set_row(0, &updated);
+        indexed.set_row(0, &updated);
+        check(&plain, &indexed);
+        assert_eq!(
+            indexed
+                .filter(Some(&expression("region = 'us'")))
+                .expect("Could not filter"),
+            vec![0, 1, 4]
+        );
+
+        plain.remove(&[3, 1]);
+        indexed.remove(&[3, 1]);
+        check(&plain, &indexed);
+        assert_eq!(
+            indexed
+                .filter(Some(&expression("id > 2")))
+                .expect("Could not filter"),
+            vec![0, 1, 2]
+        );
+    }
+}
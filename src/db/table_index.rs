@@ -0,0 +1,191 @@
+// Secondary indexes of a table: the positions of the rows by the value of one column. Lookups
+// only narrow down the rows a constraint can match, every candidate is still checked against the
+// constraint, so keys that stand for more than one value (see ValueKey) don't cause wrong results.
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use super::data::TypeValue;
+use super::models::value_key::ValueKey;
+use crate::models::create_index_query::IndexKind;
+use crate::models::expression::{ExpressionAtom, ExpressionBinaryOperator};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+enum IndexEntries {
+    Hash(HashMap<ValueKey, Vec<usize>>),
+    BTree(BTreeMap<ValueKey, Vec<usize>>),
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct TableIndex {
+    pub name: String,
+    pub column: String,
+    entries: IndexEntries,
+}
+
+impl TableIndex {
+    pub fn new(name: &str, column: &str, kind: IndexKind) -> Self {
+        TableIndex {
+            name: name.to_string(),
+            column: column.to_string(),
+            entries: match kind {
+                IndexKind::Hash => IndexEntries::Hash(HashMap::new()),
+                IndexKind::BTree => IndexEntries::BTree(BTreeMap::new()),
+            },
+        }
+    }
+
+    pub fn kind(&self) -> IndexKind {
+        match self.entries {
+            IndexEntries::Hash(_) => IndexKind::Hash,
+            IndexEntries::BTree(_) => IndexKind::BTree,
+        }
+    }
+
+    fn positions_mut(&mut self, key: ValueKey) -> &mut Vec<usize> {
+        match &mut self.entries {
+            IndexEntries::Hash(entries) => entries.entry(key).or_default(),
+            IndexEntries::BTree(entries) => entries.entry(key).or_default(),
+        }
+    }
+
+    // null never matches a comparison, so nulls are not indexed
+    pub fn insert(&mut self, position: usize, value: &TypeValue) {
+        if matches!(value, TypeValue::NullValueType) {
+            return;
+        }
+
+        let positions = self.positions_mut(ValueKey::from(value));
+
+        // positions are kept sorted, rows are mostly appended
+        match positions.last() {
+            Some(last) if *last > position => {
+                let i = positions.partition_point(|p| *p < position);
+                positions.insert(i, position);
+            }
+            _ => positions.push(position),
+        }
+    }
+
+    pub fn remove(&mut self, position: usize, value: &TypeValue) {
+        if matches!(value, TypeValue::NullValueType) {
+            return;
+        }
+
+        let key = ValueKey::from(value);
+
+        let emptied = match &mut self.entries {
+            IndexEntries::Hash(entries) => entries.get_mut(&key),
+            IndexEntries::BTree(entries) => entries.get_mut(&key),
+        }
+        .map(|positions| {
+            positions.retain(|p| *p != position);
+            positions.is_empty()
+        });
+
+        if emptied == Some(true) {
+            match &mut self.entries {
+                IndexEntries::Hash(entries) => entries.remove(&key),
+                IndexEntries::BTree(entries) => entries.remove(&key),
+            };
+        }
+    }
+
+    // Moves every position past the removed (sorted) positions back by as many rows as were
+    // removed before it, after the rows were taken out of the table
+    pub fn remove_positions(&mut self, removed: &[usize]) {
+        let shift = |positions: &mut Vec<usize>| {
+            positions.retain(|p| removed.binary_search(p).is_err());
+
+            for p in positions.iter_mut() {
+                *p -= removed.partition_point(|r| r < p);
+            }
+
+            !positions.is_empty()
+        };
+
+        match &mut self.entries {
+            IndexEntries::Hash(entries) => entries.retain(|_, positions| shift(positions)),
+            IndexEntries::BTree(entries) => entries.retain(|_, positions| shift(positions)),
+        }
+    }
+
+    // The sorted positions of the rows that can pass every comparison on the column of the index,
+    // None if this index can't answer any of them (ranges on a hash index, `!=`). Bounds on the
+    // column are put together into one range, bounds of another type than the first are left to
+    // be checked on the rows.
+    pub fn lookup(&self, atoms: &[ExpressionAtom]) -> Option<Vec<usize>> {
+        let atoms: Vec<&ExpressionAtom> = atoms
+            .iter()
+            .filter(|atom| {
+                atom.column == self.column && !matches!(atom.value, TypeValue::NullValueType)
+            })
+            .collect();
+
+        if let Some(atom) = atoms
+            .iter()
+            .find(|atom| atom.operator == ExpressionBinaryOperator::Eq)
+        {
+            let key = ValueKey::from(&atom.value);
+
+            return Some(
+                match &self.entries {
+                    IndexEntries::Hash(entries) => entries.get(&key),
+                    IndexEntries::BTree(entries) => entries.get(&key),
+                }
+                .cloned()
+                .unwrap_or_default(),
+            );
+        }
+
+        let entries = match &self.entries {
+            IndexEntries::BTree(entries) => entries,
+            IndexEntries::Hash(_) => return None,
+        };
+
+        let mut lower: Option<ValueKey> = None;
+        let mut upper: Option<ValueKey> = None;
+
+        for atom in atoms.iter() {
+            let key = ValueKey::from(&atom.value);
+
+            if let Some(first) = lower.as_ref().or(upper.as_ref()) {
+                if !first.same_type(&key) {
+                    continue;
+                }
+            }
+
+            // the bounds are inclusive, several values can share a key
+            match atom.operator {
+                ExpressionBinaryOperator::Gt | ExpressionBinaryOperator::GtEq => {
+                    lower = Some(lower.map_or(key.clone(), |lower| lower.max(key)));
+                }
+                ExpressionBinaryOperator::Lt | ExpressionBinaryOperator::LtEq => {
+                    upper = Some(upper.map_or(key.clone(), |upper| upper.min(key)));
+                }
+                _ => {}
+            }
+        }
+
+        let mut positions: Vec<usize> = match (lower, upper) {
+            (None, None) => return None,
+            (Some(lower), Some(upper)) if lower > upper => vec![],
+            (Some(lower), Some(upper)) => entries
+                .range(lower..=upper)
+                .flat_map(|(_, positions)| positions.iter().copied())
+                .collect(),
+            (Some(lower), None) => entries
+                .range(lower.clone()..)
+                .take_while(|(k, _)| k.same_type(&lower))
+                .flat_map(|(_, positions)| positions.iter().copied())
+                .collect(),
+            (None, Some(upper)) => entries
+                .range(upper.type_minimum()..=upper)
+                .flat_map(|(_, positions)| positions.iter().copied())
+                .collect(),
+        };
+
+        positions.sort_unstable();
+
+        Some(positions)
+    }
+}
@@ -0,0 +1,266 @@
+// a materialized view, the result of a select that is kept up to date as rows come in
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::aggregate_state::AggregateState;
+use super::data::{project_row, RowUpdate, TypeValue};
+use super::join_state::{qualify_row, JoinState};
+use crate::models::select_query::SelectQuery;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct View {
+    pub query: SelectQuery,
+    aggregate: Option<AggregateState>,
+    join: Option<JoinState>,
+    // The rows every other node gave the view, in the shape add_results takes them, so they can be
+    // taken back out when the node answers the select again. The rows of our own data are not kept,
+    // nobody answers for them.
+    sources: HashMap<String, Vec<HashMap<String, TypeValue>>>,
+}
+
+impl View {
+    // the view together with its initial (empty) table
+    pub fn new(query: SelectQuery) -> (Self, Vec<HashMap<String, TypeValue>>) {
+        let mut table = vec![];
+
+        let aggregate = if query.is_aggregate() {
+            Some(AggregateState::new(&query, &mut table))
+        } else {
+            None
+        };
+
+        let join = query
+            .join
+            .as_ref()
+            .map(|join| JoinState::new(&query.from, join));
+
+        (
+            View {
+                query,
+                aggregate,
+                join,
+                sources: HashMap::new(),
+            },
+            table,
+        )
+    }
+
+    // Adds rows that are known to match the select, in the shape Db::query_data_for_view gives them:
+    // projected rows for plain views, whole rows for aggregating views and the qualified rows
+    // of either relation for joining views. The source is the node they came from, None for our own.
+    pub fn add_results(
+        &mut self,
+        source: Option<&str>,
+        rows: Vec<HashMap<String, TypeValue>>,
+        table: &mut Vec<HashMap<String, TypeValue>>,
+    ) {
+        if let Some(join) = self.join.as_mut() {
+            let joined = rows
+                .iter()
+                .flat_map(|row| join.add_qualified(row.clone()))
+                .collect();
+
+            self.emit(joined, table);
+            self.track(source, rows);
+        } else if self.aggregate.is_some() {
+            let added = self.emit(rows, table);
+            self.track(source, added);
+        } else {
+            self.track(source, rows.clone());
+            table.extend(rows);
+        }
+    }
+
+    // applies rows inserted into a relation the view selects from, returns whether the view changed
+    pub fn apply_insert(
+        &mut self,
+        source: Option<&str>,
+        relation: &str,
+        rows: &[HashMap<String, TypeValue>],
+        table: &mut Vec<HashMap<String, TypeValue>>,
+    ) -> bool {
+        let joined = match self.join.as_mut() {
+            Some(join) => rows
+                .iter()
+                .flat_map(|row| join.add(relation, row.clone()))
+                .collect(),
+            None => {
+                let added = self.emit(rows.to_vec(), table);
+                let changed = !added.is_empty();
+
+                self.track(source, added);
+                return changed;
+            }
+        };
+
+        self.track(
+            source,
+            rows.iter().map(|row| qualify_row(relation, row)).collect(),
+        );
+
+        !self.emit(joined, table).is_empty()
+    }
+
+    // retracts rows deleted from a relation the view selects from, returns whether the view changed
+    pub fn apply_delete(
+        &mut self,
+        source: Option<&str>,
+        relation: &str,
+        rows: &[HashMap<String, TypeValue>],
+        table: &mut Vec<HashMap<String, TypeValue>>,
+    ) -> bool {
+        let joined = match self.join.as_mut() {
+            Some(join) => rows
+                .iter()
+                .flat_map(|row| join.remove(relation, row))
+                .collect(),
+            None => {
+                let removed = self.retract(rows.to_vec(), table);
+                let changed = !removed.is_empty();
+
+                self.untrack(source, &removed);
+                return changed;
+            }
+        };
+
+        self.untrack(
+            source,
+            &rows
+                .iter()
+                .map(|row| qualify_row(relation, row))
+                .collect::<Vec<_>>(),
+        );
+
+        !self.retract(joined, table).is_empty()
+    }
+
+    // an updated row is retracted as it was before the update and added as it is after it
+    pub fn apply_update(
+        &mut self,
+        source: Option<&str>,
+        relation: &str,
+        changes: &[RowUpdate],
+        table: &mut Vec<HashMap<String, TypeValue>>,
+    ) -> bool {
+        let (old, new): (Vec<_>, Vec<_>) = changes.iter().cloned().unzip();
+
+        let retracted = self.apply_delete(source, relation, &old, table);
+        let added = self.apply_insert(source, relation, &new, table);
+
+        retracted || added
+    }
+
+    // takes every row a node gave the view back out, returns whether the view changed
+    pub fn clear(&mut self, source: &str, table: &mut Vec<HashMap<String, TypeValue>>) -> bool {
+        let rows = self.sources.remove(source).unwrap_or_default();
+
+        if let Some(join) = self.join.as_mut() {
+            let joined = rows
+                .iter()
+                .flat_map(|row| join.remove_qualified(row))
+                .collect();
+
+            !self.retract(joined, table).is_empty()
+        } else if self.aggregate.is_some() {
+            !self.retract(rows, table).is_empty()
+        } else {
+            // the rows of a plain view are kept as they were projected
+            for row in rows.iter() {
+                remove_one(table, row);
+            }
+
+            !rows.is_empty()
+        }
+    }
+
+    fn track(&mut self, source: Option<&str>, rows: Vec<HashMap<String, TypeValue>>) {
+        if let Some(source) = source {
+            self.sources
+                .entry(source.to_string())
+                .or_default()
+                .extend(rows);
+        }
+    }
+
+    fn untrack(&mut self, source: Option<&str>, rows: &[HashMap<String, TypeValue>]) {
+        if let Some(kept) = source.and_then(|source| self.sources.get_mut(source)) {
+            for row in rows {
+                remove_one(kept, row);
+            }
+        }
+    }
+
+    // Folds (joined) rows that match the select into the view. Returns the rows that went in, as
+    // whole rows for aggregating views and projected rows otherwise.
+    fn emit(
+        &mut self,
+        rows: Vec<HashMap<String, TypeValue>>,
+        table: &mut Vec<HashMap<String, TypeValue>>,
+    ) -> Vec<HashMap<String, TypeValue>> {
+        let matching: Vec<HashMap<String, TypeValue>> = rows
+            .into_iter()
+            .filter(|row| self.query.matches(row))
+            .collect();
+
+        match self.aggregate.as_mut() {
+            Some(state) => {
+                for row in matching.iter() {
+                    state.add_row(&self.query, row, table);
+                }
+
+                matching
+            }
+            None => {
+                let projected: Vec<HashMap<String, TypeValue>> = matching
+                    .iter()
+                    .map(|row| project_row(&self.query.projection, row))
+                    .collect();
+
+                table.extend(projected.iter().cloned());
+
+                projected
+            }
+        }
+    }
+
+    // takes (joined) rows that match the select back out of the view, returns them like emit does
+    fn retract(
+        &mut self,
+        rows: Vec<HashMap<String, TypeValue>>,
+        table: &mut Vec<HashMap<String, TypeValue>>,
+    ) -> Vec<HashMap<String, TypeValue>> {
+        let matching: Vec<HashMap<String, TypeValue>> = rows
+            .into_iter()
+            .filter(|row| self.query.matches(row))
+            .collect();
+
+        match self.aggregate.as_mut() {
+            Some(state) => {
+                for row in matching.iter() {
+                    state.remove_row(&self.query, row, table);
+                }
+
+                matching
+            }
+            None => {
+                let projected: Vec<HashMap<String, TypeValue>> = matching
+                    .iter()
+                    .map(|row| project_row(&self.query.projection, row))
+                    .collect();
+
+                for row in projected.iter() {
+                    remove_one(table, row);
+                }
+
+                projected
+            }
+        }
+    }
+}
+
+// rows are not unique, so only one of the equal rows goes
+fn remove_one(rows: &mut Vec<HashMap<String, TypeValue>>, row: &HashMap<String, TypeValue>) {
+    if let Some(position) = rows.iter().position(|r| r == row) {
+        rows.remove(position);
+    }
+}
@@ -0,0 +1,304 @@
+// the write-ahead log of a Db: every change is appended here before it is applied, so the
+// state of the Db can be rebuilt by replaying the log after a restart or a crash
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use super::data::{RowUpdate, TypeValue};
+use super::errors::DatabaseError;
+use crate::models::{
+    create_index_query::CreateIndexQuery, create_table_query::CreateTableQuery,
+    delete_query::DeleteQuery, drop_view_query::DropViewQuery, insert_query::InsertQuery,
+    select_query::SelectQuery, update_query::UpdateQuery,
+};
+
+// length and checksum of a record
+const WAL_RECORD_HEADER_LENGTH: usize = 8;
+
+// log sequence number, the position of a record in the log
+pub type Lsn = u64;
+
+// A change to a Db, one for every method of Db that changes it
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub enum WalRecord {
+    CreateTable(CreateTableQuery),
+    CreateIndex(CreateIndexQuery),
+    Insert(InsertQuery),
+    Update(UpdateQuery),
+    Delete(DeleteQuery),
+
+    CreateView(SelectQuery),
+    DropView(DropViewQuery),
+    // the changes to views below come from another node, they carry the node they came from
+    InsertIntoView(String, String, Vec<HashMap<String, TypeValue>>),
+    ClearViewResults(String, String),
+    MaterializeInsert(String, InsertQuery),
+    MaterializeUpdate(String, String, Vec<RowUpdate>),
+    MaterializeDelete(String, String, Vec<HashMap<String, TypeValue>>),
+}
+
+// When appended records are forced to disk. Whatever is not synced yet is lost if the machine
+// (not just the node) goes down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsyncPolicy {
+    // every record is synced before the change is applied
+    Always,
+    // records are synced every n records
+    Batch(usize),
+    // syncing is left to the OS
+    Never,
+}
+
+#[derive(Debug)]
+pub struct Wal {
+    path: PathBuf,
+    file: File,
+    policy: FsyncPolicy,
+    next_lsn: Lsn,
+    unsynced: usize,
+}
+
+impl Wal {
+    // Opens (or creates) the log at the path, returns it with the records in it. A torn or
+    // corrupt record ends the log, it and everything after it is cut off.
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        policy: FsyncPolicy,
+    ) -> Result<(Self, Vec<(Lsn, WalRecord)>), DatabaseError> {
+        if let Some(parent) = path.as_ref().parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+
+        let mut bytes = vec![];
+        file.read_to_end(&mut bytes)?;
+
+        let (records, valid_length) = read_records(&bytes);
+
+        if valid_length < bytes.len() {
+            eprintln!(
+                "Cutting off {} bytes of torn or corrupt records from the log",
+                bytes.len() - valid_length
+            );
+
+            file.set_len(valid_length as u64)?;
+            file.sync_all()?;
+        }
+
+        file.seek(SeekFrom::Start(valid_length as u64))?;
+
+        let next_lsn = records.last().map(|(lsn, _)| lsn + 1).unwrap_or(1);
+
+        Ok((
+            Wal {
+                path: path.as_ref().to_path_buf(),
+                file,
+                policy,
+                next_lsn,
+                unsynced: 0,
+            },
+            records,
+        ))
+    }
+
+    // appends a record, synced according to the fsync policy, and returns its lsn
+    pub fn append(&mut self, record: &WalRecord) -> Result<Lsn, DatabaseError> {
+        let lsn = self.next_lsn;
+
+        self.file.write_all(&encode_record(lsn, record)?)?;
+
+        self.next_lsn += 1;
+        self.unsynced += 1;
+
+        match self.policy {
+            FsyncPolicy::Always => self.sync()?,
+            FsyncPolicy::Batch(n) if self.unsynced >= n => self.sync()?,
+            _ => {}
+        }
+
+        Ok(lsn)
+    }
+
+    // forces every appended record to disk
+    pub fn sync(&mut self) -> Result<(), DatabaseError> {
+        self.file.sync_data()?;
+        self.unsynced = 0;
+
+        Ok(())
+    }
+
+    // the lsn of the last appended record, 0 for an empty log
+    pub fn last_lsn(&self) -> Lsn {
+        self.next_lsn - 1
+    }
+
+    // Drops the records up to and including the lsn, once a snapshot that was read back holds
+    // them. The rest is written to a new log that then takes the place of this one, so a crash
+    // halfway through leaves the old log as it was. The lsns keep counting up from where they
+    // were, so records after the snapshot can be told apart from records in it.
+    pub fn truncate_through(&mut self, lsn: Lsn) -> Result<(), DatabaseError> {
+        self.sync()?;
+
+        let mut bytes = vec![];
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.read_to_end(&mut bytes)?;
+
+        let mut kept = vec![];
+
+        for (record_lsn, record) in read_records(&bytes).0 {
+            if record_lsn > lsn {
+                kept.extend(encode_record(record_lsn, &record)?);
+            }
+        }
+
+        let temporary = self.path.with_extension("tmp");
+
+        let mut file = File::create(&temporary)?;
+        file.write_all(&kept)?;
+        file.sync_all()?;
+
+        std::fs::rename(&temporary, &self.path)?;
+
+        // makes the rename itself durable
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            File::open(parent)?.sync_all()?;
+        }
+
+        self.file = OpenOptions::new().read(true).write(true).open(&self.path)?;
+        self.file.seek(SeekFrom::End(0))?;
+
+        Ok(())
+    }
+
+    // makes the next record get at least the given lsn, used after restoring a snapshot of an emptied log
+    pub fn skip_to(&mut self, lsn: Lsn) {
+        self.next_lsn = self.next_lsn.max(lsn);
+    }
+}
+
+// a record with its length and checksum in front of it
+fn encode_record(lsn: Lsn, record: &WalRecord) -> Result<Vec<u8>, DatabaseError> {
+    let payload = postcard::to_allocvec(&(lsn, record))?;
+
+    let mut bytes = Vec::with_capacity(WAL_RECORD_HEADER_LENGTH + payload.len());
+    bytes.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(&crc32(&payload).to_be_bytes());
+    bytes.extend_from_slice(&payload);
+
+    Ok(bytes)
+}
+
+// the records of a log up to the first torn or corrupt one, and the length of the log up to there
+fn read_records(bytes: &[u8]) -> (Vec<(Lsn, WalRecord)>, usize) {
+    let mut records = vec![];
+    let mut offset = 0;
+
+    while bytes.len() - offset >= WAL_RECORD_HEADER_LENGTH {
+        let length = u32::from_be_bytes([
+            bytes[offset],
+            bytes[offset + 1],
+            bytes[offset + 2],
+            bytes[offset + 3],
+        ]) as usize;
+        let checksum = u32::from_be_bytes([
+            bytes[offset + 4],
+            bytes[offset + 5],
+            bytes[offset + 6],
+            bytes[offset + 7],
+        ]);
+
+        let start = offset + WAL_RECORD_HEADER_LENGTH;
+
+        let payload = match bytes.get(start..start + length) {
+            Some(payload) if crc32(payload) == checksum => payload,
+            _ => break,
+        };
+
+        match postcard::from_bytes::<(Lsn, WalRecord)>(payload) {
+            Ok(record) => records.push(record),
+            Err(_) => break,
+        }
+
+        offset = start + length;
+    }
+
+    (records, offset)
+}
+
+// CRC-32 (IEEE), catches records that were only partly written
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+
+    for byte in bytes {
+        crc ^= *byte as u32;
+
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::number_value::NumberValueType;
+
+    fn insert(id: f64) -> WalRecord {
+        WalRecord::Insert(InsertQuery {
+            table_name: "customer".to_string(),
+            columns: vec!["id".to_string()],
+            rows: vec![vec![Some(TypeValue::NumberValueType(NumberValueType {
+                value: id,
+            }))]],
+        })
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn torn_records_are_cut_off() {
+        let path = std::env::temp_dir().join(format!("turnip-wal-test-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        {
+            let (mut wal, records) = Wal::open(&path, FsyncPolicy::Always).expect("Could not open");
+            assert_eq!(records, vec![]);
+
+            assert_eq!(wal.append(&insert(1.0)).expect("Could not append"), 1);
+            assert_eq!(wal.append(&insert(2.0)).expect("Could not append"), 2);
+        }
+
+        // a crash halfway through writing the third record
+        let mut bytes = std::fs::read(&path).expect("Could not read");
+        bytes.extend_from_slice(&[0, 0, 0, 100, 1, 2, 3, 4, 5, 6]);
+        std::fs::write(&path, &bytes).expect("Could not write");
+
+        {
+            let (mut wal, records) = Wal::open(&path, FsyncPolicy::Never).expect("Could not open");
+            assert_eq!(records, vec![(1, insert(1.0)), (2, insert(2.0))]);
+
+            assert_eq!(wal.append(&insert(3.0)).expect("Could not append"), 3);
+        }
+
+        let (wal, records) = Wal::open(&path, FsyncPolicy::Never).expect("Could not open");
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(wal.last_lsn(), 3);
+
+        std::fs::remove_file(&path).expect("Could not remove the log");
+    }
+}
@@ -1,5 +1,5 @@
 pub mod db;
-mod messaging;
+pub mod messaging;
 pub mod models;
 pub mod runtime;
 pub mod server;
@@ -1,19 +1,13 @@
-use crate::runtime::TurnipRuntime;
 use std::error::Error;
-
-pub mod db;
-pub mod messaging;
-pub mod models;
-pub mod runtime;
-pub mod server;
+use turnip_rs::runtime::{load_node_id, TurnipRuntime, TurnipRuntimeConfig};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    let mut runtime = TurnipRuntime::new("127.0.0.1:8080");
+    let mut runtime =
+        TurnipRuntime::new(TurnipRuntimeConfig::new(load_node_id("turnip_8080")?, 8080));
 
+    // only returns once the server stops, i.e. when the port is taken
     runtime.run_blocking().await;
 
-    // this will just complete if it is not blocking
-
     Ok(())
 }
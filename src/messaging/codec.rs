@@ -0,0 +1,177 @@
+// framing for the peer tcp protocol, every message on the wire looks like:
+//
+//     [ length: u32 (big endian) ][ payload: `length` bytes ]
+//
+// where the payload is a postcard encoded `Message`. the frames aren't versioned, the version of
+// the messages is agreed on in the handshake (see `PROTOCOL_VERSION`).
+use super::errors::FrameError;
+
+// 4 bytes of length prefix
+pub const FRAME_HEADER_LENGTH: usize = 4;
+
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 8 * 1024 * 1024;
+
+pub fn encode_frame(payload: &[u8], max_frame_size: usize) -> Result<Vec<u8>, FrameError> {
+    if payload.len() > max_frame_size || payload.len() > u32::MAX as usize {
+        return Err(FrameError::FrameTooLargeError(
+            payload.len(),
+            max_frame_size,
+        ));
+    }
+
+    let mut frame = Vec::with_capacity(FRAME_HEADER_LENGTH + payload.len());
+
+    frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    frame.extend_from_slice(payload);
+
+    Ok(frame)
+}
+
+// Reassembles frames out of the raw reads of a socket, a single read can contain
+// part of a frame, exactly one frame or several frames.
+#[derive(Debug)]
+pub struct FrameDecoder {
+    buffer: Vec<u8>,
+    max_frame_size: usize,
+}
+
+impl Default for FrameDecoder {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_FRAME_SIZE)
+    }
+}
+
+impl FrameDecoder {
+    pub fn new(max_frame_size: usize) -> Self {
+        FrameDecoder {
+            buffer: vec![],
+            max_frame_size,
+        }
+    }
+
+    pub fn extend(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    // returns the payload of the next complete frame, or None if more bytes are needed
+    pub fn next_frame(&mut self) -> Result<Option<Vec<u8>>, FrameError> {
+        if self.buffer.len() < FRAME_HEADER_LENGTH {
+            return Ok(None);
+        }
+
+        let length = u32::from_be_bytes([
+            self.buffer[0],
+            self.buffer[1],
+            self.buffer[2],
+            self.buffer[3],
+        ]) as usize;
+
+        // check the header before buffering the body, so an oversized frame is rejected early
+        if length > self.max_frame_size {
+            return Err(FrameError::FrameTooLargeError(length, self.max_frame_size));
+        }
+
+        if self.buffer.len() < FRAME_HEADER_LENGTH + length {
+            return Ok(None);
+        }
+
+        let payload = self.buffer[FRAME_HEADER_LENGTH..FRAME_HEADER_LENGTH + length].to_vec();
+
+        self.buffer.drain(..FRAME_HEADER_LENGTH + length);
+
+        Ok(Some(payload))
+    }
+
+    // to be called once the connection has closed, errors if a partial frame was left behind
+    pub fn finish(&self) -> Result<(), FrameError> {
+        if self.buffer.is_empty() {
+            Ok(())
+        } else {
+            Err(FrameError::TruncatedFrameError(self.buffer.len()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_and_decode_frame() {
+        let frame = encode_frame(b"hello", DEFAULT_MAX_FRAME_SIZE).expect("Could not encode");
+
+        assert_eq!(frame, vec![0, 0, 0, 5, b'h', b'e', b'l', b'l', b'o']);
+
+        let mut decoder = FrameDecoder::default();
+
+        decoder.extend(&frame);
+
+        assert_eq!(decoder.next_frame(), Ok(Some(b"hello".to_vec())));
+        assert_eq!(decoder.next_frame(), Ok(None));
+        assert_eq!(decoder.finish(), Ok(()));
+    }
+
+    #[test]
+    fn decode_frame_split_across_reads() {
+        let frame = encode_frame(b"hello world", DEFAULT_MAX_FRAME_SIZE).expect("Could not encode");
+
+        let mut decoder = FrameDecoder::default();
+
+        decoder.extend(&frame[..3]);
+        assert_eq!(decoder.next_frame(), Ok(None));
+
+        decoder.extend(&frame[3..8]);
+        assert_eq!(decoder.next_frame(), Ok(None));
+
+        decoder.extend(&frame[8..]);
+        assert_eq!(decoder.next_frame(), Ok(Some(b"hello world".to_vec())));
+    }
+
+    #[test]
+    fn decode_multiple_frames_in_one_read() {
+        let mut bytes = encode_frame(b"first", DEFAULT_MAX_FRAME_SIZE).expect("Could not encode");
+        bytes.extend(encode_frame(b"", DEFAULT_MAX_FRAME_SIZE).expect("Could not encode"));
+        bytes.extend(encode_frame(b"third", DEFAULT_MAX_FRAME_SIZE).expect("Could not encode"));
+
+        let mut decoder = FrameDecoder::default();
+
+        decoder.extend(&bytes);
+
+        assert_eq!(decoder.next_frame(), Ok(Some(b"first".to_vec())));
+        assert_eq!(decoder.next_frame(), Ok(Some(vec![])));
+        assert_eq!(decoder.next_frame(), Ok(Some(b"third".to_vec())));
+        assert_eq!(decoder.next_frame(), Ok(None));
+    }
+
+    #[test]
+    fn reject_oversized_frame() {
+        assert_eq!(
+            encode_frame(&[0; 16], 8),
+            Err(FrameError::FrameTooLargeError(16, 8))
+        );
+
+        let frame = encode_frame(&[0; 16], DEFAULT_MAX_FRAME_SIZE).expect("Could not encode");
+
+        let mut decoder = FrameDecoder::new(8);
+
+        // only the header is needed to reject the frame
+        decoder.extend(&frame[..FRAME_HEADER_LENGTH]);
+
+        assert_eq!(
+            decoder.next_frame(),
+            Err(FrameError::FrameTooLargeError(16, 8))
+        );
+    }
+
+    #[test]
+    fn detect_truncated_frame() {
+        let frame = encode_frame(b"hello", DEFAULT_MAX_FRAME_SIZE).expect("Could not encode");
+
+        let mut decoder = FrameDecoder::default();
+
+        decoder.extend(&frame[..6]);
+
+        assert_eq!(decoder.next_frame(), Ok(None));
+        assert_eq!(decoder.finish(), Err(FrameError::TruncatedFrameError(6)));
+    }
+}
@@ -0,0 +1,10 @@
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum FrameError {
+    #[error("Frame of {0} bytes exceeds the maximum frame size of {1} bytes.")]
+    FrameTooLargeError(usize, usize),
+
+    #[error("Connection closed with {0} bytes of an incomplete frame still buffered.")]
+    TruncatedFrameError(usize),
+}
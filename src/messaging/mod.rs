@@ -1,9 +1,139 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::db::data::{RowUpdate, TypeValue};
 use crate::models::{insert_query::InsertQuery, select_query::SelectQuery};
 
-#[derive(Deserialize, Serialize)]
+pub mod codec;
+pub mod errors;
+pub mod subscription;
+
+// Ties the results of a select back to the select that asked for them
+pub type CorrelationId = u64;
+
+// The id every node introduces itself with, the runtime knows its peers by it rather than by the
+// addresses of their links
+pub type NodeId = String;
+
+// the version of the messages below and of their framing, nodes speaking different versions don't
+// link up. this is the only version on the wire, bump it whenever either changes
+pub const PROTOCOL_VERSION: u16 = 3;
+
+// rows per QueryResult message, keeps the frames of large results small
+pub const RESULT_BATCH_SIZE: usize = 256;
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub enum Message {
-    Select(SelectQuery),
+    // the first message on every link, in both directions. kept as the first variant so a node
+    // can still read the handshake of a node on another version and tell it apart
+    Handshake(Handshake),
+
+    Select(CorrelationId, SelectQuery),
     Insert(InsertQuery),
+    // rows of a table that were updated, as they were before and after the update
+    Update(String, Vec<RowUpdate>),
+    // rows that were deleted from a table
+    Delete(String, Vec<HashMap<String, TypeValue>>),
+
+    // the node is about to answer the select, whatever it sent for the select before is replaced by
+    // the answer that follows
+    StartOfResults(CorrelationId),
+    // a batch of the rows answering a select, there can be any number of these per select
+    QueryResult(CorrelationId, Vec<HashMap<String, TypeValue>>),
+    // the node has sent all of its rows for the select
+    EndOfResults(CorrelationId),
+    // the node could not answer the select
+    QueryError(CorrelationId, String),
+
+    // the address the sending node listens on and the addresses of the nodes it knows of, handled
+    // by the runtime itself
+    Gossip(String, Vec<String>),
+
+    // a heartbeat, only there so the peer hears from us when we have nothing else to say
+    Ping,
+}
+
+// Who the node on the other end of a link is
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Handshake {
+    pub node_id: NodeId,
+    // the address the node listens on
+    pub listen_addr: String,
+    pub protocol_version: u16,
+}
+
+// The kind of a message without its contents, used to filter subscriptions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageKind {
+    Select,
+    Insert,
+    Update,
+    Delete,
+    StartOfResults,
+    QueryResult,
+    EndOfResults,
+    QueryError,
+    Gossip,
+    Ping,
+    Handshake,
+}
+
+impl Message {
+    pub fn kind(&self) -> MessageKind {
+        match self {
+            Message::Select(_, _) => MessageKind::Select,
+            Message::Insert(_) => MessageKind::Insert,
+            Message::Update(_, _) => MessageKind::Update,
+            Message::Delete(_, _) => MessageKind::Delete,
+            Message::StartOfResults(_) => MessageKind::StartOfResults,
+            Message::QueryResult(_, _) => MessageKind::QueryResult,
+            Message::EndOfResults(_) => MessageKind::EndOfResults,
+            Message::QueryError(_, _) => MessageKind::QueryError,
+            Message::Gossip(_, _) => MessageKind::Gossip,
+            Message::Ping => MessageKind::Ping,
+            Message::Handshake(_) => MessageKind::Handshake,
+        }
+    }
+}
+
+// The messages answering a select: the start of the results, the rows in batches and the end of
+// the results
+pub fn select_reply(id: CorrelationId, rows: Vec<HashMap<String, TypeValue>>) -> Vec<Message> {
+    let mut messages = vec![Message::StartOfResults(id)];
+
+    messages.extend(
+        rows.chunks(RESULT_BATCH_SIZE)
+            .map(|batch| Message::QueryResult(id, batch.to_vec())),
+    );
+
+    messages.push(Message::EndOfResults(id));
+
+    messages
+}
+
+// Ids only have to be unique on the node making the selects. Starting from the clock keeps a
+// restarted node from mistaking late results of its old selects for results of its new ones.
+pub fn next_correlation_id() -> CorrelationId {
+    static NEXT_ID: OnceLock<AtomicU64> = OnceLock::new();
+
+    NEXT_ID
+        .get_or_init(|| {
+            AtomicU64::new(
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_nanos() as u64)
+                    .unwrap_or(1),
+            )
+        })
+        .fetch_add(1, Ordering::Relaxed)
+}
+
+// A decoded message together with the peer that sent it, by its node id
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceivedMessage {
+    pub addr: NodeId,
+    pub message: Message,
 }
@@ -0,0 +1,102 @@
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use super::{MessageKind, ReceivedMessage};
+
+// A typed view over the runtime's broadcast of received messages,
+// optionally only yielding messages of the given kinds.
+#[derive(Debug)]
+pub struct MessageSubscription {
+    receiver: broadcast::Receiver<ReceivedMessage>,
+    kinds: Option<Vec<MessageKind>>,
+}
+
+impl MessageSubscription {
+    pub fn new(
+        receiver: broadcast::Receiver<ReceivedMessage>,
+        kinds: Option<Vec<MessageKind>>,
+    ) -> Self {
+        MessageSubscription { receiver, kinds }
+    }
+
+    pub fn accepts(&self, kind: MessageKind) -> bool {
+        match &self.kinds {
+            Some(kinds) => kinds.contains(&kind),
+            None => true,
+        }
+    }
+
+    // waits for the next message matching this subscription, None once the runtime has shut down
+    pub async fn recv(&mut self) -> Option<ReceivedMessage> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(received) => {
+                    if self.accepts(received.message.kind()) {
+                        return Some(received);
+                    }
+                }
+                Err(RecvError::Lagged(n)) => {
+                    eprintln!("Subscription lagged behind, skipped {n} messages");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messaging::Message;
+    use crate::models::insert_query::InsertQuery;
+    use crate::models::projection::ProjectionItem;
+    use crate::models::select_query::SelectQuery;
+
+    fn insert_message() -> ReceivedMessage {
+        ReceivedMessage {
+            addr: "127.0.0.1:8080".to_string(),
+            message: Message::Insert(InsertQuery {
+                table_name: "customer".to_string(),
+                columns: vec![],
+                rows: vec![],
+            }),
+        }
+    }
+
+    fn select_message() -> ReceivedMessage {
+        ReceivedMessage {
+            addr: "127.0.0.1:8081".to_string(),
+            message: Message::Select(
+                1,
+                SelectQuery {
+                    into: "customer_cache".to_string(),
+                    projection: vec![ProjectionItem::Wildcard],
+                    from: "customer".to_string(),
+                    constraints: None,
+                    aggregates: vec![],
+                    group_by: vec![],
+                    join: None,
+                },
+            ),
+        }
+    }
+
+    #[tokio::test]
+    async fn subscription_filters_by_kind() {
+        let (tx, _) = broadcast::channel::<ReceivedMessage>(16);
+
+        let mut selects = MessageSubscription::new(tx.subscribe(), Some(vec![MessageKind::Select]));
+        let mut everything = MessageSubscription::new(tx.subscribe(), None);
+
+        tx.send(insert_message()).expect("Could not send");
+        tx.send(select_message()).expect("Could not send");
+
+        drop(tx);
+
+        assert_eq!(selects.recv().await, Some(select_message()));
+        assert_eq!(selects.recv().await, None);
+
+        assert_eq!(everything.recv().await, Some(insert_message()));
+        assert_eq!(everything.recv().await, Some(select_message()));
+        assert_eq!(everything.recv().await, None);
+    }
+}
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use sqlparser::ast::{Function, FunctionArg, FunctionArgExpr};
+
+use super::errors::StatementError;
+use super::expression::Expression;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+pub enum AggregateFunction {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl AggregateFunction {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "count" => Some(AggregateFunction::Count),
+            "sum" => Some(AggregateFunction::Sum),
+            "avg" => Some(AggregateFunction::Avg),
+            "min" => Some(AggregateFunction::Min),
+            "max" => Some(AggregateFunction::Max),
+            _ => None,
+        }
+    }
+}
+
+// An aggregate in the projection of a select, e.g. `SUM(price) AS total`
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct Aggregate {
+    pub function: AggregateFunction,
+    // what is aggregated, None for COUNT(*)
+    pub argument: Option<Expression>,
+    // the column the aggregate is materialized as
+    pub alias: String,
+}
+
+impl Aggregate {
+    // None if the function is not an aggregate, the column name defaults to the function as written
+    pub fn try_from_function(
+        function: &Function,
+        alias: Option<String>,
+    ) -> Result<Option<Self>, StatementError> {
+        let name = match function.name.0.last() {
+            Some(ident) => ident.value.clone(),
+            None => return Ok(None),
+        };
+
+        let aggregate_function = match AggregateFunction::from_name(&name) {
+            Some(f) => f,
+            None => return Ok(None),
+        };
+
+        // the state kept per group can not support these incrementally
+        if function.distinct || function.over.is_some() {
+            return Err(StatementError::NotImplementedError());
+        }
+
+        let argument = match function.args.as_slice() {
+            [FunctionArg::Unnamed(FunctionArgExpr::Wildcard)]
+                if aggregate_function == AggregateFunction::Count =>
+            {
+                None
+            }
+            [FunctionArg::Unnamed(FunctionArgExpr::Expr(expr))] => {
+                Some(Expression::try_from(expr)?)
+            }
+            _ => return Err(StatementError::NotImplementedError()),
+        };
+
+        Ok(Some(Aggregate {
+            function: aggregate_function,
+            argument,
+            alias: alias.unwrap_or_else(|| function.to_string()),
+        }))
+    }
+}
@@ -0,0 +1,314 @@
+// Arithmetic on values: `+ - * / %` on numbers and dates, and `||` on anything that has a text
+// form. Integers stay integers and decimals stay exact as long as the results fit, floats win over
+// both. Null in, null out.
+use crate::db::data::TypeValue;
+use crate::db::models::{
+    date_value::DateValueType,
+    decimal_value::{DecimalValueType, MAX_DECIMAL_SCALE},
+    integer_value::IntegerValueType,
+    number_value::NumberValueType,
+    string_value::StringTypeValue,
+};
+
+use thiserror::Error;
+
+use super::expression::ExpressionBinaryOperator;
+
+// Why arithmetic on two values failed. The expression the arithmetic is part of turns these into
+// an ArithmeticError naming itself.
+#[derive(Error, Debug, PartialEq)]
+pub enum ArithmeticError {
+    #[error("the values are not numbers")]
+    TypeMismatchError(),
+
+    #[error("division by zero")]
+    DivisionByZeroError(),
+
+    #[error("the result is out of range")]
+    OutOfRangeError(),
+}
+
+// the digits a division of decimals keeps at least
+const DIVISION_SCALE: u32 = 6;
+
+// a number with the type the arithmetic is done in
+enum Number {
+    Integer(i64),
+    Decimal(DecimalValueType),
+    Float(f64),
+}
+
+impl Number {
+    fn of(value: &TypeValue) -> Option<Number> {
+        match value {
+            TypeValue::IntegerValueType(v) => Some(Number::Integer(v.value)),
+            TypeValue::DecimalValueType(v) => Some(Number::Decimal(v.clone())),
+            TypeValue::NumberValueType(v) => Some(Number::Float(v.value)),
+            _ => None,
+        }
+    }
+
+    fn decimal(&self) -> Option<DecimalValueType> {
+        match self {
+            Number::Integer(v) => Some(DecimalValueType::new(*v as i128, 0)),
+            Number::Decimal(v) => Some(v.clone()),
+            Number::Float(_) => None,
+        }
+    }
+
+    fn float(&self) -> f64 {
+        match self {
+            Number::Integer(v) => *v as f64,
+            Number::Decimal(v) => v.to_f64(),
+            Number::Float(v) => *v,
+        }
+    }
+}
+
+fn integer(value: i64) -> TypeValue {
+    TypeValue::IntegerValueType(IntegerValueType { value })
+}
+
+fn float(value: f64) -> TypeValue {
+    TypeValue::NumberValueType(NumberValueType { value })
+}
+
+// Applies an arithmetic operator to two values
+pub fn apply(
+    op: &ExpressionBinaryOperator,
+    left: &TypeValue,
+    right: &TypeValue,
+) -> Result<TypeValue, ArithmeticError> {
+    if matches!(left, TypeValue::NullValueType) || matches!(right, TypeValue::NullValueType) {
+        return Ok(TypeValue::NullValueType);
+    }
+
+    if *op == ExpressionBinaryOperator::StringConcat {
+        return match (left.to_text(), right.to_text()) {
+            (Some(l), Some(r)) => Ok(TypeValue::StringTypeValue(StringTypeValue {
+                value: l + &r,
+            })),
+            _ => Ok(TypeValue::NullValueType),
+        };
+    }
+
+    if let Some(value) = apply_to_dates(op, left, right) {
+        return value;
+    }
+
+    match (Number::of(left), Number::of(right)) {
+        (Some(l), Some(r)) => apply_to_numbers(op, l, r),
+        _ => Err(ArithmeticError::TypeMismatchError()),
+    }
+}
+
+// days added to and taken off dates, and the days between two dates
+fn apply_to_dates(
+    op: &ExpressionBinaryOperator,
+    left: &TypeValue,
+    right: &TypeValue,
+) -> Option<Result<TypeValue, ArithmeticError>> {
+    let date = |days: Option<i64>| match days.and_then(DateValueType::from_days) {
+        Some(date) => Ok(TypeValue::DateValueType(date)),
+        None => Err(ArithmeticError::OutOfRangeError()),
+    };
+
+    match (op, left, right) {
+        (
+            ExpressionBinaryOperator::Plus,
+            TypeValue::DateValueType(d),
+            TypeValue::IntegerValueType(n),
+        )
+        | (
+            ExpressionBinaryOperator::Plus,
+            TypeValue::IntegerValueType(n),
+            TypeValue::DateValueType(d),
+        ) => Some(date((d.days as i64).checked_add(n.value))),
+        (
+            ExpressionBinaryOperator::Minus,
+            TypeValue::DateValueType(d),
+            TypeValue::IntegerValueType(n),
+        ) => Some(date((d.days as i64).checked_sub(n.value))),
+        (
+            ExpressionBinaryOperator::Minus,
+            TypeValue::DateValueType(a),
+            TypeValue::DateValueType(b),
+        ) => Some(Ok(integer(a.days as i64 - b.days as i64))),
+        _ => None,
+    }
+}
+
+fn apply_to_numbers(
+    op: &ExpressionBinaryOperator,
+    left: Number,
+    right: Number,
+) -> Result<TypeValue, ArithmeticError> {
+    let divides = matches!(
+        op,
+        ExpressionBinaryOperator::Divide | ExpressionBinaryOperator::Modulo
+    );
+
+    // like postgres, a division by zero is an error rather than infinity
+    if divides && right.float() == 0.0 {
+        return Err(ArithmeticError::DivisionByZeroError());
+    }
+
+    if let (Number::Integer(l), Number::Integer(r)) = (&left, &right) {
+        let (l, r) = (*l, *r);
+
+        let result = match op {
+            ExpressionBinaryOperator::Plus => l.checked_add(r),
+            ExpressionBinaryOperator::Minus => l.checked_sub(r),
+            ExpressionBinaryOperator::Multiply => l.checked_mul(r),
+            // whole numbers divide into whole numbers, truncated
+            ExpressionBinaryOperator::Divide => l.checked_div(r),
+            ExpressionBinaryOperator::Modulo => l.checked_rem(r),
+            _ => return Err(ArithmeticError::TypeMismatchError()),
+        };
+
+        return match result {
+            Some(value) => Ok(integer(value)),
+            None => Err(ArithmeticError::OutOfRangeError()),
+        };
+    }
+
+    if let (Some(l), Some(r)) = (left.decimal(), right.decimal()) {
+        // what doesn't fit an exact decimal falls back to floats
+        if let Some(result) = apply_to_decimals(op, &l, &r) {
+            return Ok(TypeValue::DecimalValueType(result));
+        }
+    }
+
+    let (l, r) = (left.float(), right.float());
+
+    match op {
+        ExpressionBinaryOperator::Plus => Ok(float(l + r)),
+        ExpressionBinaryOperator::Minus => Ok(float(l - r)),
+        ExpressionBinaryOperator::Multiply => Ok(float(l * r)),
+        ExpressionBinaryOperator::Divide => Ok(float(l / r)),
+        ExpressionBinaryOperator::Modulo => Ok(float(l % r)),
+        _ => Err(ArithmeticError::TypeMismatchError()),
+    }
+}
+
+fn apply_to_decimals(
+    op: &ExpressionBinaryOperator,
+    left: &DecimalValueType,
+    right: &DecimalValueType,
+) -> Option<DecimalValueType> {
+    let aligned = || {
+        let scale = left.scale.max(right.scale);
+        Some((left.rescaled(scale)?, right.rescaled(scale)?, scale))
+    };
+
+    match op {
+        ExpressionBinaryOperator::Plus => {
+            let (l, r, scale) = aligned()?;
+            Some(DecimalValueType::new(l.checked_add(r)?, scale))
+        }
+        ExpressionBinaryOperator::Minus => {
+            let (l, r, scale) = aligned()?;
+            Some(DecimalValueType::new(l.checked_sub(r)?, scale))
+        }
+        ExpressionBinaryOperator::Modulo => {
+            let (l, r, scale) = aligned()?;
+            Some(DecimalValueType::new(l.checked_rem(r)?, scale))
+        }
+        ExpressionBinaryOperator::Multiply => {
+            let mantissa = left.mantissa.checked_mul(right.mantissa)?;
+            let scale = left.scale + right.scale;
+
+            // digits past the most a decimal keeps are cut off
+            match scale.checked_sub(MAX_DECIMAL_SCALE) {
+                Some(excess) if excess > 0 => Some(DecimalValueType::new(
+                    mantissa / 10i128.checked_pow(excess)?,
+                    MAX_DECIMAL_SCALE,
+                )),
+                _ => Some(DecimalValueType::new(mantissa, scale)),
+            }
+        }
+        // l / r at the scale of the result is l * 10^(scale - l.scale + r.scale) / r
+        ExpressionBinaryOperator::Divide => {
+            let scale = left
+                .scale
+                .max(right.scale)
+                .clamp(DIVISION_SCALE, MAX_DECIMAL_SCALE);
+            let factor = 10i128.checked_pow(scale - left.scale + right.scale)?;
+
+            Some(DecimalValueType::new(
+                left.mantissa
+                    .checked_mul(factor)?
+                    .checked_div(right.mantissa)?,
+                scale,
+            ))
+        }
+        _ => None,
+    }
+}
+
+// the value of a number negated, null stays null
+pub fn negate(value: &TypeValue) -> Result<TypeValue, ArithmeticError> {
+    match value {
+        TypeValue::NullValueType => Ok(TypeValue::NullValueType),
+        value if Number::of(value).is_none() => Err(ArithmeticError::TypeMismatchError()),
+        value => value.negated().ok_or(ArithmeticError::OutOfRangeError()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(s: &str) -> TypeValue {
+        TypeValue::DecimalValueType(DecimalValueType::parse(s).expect("Not a decimal"))
+    }
+
+    #[test]
+    fn arithmetic_keeps_the_type_of_the_numbers() {
+        use ExpressionBinaryOperator::*;
+
+        for (op, left, right, expected) in [
+            (Plus, integer(2), integer(3), integer(5)),
+            (Divide, integer(7), integer(2), integer(3)),
+            (Modulo, integer(-7), integer(2), integer(-1)),
+            (Plus, decimal("0.10"), decimal("0.2"), decimal("0.30")),
+            (Multiply, decimal("1.5"), integer(3), decimal("4.5")),
+            (Divide, decimal("1"), integer(3), decimal("0.333333")),
+            (Multiply, float(1.5), integer(2), float(3.0)),
+            (
+                Minus,
+                integer(1),
+                TypeValue::NullValueType,
+                TypeValue::NullValueType,
+            ),
+        ] {
+            let result = apply(&op, &left, &right).expect("Could not apply");
+
+            assert_eq!(result, expected, "{left:?} {op:?} {right:?}");
+            assert_eq!(
+                std::mem::discriminant(&result),
+                std::mem::discriminant(&expected),
+                "{left:?} {op:?} {right:?}"
+            );
+        }
+
+        assert_eq!(
+            apply(&Divide, &integer(1), &integer(0)),
+            Err(ArithmeticError::DivisionByZeroError())
+        );
+        assert_eq!(
+            apply(&Plus, &integer(i64::MAX), &integer(1)),
+            Err(ArithmeticError::OutOfRangeError())
+        );
+        assert_eq!(
+            apply(
+                &Plus,
+                &integer(1),
+                &TypeValue::StringTypeValue(StringTypeValue {
+                    value: "1".to_string()
+                })
+            ),
+            Err(ArithmeticError::TypeMismatchError())
+        );
+    }
+}
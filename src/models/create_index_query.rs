@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+use sqlparser::ast::{
+    Expr,
+    Statement::{self, CreateIndex},
+};
+
+use super::errors::StatementError;
+
+// How an index keeps its keys. Hash indexes only answer equalities, ordered ones ranges as well.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub enum IndexKind {
+    Hash,
+    BTree,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct CreateIndexQuery {
+    pub name: String,
+    pub table_name: String,
+    pub column: String,
+    pub kind: IndexKind,
+    pub if_not_exists: bool,
+}
+
+impl TryFrom<&Statement> for CreateIndexQuery {
+    type Error = StatementError;
+
+    fn try_from(value: &Statement) -> Result<Self, Self::Error> {
+        if let CreateIndex {
+            name,
+            table_name,
+            using,
+            columns,
+            unique,
+            if_not_exists,
+        } = value
+        {
+            // unique indexes would need inserts to be checked against them
+            if *unique {
+                return Err(StatementError::NotImplementedError());
+            }
+
+            // like postgres, indexes are ordered unless asked otherwise
+            let kind = match using.as_ref().map(|i| i.value.to_lowercase()).as_deref() {
+                None | Some("btree") => IndexKind::BTree,
+                Some("hash") => IndexKind::Hash,
+                Some(_) => return Err(StatementError::NotImplementedError()),
+            };
+
+            // only indexes on a single column
+            let column = match columns.as_slice() {
+                [column] => match &column.expr {
+                    Expr::Identifier(i) => i.value.clone(),
+                    _ => return Err(StatementError::NotImplementedError()),
+                },
+                _ => return Err(StatementError::NotImplementedError()),
+            };
+
+            Ok(CreateIndexQuery {
+                name: name.to_string(),
+                table_name: match table_name.0.first() {
+                    Some(v) => Ok(v.value.clone()),
+                    None => Err(StatementError::NotImplementedError()),
+                }?,
+                column,
+                kind,
+                if_not_exists: *if_not_exists,
+            })
+        } else {
+            Err(StatementError::NotImplementedError())
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+use sqlparser::ast::{
+    ColumnDef, ColumnOption, DataType,
+    Statement::{self, CreateTable},
+};
+
+use super::errors::StatementError;
+
+// the type of the values of a column
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub enum ColumnType {
+    // floats
+    Number,
+    Integer,
+    Decimal,
+    Boolean,
+    String,
+    Timestamp,
+    Date,
+    Bytes,
+}
+
+impl TryFrom<&DataType> for ColumnType {
+    type Error = StatementError;
+
+    fn try_from(data_type: &DataType) -> Result<Self, Self::Error> {
+        match data_type {
+            DataType::Float(_) | DataType::Real | DataType::Double | DataType::DoublePrecision => {
+                Ok(ColumnType::Number)
+            }
+
+            DataType::TinyInt(_)
+            | DataType::SmallInt(_)
+            | DataType::MediumInt(_)
+            | DataType::Int(_)
+            | DataType::Integer(_)
+            | DataType::BigInt(_) => Ok(ColumnType::Integer),
+
+            DataType::Numeric(_)
+            | DataType::Decimal(_)
+            | DataType::BigNumeric(_)
+            | DataType::BigDecimal(_)
+            | DataType::Dec(_) => Ok(ColumnType::Decimal),
+
+            DataType::Boolean => Ok(ColumnType::Boolean),
+
+            DataType::Character(_)
+            | DataType::Char(_)
+            | DataType::CharacterVarying(_)
+            | DataType::CharVarying(_)
+            | DataType::Varchar(_)
+            | DataType::Nvarchar(_)
+            | DataType::Uuid
+            | DataType::Text
+            | DataType::String => Ok(ColumnType::String),
+
+            DataType::Timestamp(..) | DataType::Datetime(_) => Ok(ColumnType::Timestamp),
+            DataType::Date => Ok(ColumnType::Date),
+
+            DataType::Binary(_) | DataType::Varbinary(_) | DataType::Blob(_) | DataType::Bytea => {
+                Ok(ColumnType::Bytes)
+            }
+
+            _ => Err(StatementError::UnsupportedDataTypeError(
+                data_type.to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct ColumnDefinition {
+    pub name: String,
+    pub column_type: ColumnType,
+    pub not_null: bool,
+}
+
+impl TryFrom<&ColumnDef> for ColumnDefinition {
+    type Error = StatementError;
+
+    fn try_from(column: &ColumnDef) -> Result<Self, Self::Error> {
+        // a primary key can not be null either
+        let not_null = column.options.iter().any(|option| {
+            matches!(
+                option.option,
+                ColumnOption::NotNull | ColumnOption::Unique { is_primary: true }
+            )
+        });
+
+        Ok(ColumnDefinition {
+            name: column.name.value.clone(),
+            column_type: ColumnType::try_from(&column.data_type)?,
+            not_null,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct CreateTableQuery {
+    pub table_name: String,
+    pub columns: Vec<ColumnDefinition>,
+    pub if_not_exists: bool,
+}
+
+impl TryFrom<&Statement> for CreateTableQuery {
+    type Error = StatementError;
+
+    fn try_from(value: &Statement) -> Result<Self, Self::Error> {
+        if let CreateTable {
+            name,
+            columns,
+            if_not_exists,
+            query,
+            like,
+            clone,
+            ..
+        } = value
+        {
+            // tables made from other tables are not supported
+            if query.is_some() || like.is_some() || clone.is_some() {
+                return Err(StatementError::NotImplementedError());
+            }
+
+            let columns = columns
+                .iter()
+                .map(ColumnDefinition::try_from)
+                .collect::<Result<Vec<ColumnDefinition>, StatementError>>()?;
+
+            Ok(CreateTableQuery {
+                table_name: match name.0.first() {
+                    Some(v) => Ok(v.value.clone()),
+                    None => Err(StatementError::NotImplementedError()),
+                }?,
+                columns,
+                if_not_exists: *if_not_exists,
+            })
+        } else {
+            Err(StatementError::NotImplementedError())
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use sqlparser::ast::Statement::{self, Delete};
+use std::collections::HashMap;
+
+use crate::db::data::TypeValue;
+
+use super::errors::StatementError;
+use super::expression::Expression;
+use super::join::table_name;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct DeleteQuery {
+    pub table_name: String,
+    pub constraints: Option<Expression>,
+}
+
+impl DeleteQuery {
+    // whether a row is deleted by this query, no constraints deletes every row
+    pub fn matches(&self, row: &HashMap<String, TypeValue>) -> bool {
+        match &self.constraints {
+            Some(constraints) => constraints.evaluate(row).unwrap_or(false),
+            None => true,
+        }
+    }
+}
+
+impl TryFrom<&Statement> for DeleteQuery {
+    type Error = StatementError;
+
+    fn try_from(value: &Statement) -> Result<Self, Self::Error> {
+        if let Delete {
+            table_name: relation,
+            using,
+            selection,
+            returning: _,
+        } = value
+        {
+            if using.is_some() {
+                return Err(StatementError::NotImplementedError());
+            }
+
+            let constraints = match selection {
+                Some(expr) => Some(Expression::try_from(expr)?),
+                None => None,
+            };
+
+            Ok(DeleteQuery {
+                table_name: table_name(relation)?,
+                constraints,
+            })
+        } else {
+            Err(StatementError::NotImplementedError())
+        }
+    }
+}
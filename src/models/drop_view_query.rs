@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+use sqlparser::ast::{
+    ObjectType,
+    Statement::{self, Drop},
+};
+
+use super::errors::StatementError;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct DropViewQuery {
+    pub name: String,
+    pub if_exists: bool,
+}
+
+impl TryFrom<&Statement> for DropViewQuery {
+    type Error = StatementError;
+
+    fn try_from(value: &Statement) -> Result<Self, Self::Error> {
+        match value {
+            // one view at a time, tables can't be dropped
+            Drop {
+                object_type: ObjectType::View,
+                if_exists,
+                names,
+                ..
+            } => match names.as_slice() {
+                [name] => Ok(DropViewQuery {
+                    name: match name.0.first() {
+                        Some(v) => Ok(v.value.clone()),
+                        None => Err(StatementError::NotImplementedError()),
+                    }?,
+                    if_exists: *if_exists,
+                }),
+                _ => Err(StatementError::NotImplementedError()),
+            },
+            _ => Err(StatementError::NotImplementedError()),
+        }
+    }
+}
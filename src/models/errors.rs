@@ -1,6 +1,8 @@
 use sqlparser::parser::ParserError;
 use thiserror::Error;
 
+use super::expression::ExpressionConversionError;
+
 #[derive(Error, Debug, PartialEq)]
 pub enum StatementError {
     #[error("Could not correctly parse the query.")]
@@ -14,6 +16,24 @@ pub enum StatementError {
 
     #[error("No `into` parameter specified for Select Query. Each Select needs to have an into parameter specified.")]
     NoIntoSpecifiedForSelect(),
+
+    #[error("Column `{0}` has to be in the GROUP BY clause or be used in an aggregate.")]
+    ColumnNotGroupedError(String),
+
+    #[error("`{0}` is not a relation the select reads from.")]
+    UnknownRelationError(String),
+
+    #[error("Relation `{0}` is aliased, relations can only be referred to by their names.")]
+    AliasNotSupportedError(String),
+
+    #[error("Column `{0}` is not qualified, the columns of a join are referred to as `relation.column`.")]
+    UnqualifiedColumnError(String),
+
+    #[error("Columns of type `{0}` are not supported.")]
+    UnsupportedDataTypeError(String),
+
+    #[error("{0}")]
+    ExpressionError(#[from] ExpressionConversionError),
 }
 
 #[derive(Error, Debug, PartialEq)]
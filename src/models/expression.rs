@@ -1,57 +1,227 @@
 use crate::db::data::TypeValue;
-use crate::db::models::number_value::NumberValueType;
+use crate::db::models::boolean_value::BooleanValueType;
+use crate::db::models::bytes_value::BytesValueType;
+use crate::db::models::date_value::DateValueType;
 use crate::db::models::string_value::StringTypeValue;
+use crate::db::models::timestamp_value::TimestampValueType;
+use crate::db::schema::value_type;
+use crate::models::arithmetic::{self, ArithmeticError};
+use crate::models::functions;
+use crate::models::join::qualified_name;
 
 use serde::{Deserialize, Serialize};
 
-use sqlparser::ast::{BinaryOperator, Expr, Value};
+use sqlparser::ast::{
+    BinaryOperator, DateTimeField, Expr, FunctionArg, FunctionArgExpr, TrimWhereField,
+    UnaryOperator, Value,
+};
 
 use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::fmt;
 use thiserror::Error;
 
 // use sqlparser::tokenizer::Token::{Number, SingleQuotedString, SingleQuotedByteStringLiteral, };
 
-// conversion error
+// Why a sql expression could not be turned into an Expression. Each carries the offending
+// expression as written.
 #[derive(Error, Debug, PartialEq)]
 pub enum ExpressionConversionError {
-    #[error("Conversion Error")]
-    StandardError(),
+    #[error("The operator `{0}` is not supported, in `{1}`.")]
+    UnsupportedOperatorError(String, String),
+
+    #[error("Expressions like `{0}` are not supported.")]
+    UnsupportedExpressionError(String),
+
+    #[error("`{0}` is not a valid literal.")]
+    InvalidLiteralError(String),
+
+    #[error("No function named `{0}` exists, in `{1}`.")]
+    UnknownFunctionError(String, String),
+
+    #[error("`{0}` does not take {1} arguments, in `{2}`.")]
+    ArgumentCountError(String, usize, String),
+
+    #[error("No column named `{0}` exists, in `{1}`.")]
+    UnknownColumnError(String, String),
 }
 
+// Why an Expression could not be evaluated against a row. Each carries the offending expression.
 #[derive(Error, Debug, PartialEq)]
 pub enum ExpressionEvaluationError {
-    #[error("Evaluation Error")]
-    StandardError(),
+    #[error("Mismatched types in `{0}`: {1}.")]
+    TypeMismatchError(String, String),
+
+    #[error("Division by zero in `{0}`.")]
+    DivisionByZeroError(String),
+
+    #[error("The result of `{0}` is out of range.")]
+    OutOfRangeError(String),
+
+    #[error("Could not parse the literal `{0}`.")]
+    ParseError(String),
+
+    #[error("No function named `{0}` exists.")]
+    UnknownFunctionError(String),
+
+    #[error("Could not evaluate `{0}`: {1}.")]
+    InvalidArgumentError(String, String),
 }
 
-#[derive(Deserialize, Serialize, PartialEq, Debug)]
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
 pub enum Expression {
     BinaryOp(Box<Expression>, Box<Expression>, ExpressionBinaryOperator),
     Value(ExpressionValue),
     Identifier(ExpressionIdentifier),
+    Not(Box<Expression>),
+    IsNull(Box<Expression>),
+    IsNotNull(Box<Expression>),
+    // expr, list, negated
+    InList(Box<Expression>, Vec<Expression>, bool),
+    // expr, low, high, negated
+    Between(Box<Expression>, Box<Expression>, Box<Expression>, bool),
+    Like {
+        expr: Box<Expression>,
+        pattern: Box<Expression>,
+        escape: Option<char>,
+        negated: bool,
+        case_insensitive: bool,
+    },
+    // a call of one of the built-in functions, see models::functions
+    Function(String, Vec<Expression>),
+    Negative(Box<Expression>),
 }
 
+// Compares the values of two expressions, unknown (None) if either of them is null
 pub fn compare(
     left: &Expression,
     right: &Expression,
     eval: fn(TypeValue, TypeValue) -> bool,
     values: &HashMap<String, TypeValue>,
-) -> Result<bool, ExpressionEvaluationError> {
-    match (left.resolve(values), right.resolve(values)) {
-        (Ok(tv1), Ok(tv2)) => {
-            println!("Comparing!: {:?} {:?}", tv1, tv2);
-            Ok(eval(tv1, tv2))
+) -> Result<Option<bool>, ExpressionEvaluationError> {
+    match (left.resolve(values)?, right.resolve(values)?) {
+        (TypeValue::NullValueType, _) | (_, TypeValue::NullValueType) => Ok(None),
+        (tv1, tv2) => Ok(Some(eval(tv1, tv2))),
+    }
+}
+
+// the name of the type of a value, for errors
+fn type_name(value: &TypeValue) -> String {
+    match value_type(value) {
+        Some(column_type) => format!("{column_type:?}"),
+        None => "Null".to_string(),
+    }
+}
+
+// the truth of the value of an expression, which has to be a boolean or null
+fn boolean(
+    expression: &Expression,
+    value: TypeValue,
+) -> Result<Option<bool>, ExpressionEvaluationError> {
+    match value {
+        TypeValue::BooleanValueType(b) => Ok(Some(b.value)),
+        TypeValue::NullValueType => Ok(None),
+        value => Err(ExpressionEvaluationError::TypeMismatchError(
+            expression.to_string(),
+            format!("expected a Boolean, got {}", type_name(&value)),
+        )),
+    }
+}
+
+// NOT of a truth value, unknown stays unknown
+fn not(value: Option<bool>) -> Option<bool> {
+    value.map(|v| !v)
+}
+
+// AND of two truth values: false wins over unknown, unknown over true
+fn and(left: Option<bool>, right: Option<bool>) -> Option<bool> {
+    match (left, right) {
+        (Some(false), _) | (_, Some(false)) => Some(false),
+        (Some(true), Some(true)) => Some(true),
+        _ => None,
+    }
+}
+
+// OR of two truth values: true wins over unknown, unknown over false
+fn or(left: Option<bool>, right: Option<bool>) -> Option<bool> {
+    match (left, right) {
+        (Some(true), _) | (_, Some(true)) => Some(true),
+        (Some(false), Some(false)) => Some(false),
+        _ => None,
+    }
+}
+
+// Matches a string against a LIKE pattern, where `%` is any run of characters and `_` any one
+// character. The escape character makes the character after it match only itself.
+pub fn like_matches(value: &str, pattern: &str, escape: Option<char>) -> bool {
+    enum Token {
+        Any,
+        One,
+        Char(char),
+    }
+
+    let mut tokens = vec![];
+    let mut chars = pattern.chars();
+
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            c if Some(c) == escape => Token::Char(chars.next().unwrap_or(c)),
+            '%' => Token::Any,
+            '_' => Token::One,
+            c => Token::Char(c),
+        });
+    }
+
+    let value: Vec<char> = value.chars().collect();
+
+    // the last `%` seen and the position in the value it was tried up to, to backtrack to
+    let (mut v, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while v < value.len() {
+        match tokens.get(t) {
+            Some(Token::Any) => {
+                backtrack = Some((t, v));
+                t += 1;
+            }
+            Some(Token::One) => {
+                v += 1;
+                t += 1;
+            }
+            Some(Token::Char(c)) if *c == value[v] => {
+                v += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // let the last `%` take one more character
+                Some((any, taken)) => {
+                    backtrack = Some((any, taken + 1));
+                    t = any + 1;
+                    v = taken + 1;
+                }
+                None => return false,
+            },
         }
-        _ => Err(ExpressionEvaluationError::StandardError()),
     }
+
+    tokens[t..].iter().all(|token| matches!(token, Token::Any))
 }
 
 impl Expression {
+    // Whether the expression holds for the values. Unknown (a comparison with null) does not hold.
     pub fn evaluate(
         &self,
         values: &HashMap<String, TypeValue>,
     ) -> Result<bool, ExpressionEvaluationError> {
+        Ok(self.truth_value(values)? == Some(true))
+    }
+
+    // The three-valued truth of the expression: true, false or unknown (None) when it depends on
+    // a null. Columns the values leave out are null.
+    pub fn truth_value(
+        &self,
+        values: &HashMap<String, TypeValue>,
+    ) -> Result<Option<bool>, ExpressionEvaluationError> {
         match self {
             Expression::BinaryOp(left, right, op) => {
                 match op {
@@ -76,23 +246,84 @@ impl Expression {
 
                     // compounds
                     ExpressionBinaryOperator::And => {
-                        let result = right.evaluate(values);
-
-                        match (left.evaluate(values), result) {
-                            (Ok(v1), Ok(v2)) => Ok(v1 && v2),
-                            _ => Ok(false),
-                        }
+                        Ok(and(left.truth_value(values)?, right.truth_value(values)?))
                     }
                     ExpressionBinaryOperator::Or => {
-                        match (left.evaluate(values), right.evaluate(values)) {
-                            (Ok(v1), Ok(v2)) => Ok(v1 || v2),
-                            _ => Ok(false),
+                        Ok(or(left.truth_value(values)?, right.truth_value(values)?))
+                    }
+                    ExpressionBinaryOperator::Xor => {
+                        match (left.truth_value(values)?, right.truth_value(values)?) {
+                            (Some(v1), Some(v2)) => Ok(Some(v1 != v2)),
+                            _ => Ok(None),
                         }
                     }
-                    _ => Err(ExpressionEvaluationError::StandardError()),
+
+                    // values, which hold if they are true
+                    _ => boolean(self, self.resolve(values)?),
+                }
+            }
+            Expression::Not(expr) => Ok(not(expr.truth_value(values)?)),
+            Expression::IsNull(expr) => Ok(Some(matches!(
+                expr.resolve(values)?,
+                TypeValue::NullValueType
+            ))),
+            Expression::IsNotNull(expr) => Ok(Some(!matches!(
+                expr.resolve(values)?,
+                TypeValue::NullValueType
+            ))),
+            // true if any item equals the value, otherwise unknown if any of them is null
+            Expression::InList(expr, list, negated) => {
+                let mut found = Some(false);
+
+                for item in list.iter() {
+                    found = or(found, compare(expr, item, |tv1, tv2| tv1 == tv2, values)?);
+
+                    if found == Some(true) {
+                        break;
+                    }
                 }
+
+                Ok(if *negated { not(found) } else { found })
+            }
+            Expression::Between(expr, low, high, negated) => {
+                let between = and(
+                    compare(expr, low, |tv1, tv2| tv1 >= tv2, values)?,
+                    compare(expr, high, |tv1, tv2| tv1 <= tv2, values)?,
+                );
+
+                Ok(if *negated { not(between) } else { between })
+            }
+            Expression::Like {
+                expr,
+                pattern,
+                escape,
+                negated,
+                case_insensitive,
+            } => {
+                let matches = match (expr.resolve(values)?, pattern.resolve(values)?) {
+                    (TypeValue::NullValueType, _) | (_, TypeValue::NullValueType) => None,
+                    (TypeValue::StringTypeValue(value), TypeValue::StringTypeValue(pattern)) => {
+                        Some(if *case_insensitive {
+                            like_matches(
+                                &value.value.to_lowercase(),
+                                &pattern.value.to_lowercase(),
+                                *escape,
+                            )
+                        } else {
+                            like_matches(&value.value, &pattern.value, *escape)
+                        })
+                    }
+                    // only strings are matched against patterns
+                    _ => Some(false),
+                };
+
+                Ok(if *negated { not(matches) } else { matches })
             }
-            _ => Err(ExpressionEvaluationError::StandardError()),
+            // a boolean column, literal or function on its own
+            Expression::Identifier(_)
+            | Expression::Value(_)
+            | Expression::Function(..)
+            | Expression::Negative(_) => boolean(self, self.resolve(values)?),
         }
     }
 
@@ -101,19 +332,286 @@ impl Expression {
         values: &HashMap<String, TypeValue>,
     ) -> Result<TypeValue, ExpressionEvaluationError> {
         match self {
-            Expression::Identifier(i) => {
-                let value = values.get(&i.value);
+            // a column the values leave out is null
+            Expression::Identifier(i) => Ok(values
+                .get(&i.value)
+                .cloned()
+                .unwrap_or(TypeValue::NullValueType)),
+            Expression::Value(value) => (*value).clone().try_into(),
+            Expression::BinaryOp(left, right, op) if op.is_arithmetic() => {
+                let (left, right) = (left.resolve(values)?, right.resolve(values)?);
 
-                match value {
-                    Some(v) => Ok(v.to_owned()),
-                    None => Err(ExpressionEvaluationError::StandardError()),
-                }
+                arithmetic::apply(op, &left, &right)
+                    .map_err(|e| self.arithmetic_error(e, &[&left, &right]))
+            }
+            Expression::Negative(expr) => {
+                let value = expr.resolve(values)?;
+
+                arithmetic::negate(&value).map_err(|e| self.arithmetic_error(e, &[&value]))
             }
-            Expression::Value(value) => match ((*value).clone()).try_into() {
-                Ok(r) => Ok(r),
-                Err(_) => Err(ExpressionEvaluationError::StandardError()),
+            Expression::Function(name, args) => match functions::lookup(name) {
+                Some(function) => function
+                    .call(
+                        &args
+                            .iter()
+                            .map(|arg| arg.resolve(values))
+                            .collect::<Result<Vec<TypeValue>, ExpressionEvaluationError>>()?,
+                    )
+                    .map_err(|reason| {
+                        ExpressionEvaluationError::InvalidArgumentError(self.to_string(), reason)
+                    }),
+                None => Err(ExpressionEvaluationError::UnknownFunctionError(
+                    name.to_string(),
+                )),
             },
-            _ => Err(ExpressionEvaluationError::StandardError()),
+            // conditions used as values
+            _ => Ok(match self.truth_value(values)? {
+                Some(value) => TypeValue::BooleanValueType(BooleanValueType { value }),
+                None => TypeValue::NullValueType,
+            }),
+        }
+    }
+
+    // the evaluation error of arithmetic in this expression on the operands
+    fn arithmetic_error(
+        &self,
+        error: ArithmeticError,
+        operands: &[&TypeValue],
+    ) -> ExpressionEvaluationError {
+        match error {
+            ArithmeticError::TypeMismatchError() => ExpressionEvaluationError::TypeMismatchError(
+                self.to_string(),
+                format!(
+                    "can not be applied to {}",
+                    operands
+                        .iter()
+                        .map(|operand| type_name(operand))
+                        .collect::<Vec<String>>()
+                        .join(" and ")
+                ),
+            ),
+            ArithmeticError::DivisionByZeroError() => {
+                ExpressionEvaluationError::DivisionByZeroError(self.to_string())
+            }
+            ArithmeticError::OutOfRangeError() => {
+                ExpressionEvaluationError::OutOfRangeError(self.to_string())
+            }
+        }
+    }
+
+    // Checks that the expression reads only columns that are known, e.g. the columns of the schema
+    // of the table a select reads from
+    pub fn check_columns(
+        &self,
+        known: impl Fn(&str) -> bool,
+    ) -> Result<(), ExpressionConversionError> {
+        match self.columns().into_iter().find(|column| !known(column)) {
+            Some(column) => Err(ExpressionConversionError::UnknownColumnError(
+                column.to_string(),
+                self.to_string(),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+// A single `column <op> value` comparison taken out of a constraint
+#[derive(Debug, PartialEq, Clone)]
+pub struct ExpressionAtom {
+    pub column: String,
+    pub operator: ExpressionBinaryOperator,
+    pub value: TypeValue,
+}
+
+impl Expression {
+    // The comparisons of a column against a value that all need to hold for this expression to
+    // hold, i.e. the atoms of the top level chain of ANDs. Anything else (ORs, comparisons between
+    // two columns) is left out, so the atoms are necessary but not sufficient for a match.
+    pub fn conjunctive_atoms(&self) -> Vec<ExpressionAtom> {
+        match self {
+            Expression::BinaryOp(left, right, ExpressionBinaryOperator::And) => {
+                let mut atoms = left.conjunctive_atoms();
+                atoms.extend(right.conjunctive_atoms());
+                atoms
+            }
+            Expression::BinaryOp(left, right, op) if op.is_comparison() => {
+                let atom = match (&**left, &**right) {
+                    (Expression::Identifier(i), Expression::Value(v)) => Some((i, op.clone(), v)),
+                    // `1 < id` is the same as `id > 1`
+                    (Expression::Value(v), Expression::Identifier(i)) => Some((i, op.flipped(), v)),
+                    _ => None,
+                };
+
+                match atom {
+                    Some((identifier, operator, value)) => match value.clone().try_into() {
+                        // nothing compares to null, the comparison is left to evaluate
+                        Ok(TypeValue::NullValueType) => vec![],
+                        Ok(value) => vec![ExpressionAtom {
+                            column: identifier.value.clone(),
+                            operator,
+                            value,
+                        }],
+                        Err(_) => vec![],
+                    },
+                    None => vec![],
+                }
+            }
+            // `x BETWEEN a AND b` holds only when `x >= a AND x <= b` does
+            Expression::Between(expr, low, high, false) => Expression::BinaryOp(
+                Box::new(Expression::BinaryOp(
+                    expr.clone(),
+                    low.clone(),
+                    ExpressionBinaryOperator::GtEq,
+                )),
+                Box::new(Expression::BinaryOp(
+                    expr.clone(),
+                    high.clone(),
+                    ExpressionBinaryOperator::LtEq,
+                )),
+                ExpressionBinaryOperator::And,
+            )
+            .conjunctive_atoms(),
+            _ => vec![],
+        }
+    }
+}
+
+impl Expression {
+    // every column the expression reads
+    pub fn columns(&self) -> Vec<&str> {
+        match self {
+            Expression::BinaryOp(left, right, _) => {
+                let mut columns = left.columns();
+                columns.extend(right.columns());
+                columns
+            }
+            Expression::Identifier(i) => vec![i.value.as_str()],
+            Expression::Value(_) => vec![],
+            Expression::Not(expr)
+            | Expression::IsNull(expr)
+            | Expression::IsNotNull(expr)
+            | Expression::Negative(expr) => expr.columns(),
+            Expression::Function(_, args) => args.iter().flat_map(|arg| arg.columns()).collect(),
+            Expression::InList(expr, list, _) => {
+                let mut columns = expr.columns();
+                columns.extend(list.iter().flat_map(|item| item.columns()));
+                columns
+            }
+            Expression::Between(expr, low, high, _) => {
+                let mut columns = expr.columns();
+                columns.extend(low.columns());
+                columns.extend(high.columns());
+                columns
+            }
+            Expression::Like { expr, pattern, .. } => {
+                let mut columns = expr.columns();
+                columns.extend(pattern.columns());
+                columns
+            }
+        }
+    }
+}
+
+impl Expression {
+    // The pairs of columns compared for equality in the top level chain of ANDs, e.g. the
+    // `orders.customer_id = customer.id` of a join constraint
+    pub fn column_equalities(&self) -> Vec<(String, String)> {
+        match self {
+            Expression::BinaryOp(left, right, ExpressionBinaryOperator::And) => {
+                let mut equalities = left.column_equalities();
+                equalities.extend(right.column_equalities());
+                equalities
+            }
+            Expression::BinaryOp(left, right, ExpressionBinaryOperator::Eq) => {
+                match (&**left, &**right) {
+                    (Expression::Identifier(l), Expression::Identifier(r)) => {
+                        vec![(l.value.clone(), r.value.clone())]
+                    }
+                    _ => vec![],
+                }
+            }
+            _ => vec![],
+        }
+    }
+}
+
+// Writes the expression out as sql, the way errors show it
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let not = |negated: &bool| if *negated { "NOT " } else { "" };
+
+        match self {
+            Expression::BinaryOp(left, right, op) => {
+                write!(f, "{} {op} {}", Nested(left), Nested(right))
+            }
+            Expression::Value(value) => write!(f, "{value}"),
+            Expression::Identifier(i) => write!(f, "{}", i.value),
+            Expression::Not(expr) => write!(f, "NOT {}", Nested(expr)),
+            Expression::IsNull(expr) => write!(f, "{} IS NULL", Nested(expr)),
+            Expression::IsNotNull(expr) => write!(f, "{} IS NOT NULL", Nested(expr)),
+            Expression::InList(expr, list, negated) => write!(
+                f,
+                "{} {}IN ({})",
+                Nested(expr),
+                not(negated),
+                list.iter()
+                    .map(|item| item.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Expression::Between(expr, low, high, negated) => write!(
+                f,
+                "{} {}BETWEEN {} AND {}",
+                Nested(expr),
+                not(negated),
+                Nested(low),
+                Nested(high)
+            ),
+            Expression::Like {
+                expr,
+                pattern,
+                escape,
+                negated,
+                case_insensitive,
+            } => {
+                write!(
+                    f,
+                    "{} {}{} {}",
+                    Nested(expr),
+                    not(negated),
+                    if *case_insensitive { "ILIKE" } else { "LIKE" },
+                    Nested(pattern)
+                )?;
+
+                match escape {
+                    Some(c) => write!(f, " ESCAPE '{c}'"),
+                    None => Ok(()),
+                }
+            }
+            Expression::Function(name, args) => write!(
+                f,
+                "{name}({})",
+                args.iter()
+                    .map(|arg| arg.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Expression::Negative(expr) => write!(f, "-{}", Nested(expr)),
+        }
+    }
+}
+
+// an operand written out in parentheses unless it is a single term
+struct Nested<'a>(&'a Expression);
+
+impl fmt::Display for Nested<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Expression::Value(_)
+            | Expression::Identifier(_)
+            | Expression::Function(..)
+            | Expression::Negative(_) => write!(f, "{}", self.0),
+            expr => write!(f, "({expr})"),
         }
     }
 }
@@ -122,6 +620,9 @@ impl TryFrom<&Expr> for Expression {
     type Error = ExpressionConversionError;
 
     fn try_from(expr: &Expr) -> Result<Self, Self::Error> {
+        // the expression as written, for errors
+        let offending = || expr.to_string();
+
         Ok(match expr {
             Expr::BinaryOp { left, op, right } => Expression::BinaryOp(
                 match Expression::try_from(&**left) {
@@ -141,40 +642,276 @@ impl TryFrom<&Expr> for Expression {
                     BinaryOperator::NotEq => ExpressionBinaryOperator::NotEq,
                     BinaryOperator::And => ExpressionBinaryOperator::And,
                     BinaryOperator::Or => ExpressionBinaryOperator::Or,
+                    BinaryOperator::Xor => ExpressionBinaryOperator::Xor,
+                    BinaryOperator::Plus => ExpressionBinaryOperator::Plus,
+                    BinaryOperator::Minus => ExpressionBinaryOperator::Minus,
+                    BinaryOperator::Multiply => ExpressionBinaryOperator::Multiply,
+                    BinaryOperator::Divide => ExpressionBinaryOperator::Divide,
+                    BinaryOperator::Modulo => ExpressionBinaryOperator::Modulo,
+                    BinaryOperator::StringConcat => ExpressionBinaryOperator::StringConcat,
                     _ => {
-                        eprintln!("Error found at Binary Operator: {:?}", op);
-                        return Err(ExpressionConversionError::StandardError());
+                        return Err(ExpressionConversionError::UnsupportedOperatorError(
+                            op.to_string(),
+                            offending(),
+                        ))
                     }
                 },
             ),
             Expr::Identifier(i) => Expression::Identifier(ExpressionIdentifier {
                 value: i.value.to_string(),
             }),
+            // a qualified column like `orders.id`, resolved against the qualified names of a joined row
+            Expr::CompoundIdentifier(idents) => Expression::Identifier(ExpressionIdentifier {
+                value: qualified_name(idents),
+            }),
             Expr::Value(value) => Expression::Value(match value {
-                Value::Number(s, _) => ExpressionValue::Number(s.to_string()),
+                Value::Number(s, _) => number_literal(s.to_string())?,
                 Value::SingleQuotedString(s) => ExpressionValue::String(s.to_string()),
                 Value::DollarQuotedString(s) => ExpressionValue::String(s.value.to_string()),
                 Value::EscapedStringLiteral(s) => ExpressionValue::String(s.to_string()),
 
-                Value::SingleQuotedByteStringLiteral(s) => ExpressionValue::String(s.to_string()),
-                Value::DoubleQuotedByteStringLiteral(s) => ExpressionValue::String(s.to_string()),
+                Value::SingleQuotedByteStringLiteral(s) => {
+                    ExpressionValue::Bytes(s.as_bytes().to_vec())
+                }
+                Value::DoubleQuotedByteStringLiteral(s) => {
+                    ExpressionValue::Bytes(s.as_bytes().to_vec())
+                }
                 Value::RawStringLiteral(s) => ExpressionValue::String(s.to_string()),
 
                 Value::NationalStringLiteral(s) => ExpressionValue::String(s.to_string()),
-                Value::HexStringLiteral(s) => ExpressionValue::String(s.to_string()),
+                Value::HexStringLiteral(s) => match BytesValueType::from_hex(s) {
+                    Some(bytes) => ExpressionValue::Bytes(bytes.value),
+                    None => {
+                        return Err(ExpressionConversionError::InvalidLiteralError(offending()))
+                    }
+                },
                 Value::DoubleQuotedString(s) => ExpressionValue::String(s.to_string()),
+                Value::Boolean(b) => ExpressionValue::Boolean(*b),
+                Value::Null => ExpressionValue::Null,
                 _ => {
-                    eprintln!("Error found at Converting value expression: {:?}", value);
-                    return Err(ExpressionConversionError::StandardError());
-                } // NULL? BOOL?
+                    return Err(ExpressionConversionError::UnsupportedExpressionError(
+                        offending(),
+                    ))
+                }
             }),
+            // typed literals like DATE '2023-01-31', checked here so a bad one rejects the query
+            Expr::TypedString { data_type, value } => {
+                match TypeValue::parse_typed(data_type, value) {
+                    Some(TypeValue::DateValueType(_)) => {
+                        Expression::Value(ExpressionValue::Date(value.to_string()))
+                    }
+                    Some(TypeValue::TimestampValueType(_)) => {
+                        Expression::Value(ExpressionValue::Timestamp(value.to_string()))
+                    }
+                    Some(TypeValue::BooleanValueType(b)) => {
+                        Expression::Value(ExpressionValue::Boolean(b.value))
+                    }
+                    Some(TypeValue::BytesValueType(b)) => {
+                        Expression::Value(ExpressionValue::Bytes(b.value))
+                    }
+                    Some(TypeValue::StringTypeValue(s)) => {
+                        Expression::Value(ExpressionValue::String(s.value))
+                    }
+                    Some(_) => Expression::Value(ExpressionValue::Number(value.trim().to_string())),
+                    None => {
+                        return Err(ExpressionConversionError::InvalidLiteralError(offending()))
+                    }
+                }
+            }
+            // negative numbers
+            Expr::UnaryOp {
+                op: UnaryOperator::Minus,
+                expr,
+            } => match &**expr {
+                Expr::Value(Value::Number(s, _)) => {
+                    Expression::Value(number_literal(format!("-{s}"))?)
+                }
+                expr => Expression::Negative(Box::new(Expression::try_from(expr)?)),
+            },
+            Expr::UnaryOp {
+                op: UnaryOperator::Plus,
+                expr,
+            } => Expression::try_from(&**expr)?,
+            Expr::Function(function) => {
+                let name = function.name.to_string();
+
+                let args = function
+                    .args
+                    .iter()
+                    .map(|arg| match arg {
+                        FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => {
+                            Expression::try_from(expr)
+                        }
+                        _ => Err(ExpressionConversionError::UnsupportedExpressionError(
+                            arg.to_string(),
+                        )),
+                    })
+                    .collect::<Result<Vec<Expression>, ExpressionConversionError>>()?;
+
+                if function.distinct || function.over.is_some() {
+                    return Err(ExpressionConversionError::UnsupportedExpressionError(
+                        offending(),
+                    ));
+                }
+
+                function_call(&name, args, expr)?
+            }
+            // functions sql spells out with keywords
+            Expr::Trim {
+                expr: value,
+                trim_where,
+                trim_what,
+            } => {
+                let mut args = vec![Expression::try_from(&**value)?];
+
+                if let Some(what) = trim_what {
+                    args.push(Expression::try_from(&**what)?);
+                }
+
+                let name = match trim_where {
+                    Some(TrimWhereField::Leading) => "ltrim",
+                    Some(TrimWhereField::Trailing) => "rtrim",
+                    Some(TrimWhereField::Both) | None => "trim",
+                };
+
+                function_call(name, args, expr)?
+            }
+            Expr::Substring {
+                expr: value,
+                substring_from,
+                substring_for,
+            } => {
+                let mut args = vec![
+                    Expression::try_from(&**value)?,
+                    match substring_from {
+                        Some(from) => Expression::try_from(&**from)?,
+                        None => Expression::Value(ExpressionValue::Number("1".to_string())),
+                    },
+                ];
+
+                if let Some(count) = substring_for {
+                    args.push(Expression::try_from(&**count)?);
+                }
+
+                function_call("substr", args, expr)?
+            }
+            Expr::Position { expr: what, r#in } => function_call(
+                "strpos",
+                vec![
+                    Expression::try_from(&**r#in)?,
+                    Expression::try_from(&**what)?,
+                ],
+                expr,
+            )?,
+            Expr::Extract { field, expr: from } => function_call(
+                "date_part",
+                vec![
+                    Expression::Value(ExpressionValue::String(field.to_string().to_lowercase())),
+                    Expression::try_from(&**from)?,
+                ],
+                expr,
+            )?,
+            Expr::Ceil {
+                expr: value,
+                field: DateTimeField::NoDateTime,
+            } => function_call("ceil", vec![Expression::try_from(&**value)?], expr)?,
+            Expr::Floor {
+                expr: value,
+                field: DateTimeField::NoDateTime,
+            } => function_call("floor", vec![Expression::try_from(&**value)?], expr)?,
+            Expr::UnaryOp {
+                op: UnaryOperator::Not,
+                expr,
+            } => Expression::Not(Box::new(Expression::try_from(&**expr)?)),
+            // parentheses only group, the tree already does that
+            Expr::Nested(expr) => Expression::try_from(&**expr)?,
+            Expr::IsNull(expr) => Expression::IsNull(Box::new(Expression::try_from(&**expr)?)),
+            Expr::IsNotNull(expr) => {
+                Expression::IsNotNull(Box::new(Expression::try_from(&**expr)?))
+            }
+            Expr::InList {
+                expr,
+                list,
+                negated,
+            } => Expression::InList(
+                Box::new(Expression::try_from(&**expr)?),
+                list.iter()
+                    .map(Expression::try_from)
+                    .collect::<Result<Vec<Expression>, ExpressionConversionError>>()?,
+                *negated,
+            ),
+            Expr::Between {
+                expr,
+                negated,
+                low,
+                high,
+            } => Expression::Between(
+                Box::new(Expression::try_from(&**expr)?),
+                Box::new(Expression::try_from(&**low)?),
+                Box::new(Expression::try_from(&**high)?),
+                *negated,
+            ),
+            Expr::Like {
+                negated,
+                expr,
+                pattern,
+                escape_char,
+            } => Expression::Like {
+                expr: Box::new(Expression::try_from(&**expr)?),
+                pattern: Box::new(Expression::try_from(&**pattern)?),
+                escape: *escape_char,
+                negated: *negated,
+                case_insensitive: false,
+            },
+            Expr::ILike {
+                negated,
+                expr,
+                pattern,
+                escape_char,
+            } => Expression::Like {
+                expr: Box::new(Expression::try_from(&**expr)?),
+                pattern: Box::new(Expression::try_from(&**pattern)?),
+                escape: *escape_char,
+                negated: *negated,
+                case_insensitive: true,
+            },
             _ => {
-                eprintln!("Error found at Expression: {:?}", expr);
-                return Err(ExpressionConversionError::StandardError());
+                return Err(ExpressionConversionError::UnsupportedExpressionError(
+                    offending(),
+                ))
             }
         })
+    }
+}
+
+// A call of a built-in function written as the expression, checked to exist and take that many
+// arguments
+fn function_call(
+    name: &str,
+    args: Vec<Expression>,
+    expr: &Expr,
+) -> Result<Expression, ExpressionConversionError> {
+    match functions::lookup(name) {
+        Some(function) if function.accepts(args.len()) => {
+            Ok(Expression::Function(function.name.to_string(), args))
+        }
+        Some(function) => Err(ExpressionConversionError::ArgumentCountError(
+            function.name.to_string(),
+            args.len(),
+            expr.to_string(),
+        )),
+        None => Err(ExpressionConversionError::UnknownFunctionError(
+            name.to_string(),
+            expr.to_string(),
+        )),
+    }
+}
 
-        // Err(ExpressionConversionError::StandardError())
+// a number literal, checked here so one that doesn't parse rejects the query
+fn number_literal(s: String) -> Result<ExpressionValue, ExpressionConversionError> {
+    match TypeValue::parse_number(&s) {
+        Some(_) => Ok(ExpressionValue::Number(s)),
+        None => Err(ExpressionConversionError::InvalidLiteralError(s)),
     }
 }
 
@@ -189,6 +926,29 @@ impl TryFrom<&Expr> for Expression {
 pub enum ExpressionValue {
     Number(String),
     String(String),
+    Boolean(bool),
+    Date(String),
+    Timestamp(String),
+    Bytes(Vec<u8>),
+    Null,
+}
+
+impl fmt::Display for ExpressionValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExpressionValue::Number(s) => write!(f, "{s}"),
+            ExpressionValue::String(s) => write!(f, "'{}'", s.replace('\'', "''")),
+            ExpressionValue::Boolean(b) => write!(f, "{}", if *b { "TRUE" } else { "FALSE" }),
+            ExpressionValue::Date(s) => write!(f, "DATE '{s}'"),
+            ExpressionValue::Timestamp(s) => write!(f, "TIMESTAMP '{s}'"),
+            ExpressionValue::Bytes(bytes) => write!(
+                f,
+                "X'{}'",
+                bytes.iter().map(|b| format!("{b:02X}")).collect::<String>()
+            ),
+            ExpressionValue::Null => write!(f, "NULL"),
+        }
+    }
 }
 
 impl TryInto<TypeValue> for ExpressionValue {
@@ -196,27 +956,40 @@ impl TryInto<TypeValue> for ExpressionValue {
 
     fn try_into(self) -> Result<TypeValue, Self::Error> {
         match self {
-            ExpressionValue::Number(s) => match s.parse::<f64>() {
-                Ok(v) => Ok(TypeValue::NumberValueType(NumberValueType { value: v })),
-                Err(e) => {
-                    eprintln!("Error with parsing float: {s} error: {:?}", e);
-                    Err(ExpressionEvaluationError::StandardError())
-                }
+            ExpressionValue::Number(s) => match TypeValue::parse_number(&s) {
+                Some(v) => Ok(v),
+                None => Err(ExpressionEvaluationError::ParseError(s)),
             },
             ExpressionValue::String(s) => {
                 Ok(TypeValue::StringTypeValue(StringTypeValue { value: s }))
             }
-            _ => Err(ExpressionEvaluationError::StandardError()),
+            ExpressionValue::Boolean(value) => {
+                Ok(TypeValue::BooleanValueType(BooleanValueType { value }))
+            }
+            ExpressionValue::Date(s) => match DateValueType::parse(&s) {
+                Some(v) => Ok(TypeValue::DateValueType(v)),
+                None => Err(ExpressionEvaluationError::ParseError(format!("DATE '{s}'"))),
+            },
+            ExpressionValue::Timestamp(s) => match TimestampValueType::parse(&s) {
+                Some(v) => Ok(TypeValue::TimestampValueType(v)),
+                None => Err(ExpressionEvaluationError::ParseError(format!(
+                    "TIMESTAMP '{s}'"
+                ))),
+            },
+            ExpressionValue::Bytes(value) => {
+                Ok(TypeValue::BytesValueType(BytesValueType { value }))
+            }
+            ExpressionValue::Null => Ok(TypeValue::NullValueType),
         }
     }
 }
 
-#[derive(Deserialize, Serialize, PartialEq, Debug)]
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
 pub struct ExpressionIdentifier {
     value: String,
 }
 
-#[derive(Deserialize, Serialize, PartialEq, Debug)]
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
 pub enum ExpressionBinaryOperator {
     Gt,
     Lt,
@@ -227,6 +1000,76 @@ pub enum ExpressionBinaryOperator {
     And,
     Or,
     Xor,
+    Plus,
+    Minus,
+    Multiply,
+    Divide,
+    Modulo,
+    StringConcat,
+}
+
+impl fmt::Display for ExpressionBinaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ExpressionBinaryOperator::Gt => ">",
+                ExpressionBinaryOperator::Lt => "<",
+                ExpressionBinaryOperator::GtEq => ">=",
+                ExpressionBinaryOperator::LtEq => "<=",
+                ExpressionBinaryOperator::Eq => "=",
+                ExpressionBinaryOperator::NotEq => "<>",
+                ExpressionBinaryOperator::And => "AND",
+                ExpressionBinaryOperator::Or => "OR",
+                ExpressionBinaryOperator::Xor => "XOR",
+                ExpressionBinaryOperator::Plus => "+",
+                ExpressionBinaryOperator::Minus => "-",
+                ExpressionBinaryOperator::Multiply => "*",
+                ExpressionBinaryOperator::Divide => "/",
+                ExpressionBinaryOperator::Modulo => "%",
+                ExpressionBinaryOperator::StringConcat => "||",
+            }
+        )
+    }
+}
+
+impl ExpressionBinaryOperator {
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            ExpressionBinaryOperator::Gt
+                | ExpressionBinaryOperator::Lt
+                | ExpressionBinaryOperator::GtEq
+                | ExpressionBinaryOperator::LtEq
+                | ExpressionBinaryOperator::Eq
+                | ExpressionBinaryOperator::NotEq
+        )
+    }
+
+    // operators that compute a value out of two values rather than a truth
+    pub fn is_arithmetic(&self) -> bool {
+        matches!(
+            self,
+            ExpressionBinaryOperator::Plus
+                | ExpressionBinaryOperator::Minus
+                | ExpressionBinaryOperator::Multiply
+                | ExpressionBinaryOperator::Divide
+                | ExpressionBinaryOperator::Modulo
+                | ExpressionBinaryOperator::StringConcat
+        )
+    }
+
+    // the operator to use when the operands of a comparison swap sides
+    pub fn flipped(&self) -> ExpressionBinaryOperator {
+        match self {
+            ExpressionBinaryOperator::Gt => ExpressionBinaryOperator::Lt,
+            ExpressionBinaryOperator::Lt => ExpressionBinaryOperator::Gt,
+            ExpressionBinaryOperator::GtEq => ExpressionBinaryOperator::LtEq,
+            ExpressionBinaryOperator::LtEq => ExpressionBinaryOperator::GtEq,
+            op => op.clone(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -234,6 +1077,14 @@ mod tests {
 
     // Note this useful idiom: importing names from outer (for mod tests) scope.
     use super::*;
+    use crate::db::models::decimal_value::DecimalValueType;
+    use crate::db::models::integer_value::IntegerValueType;
+    use crate::db::models::number_value::NumberValueType;
+    use crate::models::errors::StatementError;
+    use crate::models::select_query::SelectQuery;
+    use sqlparser::ast::Statement::Query;
+    use sqlparser::dialect::GenericDialect;
+    use sqlparser::parser::Parser;
 
     #[test]
     fn convert_expression() {
@@ -282,7 +1133,7 @@ mod tests {
 
                     match select_query {
                         Ok(select) => match select.constraints {
-                            Some(c) => assert_eq!(Expression::try_from(c), result),
+                            Some(c) => assert_eq!(Ok::<_, ExpressionConversionError>(c), result),
                             None => panic!("No Select Statement found."),
                         },
                         Err(e) => {
@@ -479,4 +1330,316 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn conjunctive_atoms_of_statement() {
+        let sql =
+            "select * into customer_cache from customer where id = 1 and 30 < age and x <> y;";
+
+        let dialect = GenericDialect {};
+
+        let ast = Parser::parse_sql(&dialect, sql).expect("Error with parsing the sql");
+
+        match ast.first() {
+            Some(Query(query)) => {
+                let select = SelectQuery::try_from(&*query.body).expect("No Select Statement");
+
+                assert_eq!(
+                    select
+                        .constraints
+                        .expect("No Constraints")
+                        .conjunctive_atoms(),
+                    vec![
+                        ExpressionAtom {
+                            column: "id".to_string(),
+                            operator: ExpressionBinaryOperator::Eq,
+                            value: TypeValue::IntegerValueType(IntegerValueType { value: 1 }),
+                        },
+                        ExpressionAtom {
+                            column: "age".to_string(),
+                            operator: ExpressionBinaryOperator::Gt,
+                            value: TypeValue::IntegerValueType(IntegerValueType { value: 30 }),
+                        },
+                    ]
+                );
+            }
+            _ => panic!("No Select Statement found."),
+        }
+    }
+
+    fn truth_value(sql: &str, values: &HashMap<String, TypeValue>) -> Option<bool> {
+        let expr = Parser::new(&GenericDialect {})
+            .try_with_sql(sql)
+            .and_then(|mut parser| parser.parse_expr())
+            .expect("Error with parsing the sql");
+
+        Expression::try_from(&expr)
+            .expect("Could not convert expression")
+            .truth_value(values)
+            .unwrap_or_else(|e| panic!("Could not evaluate {sql}: {e:?}"))
+    }
+
+    #[test]
+    fn evaluate_with_three_valued_logic() {
+        let values = HashMap::from([
+            (
+                "status".to_string(),
+                TypeValue::StringTypeValue(StringTypeValue {
+                    value: "shipped".to_string(),
+                }),
+            ),
+            (
+                "age".to_string(),
+                TypeValue::NumberValueType(NumberValueType { value: 30.0 }),
+            ),
+            (
+                "active".to_string(),
+                TypeValue::BooleanValueType(BooleanValueType { value: true }),
+            ),
+            ("note".to_string(), TypeValue::NullValueType),
+        ]);
+
+        for (sql, expected) in [
+            ("status in ('open', 'shipped')", Some(true)),
+            ("status not in ('open', 'closed')", Some(true)),
+            ("status in ('open', null)", None),
+            ("status not in ('open', null)", None),
+            ("status in ('shipped', null)", Some(true)),
+            ("age between 18 and 30", Some(true)),
+            ("age not between 18 and 30", Some(false)),
+            ("age between 18 and null", None),
+            ("age between 40 and null", Some(false)),
+            ("status like 'ship%'", Some(true)),
+            ("status like '_hip_ed'", Some(true)),
+            ("status like 'ship'", Some(false)),
+            ("status not like '%x%'", Some(true)),
+            ("status ilike 'SHIP%'", Some(true)),
+            ("'50%' like '50!%' escape '!'", Some(true)),
+            ("'500' like '50!%' escape '!'", Some(false)),
+            ("note like '%'", None),
+            ("note is null", Some(true)),
+            ("missing is null", Some(true)),
+            ("status is not null", Some(true)),
+            ("note = null", None),
+            ("note <> 'x'", None),
+            ("not note = 'x'", None),
+            ("not (age > 40)", Some(true)),
+            ("(age > 40 or active) and status = 'shipped'", Some(true)),
+            ("note = 'x' and age > 40", Some(false)),
+            ("note = 'x' or age > 20", Some(true)),
+            ("note = 'x' or age > 40", None),
+            ("active xor (age > 20)", Some(false)),
+            ("active xor (age > 40)", Some(true)),
+            ("active xor (note = 'x')", None),
+            ("not active", Some(false)),
+        ] {
+            assert_eq!(truth_value(sql, &values), expected, "{sql}");
+        }
+    }
+
+    #[test]
+    fn evaluate_arithmetic_and_functions() {
+        let values = HashMap::from([
+            (
+                "price".to_string(),
+                TypeValue::DecimalValueType(DecimalValueType::new(1250, 2)),
+            ),
+            (
+                "qty".to_string(),
+                TypeValue::IntegerValueType(IntegerValueType { value: 10 }),
+            ),
+            (
+                "email".to_string(),
+                TypeValue::StringTypeValue(StringTypeValue {
+                    value: " Ann@Example.com".to_string(),
+                }),
+            ),
+            (
+                "at".to_string(),
+                TypeValue::TimestampValueType(
+                    TimestampValueType::parse("2023-05-17 13:45:00").expect("Not a timestamp"),
+                ),
+            ),
+            ("note".to_string(), TypeValue::NullValueType),
+        ]);
+
+        for (sql, expected) in [
+            ("price * qty > 100", Some(true)),
+            ("price * qty = 125", Some(true)),
+            ("qty / 4 = 2", Some(true)),
+            ("qty % 4 = 2 and -qty < 0", Some(true)),
+            ("price + 0.5 = 13", Some(true)),
+            ("lower(trim(email)) = 'ann@example.com'", Some(true)),
+            ("upper(substring(email from 2 for 3)) = 'ANN'", Some(true)),
+            ("position('@' in email) = 5", Some(true)),
+            ("'#' || qty || '-' || price = '#10-12.50'", Some(true)),
+            ("length(note) > 1", None),
+            ("coalesce(note, 'none') = 'none'", Some(true)),
+            ("extract(hour from at) = 13", Some(true)),
+            ("date(at) + 1 = DATE '2023-05-18'", Some(true)),
+            ("round(price) = 13 and abs(-qty) = qty", Some(true)),
+            ("note || 'x' is null", Some(true)),
+        ] {
+            assert_eq!(truth_value(sql, &values), expected, "{sql}");
+        }
+
+        // unknown functions and wrong numbers of arguments don't convert
+        for sql in ["nope(qty) = 1", "lower(email, qty) = 'x'", "sum(qty) > 1"] {
+            let expr = Parser::new(&GenericDialect {})
+                .try_with_sql(sql)
+                .and_then(|mut parser| parser.parse_expr())
+                .expect("Error with parsing the sql");
+
+            assert!(Expression::try_from(&expr).is_err(), "{sql}");
+        }
+
+        // a division by zero is an error, not a match
+        let expr = Parser::new(&GenericDialect {})
+            .try_with_sql("qty / 0 > 1")
+            .and_then(|mut parser| parser.parse_expr())
+            .expect("Error with parsing the sql");
+
+        assert!(Expression::try_from(&expr)
+            .expect("Could not convert expression")
+            .truth_value(&values)
+            .is_err());
+    }
+
+    #[test]
+    fn errors_name_the_offending_expression() {
+        let convert = |sql: &str| {
+            let expr = Parser::new(&GenericDialect {})
+                .try_with_sql(sql)
+                .and_then(|mut parser| parser.parse_expr())
+                .expect("Error with parsing the sql");
+
+            Expression::try_from(&expr)
+        };
+
+        assert_eq!(
+            convert("a = 1 and b ~ 'x'"),
+            Err(ExpressionConversionError::UnsupportedOperatorError(
+                "~".to_string(),
+                "b ~ 'x'".to_string()
+            ))
+        );
+        assert_eq!(
+            convert("nope(qty) = 1"),
+            Err(ExpressionConversionError::UnknownFunctionError(
+                "nope".to_string(),
+                "nope(qty)".to_string()
+            ))
+        );
+        assert_eq!(
+            convert("lower(email, qty) = 'x'"),
+            Err(ExpressionConversionError::ArgumentCountError(
+                "lower".to_string(),
+                2,
+                "lower(email, qty)".to_string()
+            ))
+        );
+        assert_eq!(
+            convert("at > DATE '2023-02-30'"),
+            Err(ExpressionConversionError::InvalidLiteralError(
+                "DATE '2023-02-30'".to_string()
+            ))
+        );
+        assert!(matches!(
+            convert("a = (select 1)"),
+            Err(ExpressionConversionError::UnsupportedExpressionError(_))
+        ));
+
+        let values = HashMap::from([
+            (
+                "qty".to_string(),
+                TypeValue::IntegerValueType(IntegerValueType { value: 10 }),
+            ),
+            (
+                "email".to_string(),
+                TypeValue::StringTypeValue(StringTypeValue {
+                    value: "ann@example.com".to_string(),
+                }),
+            ),
+        ]);
+
+        let evaluate = |sql: &str| {
+            convert(sql)
+                .expect("Could not convert expression")
+                .truth_value(&values)
+        };
+
+        assert_eq!(
+            evaluate("qty / (qty - 10) > 1"),
+            Err(ExpressionEvaluationError::DivisionByZeroError(
+                "qty / (qty - 10)".to_string()
+            ))
+        );
+        assert_eq!(
+            evaluate("qty + email > 1"),
+            Err(ExpressionEvaluationError::TypeMismatchError(
+                "qty + email".to_string(),
+                "can not be applied to Integer and String".to_string()
+            ))
+        );
+        assert_eq!(
+            evaluate("email and qty > 1"),
+            Err(ExpressionEvaluationError::TypeMismatchError(
+                "email".to_string(),
+                "expected a Boolean, got String".to_string()
+            ))
+        );
+        assert_eq!(
+            evaluate("sqrt(-qty) > 1"),
+            Err(ExpressionEvaluationError::InvalidArgumentError(
+                "sqrt(-qty)".to_string(),
+                "square root of a negative number".to_string()
+            ))
+        );
+
+        // a where clause that doesn't convert rejects the select instead of matching every row
+        let ast = Parser::parse_sql(
+            &GenericDialect {},
+            "select * into cache from customer where nope(id) = 1;",
+        )
+        .expect("Error with parsing the sql");
+
+        match ast.first() {
+            Some(Query(query)) => assert_eq!(
+                SelectQuery::try_from(&*query.body),
+                Err(StatementError::ExpressionError(
+                    ExpressionConversionError::UnknownFunctionError(
+                        "nope".to_string(),
+                        "nope(id)".to_string()
+                    )
+                ))
+            ),
+            _ => panic!("No Select Statement found."),
+        }
+    }
+
+    #[test]
+    fn between_gives_atoms() {
+        let expr = Parser::new(&GenericDialect {})
+            .try_with_sql("age between 18 and 30 and status in ('open') and note = null")
+            .and_then(|mut parser| parser.parse_expr())
+            .expect("Error with parsing the sql");
+
+        assert_eq!(
+            Expression::try_from(&expr)
+                .expect("Could not convert expression")
+                .conjunctive_atoms(),
+            vec![
+                ExpressionAtom {
+                    column: "age".to_string(),
+                    operator: ExpressionBinaryOperator::GtEq,
+                    value: TypeValue::IntegerValueType(IntegerValueType { value: 18 }),
+                },
+                ExpressionAtom {
+                    column: "age".to_string(),
+                    operator: ExpressionBinaryOperator::LtEq,
+                    value: TypeValue::IntegerValueType(IntegerValueType { value: 30 }),
+                },
+            ]
+        );
+    }
 }
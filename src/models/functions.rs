@@ -0,0 +1,725 @@
+// The built-in scalar functions expressions can call, looked up by name when an expression is
+// converted. Aggregates (count, sum, ..) are not in here, they live in models::aggregate.
+use std::cmp::Ordering;
+
+use crate::db::data::TypeValue;
+use crate::db::models::{
+    boolean_value::BooleanValueType,
+    date_value::{civil_from_days, days_from_civil, DateValueType, MICROS_PER_DAY},
+    decimal_value::DecimalValueType,
+    integer_value::IntegerValueType,
+    number_value::NumberValueType,
+    string_value::StringTypeValue,
+    timestamp_value::TimestampValueType,
+};
+
+use super::arithmetic;
+use super::expression::ExpressionBinaryOperator;
+
+// a function either gives a value or the reason it could not, the expression calling it adds
+// itself to that reason
+type Call = fn(&[TypeValue]) -> Result<TypeValue, String>;
+
+pub struct ScalarFunction {
+    pub name: &'static str,
+    pub min_args: usize,
+    // None for any number of arguments
+    pub max_args: Option<usize>,
+    // whether a null argument makes the result null, without calling the function
+    strict: bool,
+    call: Call,
+}
+
+impl ScalarFunction {
+    pub fn accepts(&self, count: usize) -> bool {
+        count >= self.min_args && self.max_args.is_none_or(|max| count <= max)
+    }
+
+    pub fn call(&self, args: &[TypeValue]) -> Result<TypeValue, String> {
+        if !self.accepts(args.len()) {
+            return Err(format!("{} arguments are too many or too few", args.len()));
+        }
+
+        if self.strict
+            && args
+                .iter()
+                .any(|arg| matches!(arg, TypeValue::NullValueType))
+        {
+            return Ok(TypeValue::NullValueType);
+        }
+
+        (self.call)(args)
+    }
+}
+
+const fn function(
+    name: &'static str,
+    min_args: usize,
+    max_args: Option<usize>,
+    strict: bool,
+    call: Call,
+) -> ScalarFunction {
+    ScalarFunction {
+        name,
+        min_args,
+        max_args,
+        strict,
+        call,
+    }
+}
+
+const FUNCTIONS: &[ScalarFunction] = &[
+    // strings
+    function("lower", 1, Some(1), true, lower),
+    function("upper", 1, Some(1), true, upper),
+    function("length", 1, Some(1), true, length),
+    function("char_length", 1, Some(1), true, length),
+    function("character_length", 1, Some(1), true, length),
+    function("trim", 1, Some(2), true, trim),
+    function("btrim", 1, Some(2), true, trim),
+    function("ltrim", 1, Some(2), true, ltrim),
+    function("rtrim", 1, Some(2), true, rtrim),
+    function("substr", 2, Some(3), true, substr),
+    function("substring", 2, Some(3), true, substr),
+    function("replace", 3, Some(3), true, replace),
+    function("left", 2, Some(2), true, left),
+    function("right", 2, Some(2), true, right),
+    function("strpos", 2, Some(2), true, strpos),
+    function("starts_with", 2, Some(2), true, starts_with),
+    function("reverse", 1, Some(1), true, reverse),
+    function("concat", 0, None, false, concat),
+    // math
+    function("abs", 1, Some(1), true, abs),
+    function("ceil", 1, Some(1), true, ceil),
+    function("ceiling", 1, Some(1), true, ceil),
+    function("floor", 1, Some(1), true, floor),
+    function("round", 1, Some(2), true, round),
+    function("sign", 1, Some(1), true, sign),
+    function("mod", 2, Some(2), true, modulo),
+    function("sqrt", 1, Some(1), true, sqrt),
+    function("power", 2, Some(2), true, power),
+    function("pow", 2, Some(2), true, power),
+    function("exp", 1, Some(1), true, exp),
+    function("ln", 1, Some(1), true, ln),
+    function("log10", 1, Some(1), true, log10),
+    function("greatest", 1, None, false, greatest),
+    function("least", 1, None, false, least),
+    // nulls
+    function("coalesce", 1, None, false, coalesce),
+    function("nullif", 2, Some(2), false, nullif),
+    // dates
+    function("date_part", 2, Some(2), true, date_part),
+    function("date_trunc", 2, Some(2), true, date_trunc),
+    function("date", 1, Some(1), true, date),
+];
+
+// the function by its name, in any case
+pub fn lookup(name: &str) -> Option<&'static ScalarFunction> {
+    FUNCTIONS
+        .iter()
+        .find(|function| function.name.eq_ignore_ascii_case(name))
+}
+
+fn error<T>(reason: &str) -> Result<T, String> {
+    Err(reason.to_string())
+}
+
+fn string_arg(value: &TypeValue) -> Result<&str, String> {
+    match value {
+        TypeValue::StringTypeValue(s) => Ok(&s.value),
+        _ => error("expected a string"),
+    }
+}
+
+// a whole number, of any number type
+fn integer_arg(value: &TypeValue) -> Result<i64, String> {
+    match value {
+        TypeValue::IntegerValueType(v) => Ok(v.value),
+        TypeValue::DecimalValueType(v) => {
+            let factor = power_of_ten(v.scale)?;
+
+            if v.mantissa % factor != 0 {
+                return error("expected a whole number");
+            }
+
+            i64::try_from(v.mantissa / factor).or(error("the number is out of range"))
+        }
+        // i64::MIN is -2^63 exactly, as f64 the bounds of an i64 are [-2^63, 2^63)
+        TypeValue::NumberValueType(v) if !v.value.is_finite() || v.value.fract() != 0.0 => {
+            error("expected a whole number")
+        }
+        TypeValue::NumberValueType(v)
+            if v.value < i64::MIN as f64 || v.value >= -(i64::MIN as f64) =>
+        {
+            error("the number is out of range")
+        }
+        TypeValue::NumberValueType(v) => Ok(v.value as i64),
+        _ => error("expected a whole number"),
+    }
+}
+
+// the factor between a decimal mantissa and its value
+fn power_of_ten(scale: u32) -> Result<i128, String> {
+    10i128
+        .checked_pow(scale)
+        .ok_or("the scale is out of range".to_string())
+}
+
+fn float_arg(value: &TypeValue) -> Result<f64, String> {
+    value.as_f64().ok_or("expected a number".to_string())
+}
+
+// the microseconds since the epoch of a date or timestamp
+fn micros_arg(value: &TypeValue) -> Result<i64, String> {
+    match value {
+        TypeValue::TimestampValueType(v) => Ok(v.micros),
+        TypeValue::DateValueType(v) => v.to_micros().ok_or("date out of range".to_string()),
+        _ => error("expected a date or timestamp"),
+    }
+}
+
+fn string(value: String) -> Result<TypeValue, String> {
+    Ok(TypeValue::StringTypeValue(StringTypeValue { value }))
+}
+
+fn integer(value: i64) -> Result<TypeValue, String> {
+    Ok(TypeValue::IntegerValueType(IntegerValueType { value }))
+}
+
+fn float(value: f64) -> Result<TypeValue, String> {
+    Ok(TypeValue::NumberValueType(NumberValueType { value }))
+}
+
+fn decimal(mantissa: i128, scale: u32) -> Result<TypeValue, String> {
+    Ok(TypeValue::DecimalValueType(DecimalValueType::new(
+        mantissa, scale,
+    )))
+}
+
+fn lower(args: &[TypeValue]) -> Result<TypeValue, String> {
+    string(string_arg(&args[0])?.to_lowercase())
+}
+
+fn upper(args: &[TypeValue]) -> Result<TypeValue, String> {
+    string(string_arg(&args[0])?.to_uppercase())
+}
+
+fn length(args: &[TypeValue]) -> Result<TypeValue, String> {
+    integer(string_arg(&args[0])?.chars().count() as i64)
+}
+
+// the characters to trim off, spaces unless given
+fn trim_chars(args: &[TypeValue]) -> Result<Vec<char>, String> {
+    match args.get(1) {
+        Some(chars) => Ok(string_arg(chars)?.chars().collect()),
+        None => Ok(vec![' ']),
+    }
+}
+
+fn trim(args: &[TypeValue]) -> Result<TypeValue, String> {
+    let chars = trim_chars(args)?;
+    string(string_arg(&args[0])?.trim_matches(&chars[..]).to_string())
+}
+
+fn ltrim(args: &[TypeValue]) -> Result<TypeValue, String> {
+    let chars = trim_chars(args)?;
+    string(
+        string_arg(&args[0])?
+            .trim_start_matches(&chars[..])
+            .to_string(),
+    )
+}
+
+fn rtrim(args: &[TypeValue]) -> Result<TypeValue, String> {
+    let chars = trim_chars(args)?;
+    string(
+        string_arg(&args[0])?
+            .trim_end_matches(&chars[..])
+            .to_string(),
+    )
+}
+
+// Characters from a 1-based position on, as many as the count if there is one. Like postgres,
+// positions before the first character count towards the count.
+fn substr(args: &[TypeValue]) -> Result<TypeValue, String> {
+    let value = string_arg(&args[0])?;
+    let start = integer_arg(&args[1])?;
+    let end = match args.get(2) {
+        Some(count) => match integer_arg(count)? {
+            count if count < 0 => return error("negative substring length"),
+            count => Some(start.saturating_add(count)),
+        },
+        None => None,
+    };
+
+    string(
+        value
+            .chars()
+            .enumerate()
+            .filter(|(i, _)| {
+                let position = *i as i64 + 1;
+                position >= start && end.is_none_or(|end| position < end)
+            })
+            .map(|(_, c)| c)
+            .collect(),
+    )
+}
+
+fn replace(args: &[TypeValue]) -> Result<TypeValue, String> {
+    let value = string_arg(&args[0])?;
+    let from = string_arg(&args[1])?;
+    let to = string_arg(&args[2])?;
+
+    if from.is_empty() {
+        return string(value.to_string());
+    }
+
+    string(value.replace(from, to))
+}
+
+// the first n characters, all but the last -n ones for a negative n
+fn left(args: &[TypeValue]) -> Result<TypeValue, String> {
+    let chars: Vec<char> = string_arg(&args[0])?.chars().collect();
+    let n = integer_arg(&args[1])?;
+    let count = if n >= 0 {
+        (n as usize).min(chars.len())
+    } else {
+        chars.len().saturating_sub(n.unsigned_abs() as usize)
+    };
+
+    string(chars[..count].iter().collect())
+}
+
+// the last n characters, all but the first -n ones for a negative n
+fn right(args: &[TypeValue]) -> Result<TypeValue, String> {
+    let chars: Vec<char> = string_arg(&args[0])?.chars().collect();
+    let n = integer_arg(&args[1])?;
+    let count = if n >= 0 {
+        (n as usize).min(chars.len())
+    } else {
+        chars.len().saturating_sub(n.unsigned_abs() as usize)
+    };
+
+    string(chars[chars.len() - count..].iter().collect())
+}
+
+// the 1-based position of a substring, 0 if it is not in there
+fn strpos(args: &[TypeValue]) -> Result<TypeValue, String> {
+    let value = string_arg(&args[0])?;
+
+    integer(match value.find(string_arg(&args[1])?) {
+        Some(i) => value[..i].chars().count() as i64 + 1,
+        None => 0,
+    })
+}
+
+fn starts_with(args: &[TypeValue]) -> Result<TypeValue, String> {
+    Ok(TypeValue::BooleanValueType(BooleanValueType {
+        value: string_arg(&args[0])?.starts_with(string_arg(&args[1])?),
+    }))
+}
+
+fn reverse(args: &[TypeValue]) -> Result<TypeValue, String> {
+    string(string_arg(&args[0])?.chars().rev().collect())
+}
+
+// the text of every argument that is not null
+fn concat(args: &[TypeValue]) -> Result<TypeValue, String> {
+    string(args.iter().filter_map(|arg| arg.to_text()).collect())
+}
+
+fn abs(args: &[TypeValue]) -> Result<TypeValue, String> {
+    match &args[0] {
+        TypeValue::IntegerValueType(v) => match v.value.checked_abs() {
+            Some(value) => integer(value),
+            None => error("the result is out of range"),
+        },
+        TypeValue::DecimalValueType(v) => decimal(v.mantissa.abs(), v.scale),
+        TypeValue::NumberValueType(v) => float(v.value.abs()),
+        _ => error("expected a number"),
+    }
+}
+
+fn floor(args: &[TypeValue]) -> Result<TypeValue, String> {
+    match &args[0] {
+        TypeValue::IntegerValueType(v) => integer(v.value),
+        TypeValue::DecimalValueType(v) => decimal(v.mantissa.div_euclid(power_of_ten(v.scale)?), 0),
+        TypeValue::NumberValueType(v) => float(v.value.floor()),
+        _ => error("expected a number"),
+    }
+}
+
+fn ceil(args: &[TypeValue]) -> Result<TypeValue, String> {
+    match &args[0] {
+        TypeValue::IntegerValueType(v) => integer(v.value),
+        TypeValue::DecimalValueType(v) => {
+            let factor = power_of_ten(v.scale)?;
+            let whole = v.mantissa.div_euclid(factor);
+
+            match v.mantissa.rem_euclid(factor) {
+                0 => decimal(whole, 0),
+                _ => decimal(whole + 1, 0),
+            }
+        }
+        TypeValue::NumberValueType(v) => float(v.value.ceil()),
+        _ => error("expected a number"),
+    }
+}
+
+// divides and rounds halves away from zero
+fn divide_rounded(mantissa: i128, factor: i128) -> i128 {
+    let quotient = mantissa / factor;
+    let remainder = (mantissa % factor).abs();
+
+    // the remainder is at least half the factor, without doubling it
+    if remainder >= factor - remainder {
+        quotient + mantissa.signum()
+    } else {
+        quotient
+    }
+}
+
+// rounds to as many digits after the point as given, before it for negative digits
+fn round(args: &[TypeValue]) -> Result<TypeValue, String> {
+    let digits = match args.get(1) {
+        Some(digits) => integer_arg(digits)?,
+        None => 0,
+    };
+
+    let rounded = |mantissa: i128, scale: u32| -> Result<(i128, u32), String> {
+        if digits >= scale as i64 {
+            return Ok((mantissa, scale));
+        }
+
+        if digits >= 0 {
+            let factor = power_of_ten(scale - digits as u32)?;
+            return Ok((divide_rounded(mantissa, factor), digits as u32));
+        }
+
+        let scale_factor = power_of_ten(scale)?;
+
+        // rounding off more digits before the point than an i128 has leaves nothing
+        let Some((whole, factor)) = u32::try_from(digits.unsigned_abs())
+            .ok()
+            .and_then(|digits| 10i128.checked_pow(digits))
+            .and_then(|whole| Some((whole, scale_factor.checked_mul(whole)?)))
+        else {
+            return Ok((0, 0));
+        };
+
+        match divide_rounded(mantissa, factor).checked_mul(whole) {
+            Some(mantissa) => Ok((mantissa, 0)),
+            None => error("the result is out of range"),
+        }
+    };
+
+    match &args[0] {
+        TypeValue::IntegerValueType(v) => {
+            let (mantissa, _) = rounded(v.value as i128, 0)?;
+
+            match i64::try_from(mantissa) {
+                Ok(value) => integer(value),
+                Err(_) => error("the result is out of range"),
+            }
+        }
+        TypeValue::DecimalValueType(v) => {
+            let (mantissa, scale) = rounded(v.mantissa, v.scale)?;
+            decimal(mantissa, scale)
+        }
+        TypeValue::NumberValueType(v) => {
+            let factor = 10f64.powi(digits.clamp(-308, 308) as i32);
+            float((v.value * factor).round() / factor)
+        }
+        _ => error("expected a number"),
+    }
+}
+
+fn sign(args: &[TypeValue]) -> Result<TypeValue, String> {
+    match float_arg(&args[0])? {
+        v if v > 0.0 => integer(1),
+        v if v < 0.0 => integer(-1),
+        _ => integer(0),
+    }
+}
+
+fn modulo(args: &[TypeValue]) -> Result<TypeValue, String> {
+    arithmetic::apply(&ExpressionBinaryOperator::Modulo, &args[0], &args[1])
+        .map_err(|e| e.to_string())
+}
+
+fn sqrt(args: &[TypeValue]) -> Result<TypeValue, String> {
+    match float_arg(&args[0])? {
+        v if v < 0.0 => error("square root of a negative number"),
+        v => float(v.sqrt()),
+    }
+}
+
+fn power(args: &[TypeValue]) -> Result<TypeValue, String> {
+    float(float_arg(&args[0])?.powf(float_arg(&args[1])?))
+}
+
+fn exp(args: &[TypeValue]) -> Result<TypeValue, String> {
+    float(float_arg(&args[0])?.exp())
+}
+
+fn ln(args: &[TypeValue]) -> Result<TypeValue, String> {
+    match float_arg(&args[0])? {
+        v if v <= 0.0 => error("logarithm of a number that is not positive"),
+        v => float(v.ln()),
+    }
+}
+
+fn log10(args: &[TypeValue]) -> Result<TypeValue, String> {
+    match float_arg(&args[0])? {
+        v if v <= 0.0 => error("logarithm of a number that is not positive"),
+        v => float(v.log10()),
+    }
+}
+
+// the argument that is ordered first, nulls are left out
+fn extreme(args: &[TypeValue], first: Ordering) -> Result<TypeValue, String> {
+    let mut found: Option<&TypeValue> = None;
+
+    for arg in args
+        .iter()
+        .filter(|arg| !matches!(arg, TypeValue::NullValueType))
+    {
+        found = match found {
+            None => Some(arg),
+            Some(current) => match arg.partial_cmp(current) {
+                Some(ordering) if ordering == first => Some(arg),
+                Some(_) => Some(current),
+                // values that don't compare
+                None => return error("the values do not compare"),
+            },
+        };
+    }
+
+    Ok(found.cloned().unwrap_or(TypeValue::NullValueType))
+}
+
+fn greatest(args: &[TypeValue]) -> Result<TypeValue, String> {
+    extreme(args, Ordering::Greater)
+}
+
+fn least(args: &[TypeValue]) -> Result<TypeValue, String> {
+    extreme(args, Ordering::Less)
+}
+
+fn coalesce(args: &[TypeValue]) -> Result<TypeValue, String> {
+    Ok(args
+        .iter()
+        .find(|arg| !matches!(arg, TypeValue::NullValueType))
+        .cloned()
+        .unwrap_or(TypeValue::NullValueType))
+}
+
+// null if both are the same, otherwise the first
+fn nullif(args: &[TypeValue]) -> Result<TypeValue, String> {
+    match (&args[0], &args[1]) {
+        (TypeValue::NullValueType, _) | (_, TypeValue::NullValueType) => Ok(args[0].clone()),
+        (a, b) if a == b => Ok(TypeValue::NullValueType),
+        (a, _) => Ok(a.clone()),
+    }
+}
+
+const MICROS_PER_SECOND: i64 = 1_000_000;
+const MICROS_PER_MINUTE: i64 = 60 * MICROS_PER_SECOND;
+const MICROS_PER_HOUR: i64 = 60 * MICROS_PER_MINUTE;
+
+// a field of a date or timestamp, like EXTRACT. Seconds and the epoch keep their fraction.
+fn date_part(args: &[TypeValue]) -> Result<TypeValue, String> {
+    let field = string_arg(&args[0])?.to_lowercase();
+    let micros = micros_arg(&args[1])?;
+
+    let days = micros.div_euclid(MICROS_PER_DAY);
+    let time = micros.rem_euclid(MICROS_PER_DAY);
+    let (year, month, day) = civil_from_days(days);
+
+    match field.as_str() {
+        "year" => integer(year),
+        "quarter" => integer((month as i64 - 1) / 3 + 1),
+        "month" => integer(month as i64),
+        "day" => integer(day as i64),
+        "hour" => integer(time / MICROS_PER_HOUR),
+        "minute" => integer(time % MICROS_PER_HOUR / MICROS_PER_MINUTE),
+        "second" => decimal((time % MICROS_PER_MINUTE) as i128, 6),
+        "microsecond" | "microseconds" => integer(time % MICROS_PER_MINUTE),
+        // sunday is 0
+        "dow" => integer((days + 4).rem_euclid(7)),
+        "doy" => match days_from_civil(year, 1, 1) {
+            Some(first) => integer(days - first + 1),
+            None => error("date out of range"),
+        },
+        "epoch" => decimal(micros as i128, 6),
+        _ => Err(format!("unknown field `{field}`")),
+    }
+}
+
+// a date or timestamp cut down to the start of the unit it is in, as a timestamp
+fn date_trunc(args: &[TypeValue]) -> Result<TypeValue, String> {
+    let unit = string_arg(&args[0])?.to_lowercase();
+    let micros = micros_arg(&args[1])?;
+
+    let days = micros.div_euclid(MICROS_PER_DAY);
+    let (year, month, _) = civil_from_days(days);
+
+    let start_of_day = |days: Option<i64>| days?.checked_mul(MICROS_PER_DAY);
+
+    let truncated = match unit.as_str() {
+        "year" => start_of_day(days_from_civil(year, 1, 1)),
+        "quarter" => start_of_day(days_from_civil(year, (month - 1) / 3 * 3 + 1, 1)),
+        "month" => start_of_day(days_from_civil(year, month, 1)),
+        // weeks start on monday
+        "week" => start_of_day(Some(days - (days + 3).rem_euclid(7))),
+        "day" => start_of_day(Some(days)),
+        "hour" => micros.checked_sub(micros.rem_euclid(MICROS_PER_HOUR)),
+        "minute" => micros.checked_sub(micros.rem_euclid(MICROS_PER_MINUTE)),
+        "second" => micros.checked_sub(micros.rem_euclid(MICROS_PER_SECOND)),
+        _ => return Err(format!("unknown unit `{unit}`")),
+    };
+
+    match truncated {
+        Some(micros) => Ok(TypeValue::TimestampValueType(TimestampValueType { micros })),
+        None => error("timestamp out of range"),
+    }
+}
+
+// the day of a date, timestamp or string spelling out a date
+fn date(args: &[TypeValue]) -> Result<TypeValue, String> {
+    let date = match &args[0] {
+        TypeValue::StringTypeValue(s) => DateValueType::parse(&s.value),
+        value => DateValueType::from_days(micros_arg(value)?.div_euclid(MICROS_PER_DAY)),
+    };
+
+    match date {
+        Some(date) => Ok(TypeValue::DateValueType(date)),
+        None => error("not a date"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(name: &str, args: &[TypeValue]) -> TypeValue {
+        lookup(name)
+            .expect("No such function")
+            .call(args)
+            .expect("Could not call the function")
+    }
+
+    fn text(value: &str) -> TypeValue {
+        TypeValue::StringTypeValue(StringTypeValue {
+            value: value.to_string(),
+        })
+    }
+
+    fn int(value: i64) -> TypeValue {
+        TypeValue::IntegerValueType(IntegerValueType { value })
+    }
+
+    fn dec(s: &str) -> TypeValue {
+        TypeValue::DecimalValueType(DecimalValueType::parse(s).expect("Not a decimal"))
+    }
+
+    #[test]
+    fn functions_are_looked_up_and_called() {
+        let null = TypeValue::NullValueType;
+        let at = TypeValue::TimestampValueType(
+            TimestampValueType::parse("2023-05-17 13:45:30.5").expect("Not a timestamp"),
+        );
+
+        for (name, args, expected) in [
+            ("LOWER", vec![text("MiXeD")], text("mixed")),
+            ("length", vec![text("héllo")], int(5)),
+            ("trim", vec![text("xxhixx"), text("x")], text("hi")),
+            ("substr", vec![text("hello"), int(2), int(3)], text("ell")),
+            ("substr", vec![text("hello"), int(0), int(2)], text("h")),
+            ("left", vec![text("hello"), int(-2)], text("hel")),
+            ("right", vec![text("hello"), int(2)], text("lo")),
+            ("strpos", vec![text("hello"), text("ll")], int(3)),
+            ("concat", vec![text("a"), null.clone(), int(1)], text("a1")),
+            ("upper", vec![null.clone()], null.clone()),
+            ("abs", vec![dec("-1.50")], dec("1.50")),
+            ("floor", vec![dec("-1.5")], dec("-2")),
+            ("ceil", vec![dec("1.2")], dec("2")),
+            ("round", vec![dec("2.345"), int(2)], dec("2.35")),
+            ("round", vec![dec("-2.5")], dec("-3")),
+            ("round", vec![int(1250), int(-2)], int(1300)),
+            ("mod", vec![int(7), int(3)], int(1)),
+            (
+                "greatest",
+                vec![int(1), null.clone(), dec("2.5")],
+                dec("2.5"),
+            ),
+            ("coalesce", vec![null.clone(), int(2), int(3)], int(2)),
+            ("nullif", vec![int(2), int(2)], null.clone()),
+            ("date_part", vec![text("year"), at.clone()], int(2023)),
+            ("date_part", vec![text("dow"), at.clone()], int(3)),
+            ("date_part", vec![text("second"), at.clone()], dec("30.5")),
+            (
+                "date_trunc",
+                vec![text("month"), at.clone()],
+                TypeValue::TimestampValueType(
+                    TimestampValueType::parse("2023-05-01 00:00:00").expect("Not a timestamp"),
+                ),
+            ),
+            (
+                "date",
+                vec![at.clone()],
+                TypeValue::DateValueType(DateValueType::parse("2023-05-17").expect("Not a date")),
+            ),
+        ] {
+            assert_eq!(call(name, &args), expected, "{name}({args:?})");
+        }
+
+        assert!(lookup("sum").is_none());
+        assert!(!lookup("lower").expect("No such function").accepts(2));
+        assert!(lookup("sqrt")
+            .expect("No such function")
+            .call(&[int(-1)])
+            .is_err());
+    }
+
+    #[test]
+    fn rounding_does_not_overflow() {
+        let call_err = |name: &str, args: Vec<TypeValue>| {
+            lookup(name).expect("No such function").call(&args).is_err()
+        };
+
+        // more digits before the point than there are rounds down to nothing
+        assert_eq!(call("round", &[int(1250), int(i64::MIN)]), int(0));
+        assert_eq!(call("round", &[dec("12.5"), int(-60)]), dec("0"));
+        assert_eq!(
+            call(
+                "round",
+                &[
+                    TypeValue::DecimalValueType(DecimalValueType::new(i128::MAX, 1)),
+                    int(0)
+                ]
+            ),
+            TypeValue::DecimalValueType(DecimalValueType::new(i128::MAX / 10 + 1, 0))
+        );
+        assert_eq!(call("ceil", &[dec("-1.2")]), dec("-1"));
+
+        // a scale too big for the mantissa to be divided by
+        let tiny = TypeValue::DecimalValueType(DecimalValueType::new(1, 50));
+
+        assert!(call_err("round", vec![tiny.clone(), int(2)]));
+        assert!(call_err("round", vec![tiny.clone(), int(-2)]));
+        assert!(call_err("floor", vec![tiny.clone()]));
+        assert!(call_err("ceil", vec![tiny.clone()]));
+        assert!(call_err("left", vec![text("hello"), tiny]));
+        assert!(call_err("round", vec![int(i64::MAX), int(-1)]));
+
+        // float digit counts are not saturated into range
+        let float = |value: f64| TypeValue::NumberValueType(NumberValueType { value });
+
+        assert!(call_err("round", vec![dec("1.5"), float(1e30)]));
+        assert!(call_err("round", vec![dec("1.5"), float(f64::NAN)]));
+        assert!(call_err("round", vec![dec("1.5"), float(f64::INFINITY)]));
+        assert!(call_err("left", vec![text("hello"), float(9.3e18)]));
+        assert_eq!(call("round", &[dec("1.25"), float(1.0)]), dec("1.3"));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::db::models::{number_value::NumberValueType, string_value::StringTypeValue};
+use crate::db::models::{boolean_value::BooleanValueType, string_value::StringTypeValue};
 use serde::{Deserialize, Serialize};
 use sqlparser::ast::{
     Expr, Ident, SetExpr,
@@ -16,30 +16,37 @@ fn expr_to_value(i: &Expr) -> Option<TypeValue> {
             Some(_) => Some(TypeValue::StringTypeValue(StringTypeValue {
                 value: value.to_string(),
             })),
-            // todo: Also make a place for boolean values here
-            None => match value.parse::<f64>() {
-                Ok(v) => Some(TypeValue::NumberValueType(NumberValueType { value: v })),
-                Err(e) => {
-                    eprintln!("Error with parsing float: {value} error: {:?}", e);
-                    None
+            None => match value.to_lowercase().as_str() {
+                "true" => Some(TypeValue::BooleanValueType(BooleanValueType {
+                    value: true,
+                })),
+                "false" => Some(TypeValue::BooleanValueType(BooleanValueType {
+                    value: false,
+                })),
+                _ => {
+                    let v = TypeValue::parse_number(value);
+
+                    if v.is_none() {
+                        eprintln!("Error with parsing the value: {value}");
+                    }
+
+                    v
                 }
             },
         },
-        Expr::Value(value) => match value {
-            sqlparser::ast::Value::Number(s, _) => match s.parse::<f64>() {
-                Ok(v) => Some(TypeValue::NumberValueType(NumberValueType { value: v })),
-                Err(e) => {
-                    eprintln!("Error with parsing float: {value} error: {:?}", e);
-                    None
-                }
-            },
-            _ => None,
+        // literals, typed literals like DATE '2023-01-31' and negative numbers
+        expr => match TypeValue::try_from(expr) {
+            Ok(TypeValue::NullValueType) => None,
+            Ok(v) => Some(v),
+            Err(e) => {
+                eprintln!("Error with parsing the value: {expr} error: {:?}", e);
+                None
+            }
         },
-        _ => None,
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub struct InsertQuery {
     pub table_name: String,
     pub columns: Vec<String>,
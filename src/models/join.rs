@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+use sqlparser::ast::{self, Ident, JoinConstraint, JoinOperator, TableFactor};
+
+use super::errors::StatementError;
+use super::expression::Expression;
+
+// The relation a select joins its `from` relation with, e.g. `JOIN customer ON orders.customer_id = customer.id`.
+// Only inner joins with an ON constraint are supported, the columns of both relations are
+// referred to by their qualified names in the constraint.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct Join {
+    pub relation: String,
+    pub constraint: Expression,
+}
+
+// `orders.id` for the compound identifier of a qualified column
+pub fn qualified_name(idents: &[Ident]) -> String {
+    idents
+        .iter()
+        .map(|i| i.value.as_str())
+        .collect::<Vec<&str>>()
+        .join(".")
+}
+
+// The name of a plain table in a FROM or JOIN. Joined rows are qualified by the names of their
+// relations, columns qualified by an alias would never match, so aliases are rejected.
+pub fn table_name(relation: &TableFactor) -> Result<String, StatementError> {
+    match relation {
+        TableFactor::Table {
+            name,
+            alias,
+            args: _,
+            with_hints: _,
+        } => match (name.0.first(), alias) {
+            (Some(v), None) => Ok(v.value.clone()),
+            (Some(v), Some(_)) => Err(StatementError::AliasNotSupportedError(v.value.clone())),
+            (None, _) => Err(StatementError::NotImplementedError()),
+        },
+        _ => Err(StatementError::NotImplementedError()),
+    }
+}
+
+impl TryFrom<&ast::Join> for Join {
+    type Error = StatementError;
+
+    fn try_from(join: &ast::Join) -> Result<Self, Self::Error> {
+        let relation = table_name(&join.relation)?;
+
+        let constraint = match &join.join_operator {
+            JoinOperator::Inner(JoinConstraint::On(expr)) => Expression::try_from(expr)?,
+            _ => return Err(StatementError::NotImplementedError()),
+        };
+
+        Ok(Join {
+            relation,
+            constraint,
+        })
+    }
+}
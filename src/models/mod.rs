@@ -1,7 +1,17 @@
+pub mod aggregate;
+pub mod arithmetic;
 pub mod constraint;
+pub mod create_index_query;
+pub mod create_table_query;
+pub mod delete_query;
+pub mod drop_view_query;
 pub mod errors;
 pub mod expression;
+pub mod functions;
 pub mod insert_query;
+pub mod join;
+pub mod projection;
 pub mod select_query;
 pub mod statement;
 pub mod tcp_stream_message;
+pub mod update_query;
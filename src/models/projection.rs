@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+use sqlparser::ast::{Expr, Ident, SelectItem, WildcardAdditionalOptions};
+
+use super::errors::StatementError;
+use super::expression::Expression;
+use super::join::qualified_name;
+
+// One item of the projection of a select
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub enum ProjectionItem {
+    // every column, `*`
+    Wildcard,
+    // every column of one relation of a join, `orders.*`
+    QualifiedWildcard(String),
+    // a column computed by the expression, materialized under the alias
+    Expression(Expression, String),
+}
+
+impl ProjectionItem {
+    // An expression under its alias. Columns keep their (qualified) names unless renamed, anything
+    // else is named after the expression as written, like `price * 2`.
+    pub fn from_expr(expr: &Expr, alias: Option<&Ident>) -> Result<Self, StatementError> {
+        let expression = Expression::try_from(expr)?;
+
+        let alias = match (alias, expr) {
+            (Some(alias), _) => alias.value.clone(),
+            (None, Expr::Identifier(ident)) => ident.value.clone(),
+            (None, Expr::CompoundIdentifier(idents)) => qualified_name(idents),
+            (None, expr) => expr.to_string(),
+        };
+
+        Ok(ProjectionItem::Expression(expression, alias))
+    }
+
+    // the columns the item reads, None for wildcards which read every column
+    pub fn columns(&self) -> Option<Vec<&str>> {
+        match self {
+            ProjectionItem::Expression(expression, _) => Some(expression.columns()),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<&SelectItem> for ProjectionItem {
+    type Error = StatementError;
+
+    fn try_from(item: &SelectItem) -> Result<Self, Self::Error> {
+        // wildcards leaving out or renaming columns are not supported
+        let plain = |options: &WildcardAdditionalOptions| {
+            if *options == WildcardAdditionalOptions::default() {
+                Ok(())
+            } else {
+                Err(StatementError::NotImplementedError())
+            }
+        };
+
+        match item {
+            SelectItem::UnnamedExpr(expr) => ProjectionItem::from_expr(expr, None),
+            SelectItem::ExprWithAlias { expr, alias } => {
+                ProjectionItem::from_expr(expr, Some(alias))
+            }
+            SelectItem::Wildcard(options) => {
+                plain(options)?;
+                Ok(ProjectionItem::Wildcard)
+            }
+            SelectItem::QualifiedWildcard(name, options) => {
+                plain(options)?;
+                Ok(ProjectionItem::QualifiedWildcard(qualified_name(&name.0)))
+            }
+        }
+    }
+}
@@ -1,20 +1,63 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 use sqlparser::ast::{
-    SelectItem,
+    Expr, SelectItem,
     SetExpr::{self, Select},
-    TableFactor,
 };
 
+use crate::db::data::TypeValue;
+
+use super::aggregate::Aggregate;
 use super::errors::StatementError;
 use super::expression::Expression;
+use super::join::{qualified_name, table_name, Join};
+use super::projection::ProjectionItem;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub struct SelectQuery {
     pub into: String,
-    pub projection: Vec<String>,
+    pub projection: Vec<ProjectionItem>,
     pub from: String,
     pub constraints: Option<Expression>,
+    // aggregates in the projection, the plain columns of an aggregating select are its group by columns
+    pub aggregates: Vec<Aggregate>,
+    pub group_by: Vec<String>,
+    pub join: Option<Join>,
+}
+
+impl SelectQuery {
+    // whether a row satisfies the constraints of this select, no constraints matches every row
+    pub fn matches(&self, row: &HashMap<String, TypeValue>) -> bool {
+        match &self.constraints {
+            Some(constraints) => constraints.evaluate(row).unwrap_or(false),
+            None => true,
+        }
+    }
+
+    pub fn is_aggregate(&self) -> bool {
+        !self.aggregates.is_empty() || !self.group_by.is_empty()
+    }
+
+    // the relations this select reads from, a joining select is subscribed to both of its relations
+    pub fn relations(&self) -> Vec<&str> {
+        let mut relations = vec![self.from.as_str()];
+
+        if let Some(join) = &self.join {
+            relations.push(join.relation.as_str());
+        }
+
+        relations
+    }
+
+    // Whether a row inserted into one of the relations could change the result of this select.
+    // The constraints of a join are over the joined rows, so any row of a joined relation could.
+    pub fn matches_relation(&self, relation: &str, row: &HashMap<String, TypeValue>) -> bool {
+        match &self.join {
+            Some(join) => relation == self.from || relation == join.relation,
+            None => relation == self.from && self.matches(row),
+        }
+    }
 }
 
 impl TryFrom<&SetExpr> for SelectQuery {
@@ -36,47 +79,118 @@ impl TryFrom<&SetExpr> for SelectQuery {
                 None => Err(StatementError::NoIntoSpecifiedForSelect()),
             }?;
 
-            let projection: Vec<String> = select
-                .projection
+            let mut projection: Vec<ProjectionItem> = vec![];
+            let mut aggregates: Vec<Aggregate> = vec![];
+
+            for item in select.projection.iter() {
+                let aggregate = match item {
+                    SelectItem::UnnamedExpr(Expr::Function(function)) => {
+                        Aggregate::try_from_function(function, None)?
+                    }
+                    SelectItem::ExprWithAlias {
+                        expr: Expr::Function(function),
+                        alias,
+                    } => Aggregate::try_from_function(function, Some(alias.value.clone()))?,
+                    _ => None,
+                };
+
+                match aggregate {
+                    Some(aggregate) => aggregates.push(aggregate),
+                    None => projection.push(ProjectionItem::try_from(item)?),
+                }
+            }
+
+            let group_by = select
+                .group_by
                 .iter()
-                .map(|v| match v {
-                    SelectItem::UnnamedExpr(expr) => match expr {
-                        sqlparser::ast::Expr::Identifier(ident) => ident.value.clone(),
-                        _ => "".to_string(),
-                    },
-                    _ => "*".to_string(),
+                .map(|expr| match expr {
+                    Expr::Identifier(ident) => Ok(ident.value.clone()),
+                    Expr::CompoundIdentifier(idents) => Ok(qualified_name(idents)),
+                    _ => Err(StatementError::NotImplementedError()),
                 })
-                .collect();
-
-            let from = match select.from.first() {
-                Some(v) => match &v.relation {
-                    TableFactor::Table {
-                        name,
-                        alias: _,
-                        args: _,
-                        with_hints: _,
-                    } => match name.0.first() {
-                        Some(v) => Ok(v.value.clone()),
-                        None => Err(StatementError::NotImplementedError()),
+                .collect::<Result<Vec<String>, StatementError>>()?;
+
+            // every column the projection of an aggregating select reads has to be one of its groups
+            if !aggregates.is_empty() || !group_by.is_empty() {
+                for item in projection.iter() {
+                    match item.columns() {
+                        Some(columns) => {
+                            if let Some(column) = columns
+                                .into_iter()
+                                .find(|c| !group_by.iter().any(|g| g == c))
+                            {
+                                return Err(StatementError::ColumnNotGroupedError(
+                                    column.to_string(),
+                                ));
+                            }
+                        }
+                        None => return Err(StatementError::ColumnNotGroupedError("*".to_string())),
+                    }
+                }
+            }
+
+            // a single relation, optionally joined with one other relation
+            let (from, join) = match select.from.as_slice() {
+                [table] => (
+                    table_name(&table.relation)?,
+                    match table.joins.as_slice() {
+                        [] => None,
+                        [join] => Some(Join::try_from(join)?),
+                        _ => return Err(StatementError::NotImplementedError()),
                     },
-                    _ => Err(StatementError::NotImplementedError()),
-                },
-                None => Err(StatementError::NotImplementedError()),
-            }?;
+                ),
+                _ => return Err(StatementError::NotImplementedError()),
+            };
 
+            // without aliases the two sides of a self join could not be told apart
+            if matches!(&join, Some(join) if join.relation == from) {
+                return Err(StatementError::NotImplementedError());
+            }
+
+            // rows of a single relation are not qualified, so all of its columns are just `*`
+            for item in projection.iter_mut() {
+                if let ProjectionItem::QualifiedWildcard(relation) = item {
+                    if *relation == from && join.is_none() {
+                        *item = ProjectionItem::Wildcard;
+                    } else if *relation != from
+                        && !matches!(&join, Some(join) if join.relation == *relation)
+                    {
+                        return Err(StatementError::UnknownRelationError(relation.clone()));
+                    }
+                }
+            }
+
+            // a where clause that doesn't convert rejects the select, leaving it out would match
+            // every row
             let constraints = match &select.selection {
-                Some(r) => match Expression::try_from(r) {
-                    Ok(result) => Some(result),
-                    Err(_) => None,
-                },
+                Some(r) => Some(Expression::try_from(r)?),
                 None => None,
             };
 
+            // the where clause of a join is over the joined rows, which are only known by their
+            // qualified columns, anything else would match nothing
+            if let (Some(join), Some(constraints)) = (&join, &constraints) {
+                for column in constraints.columns() {
+                    match column.split_once('.') {
+                        Some((relation, _)) if relation == from || relation == join.relation => {}
+                        Some((relation, _)) => {
+                            return Err(StatementError::UnknownRelationError(relation.to_string()))
+                        }
+                        None => {
+                            return Err(StatementError::UnqualifiedColumnError(column.to_string()))
+                        }
+                    }
+                }
+            }
+
             Ok(SelectQuery {
                 into,
                 projection,
                 from,
                 constraints,
+                aggregates,
+                group_by,
+                join,
             })
         } else {
             Err(StatementError::NotImplementedError())
@@ -1,6 +1,14 @@
-use super::{insert_query::InsertQuery, select_query::SelectQuery};
+use super::{
+    create_index_query::CreateIndexQuery, create_table_query::CreateTableQuery,
+    delete_query::DeleteQuery, insert_query::InsertQuery, select_query::SelectQuery,
+    update_query::UpdateQuery,
+};
 
 pub enum Statement {
     Select(SelectQuery),
     Insert(InsertQuery),
+    Update(UpdateQuery),
+    Delete(DeleteQuery),
+    CreateTable(CreateTableQuery),
+    CreateIndex(CreateIndexQuery),
 }
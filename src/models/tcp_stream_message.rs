@@ -1,10 +1,20 @@
-use tokio::net::TcpStream;
-
-#[derive(Debug)]
-pub enum TcpStreamMessage {
-    Connect(String, TcpStream),
-    Disconnect(String),
-    Write(String, Vec<u8>),
-    Read(String, Vec<u8>),
-    WriteAll(Vec<u8>),
-}
+use tokio::net::TcpStream;
+
+use crate::messaging::{CorrelationId, Message};
+
+#[derive(Debug)]
+pub enum TcpStreamMessage {
+    Connect(String, TcpStream),
+    Disconnect(String),
+    Write(String, Vec<u8>),
+    Read(String, Box<Message>),
+    WriteAll(Vec<u8>),
+    // a select of this node, written to every peer now and to every peer connecting later on
+    Subscribe(CorrelationId, Vec<u8>),
+    // a select of this node that was dropped, it is not sent to the peers connecting later on
+    Unsubscribe(CorrelationId),
+    // time for the periodic work of the runtime, like gossiping
+    Tick,
+    // time to ping every peer and check on the ones we haven't heard from
+    Heartbeat,
+}
@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use sqlparser::ast::Statement::{self, Update};
+use std::collections::HashMap;
+
+use crate::db::data::TypeValue;
+
+use super::errors::StatementError;
+use super::expression::{Expression, ExpressionEvaluationError};
+use super::join::table_name;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct UpdateQuery {
+    pub table_name: String,
+    // the columns that are set, to what they are set to
+    pub assignments: Vec<(String, Expression)>,
+    pub constraints: Option<Expression>,
+}
+
+impl UpdateQuery {
+    // whether a row is updated by this query, no constraints updates every row
+    pub fn matches(&self, row: &HashMap<String, TypeValue>) -> bool {
+        match &self.constraints {
+            Some(constraints) => constraints.evaluate(row).unwrap_or(false),
+            None => true,
+        }
+    }
+
+    // the row after the update, the assignments are resolved against the row before it
+    pub fn apply(
+        &self,
+        row: &HashMap<String, TypeValue>,
+    ) -> Result<HashMap<String, TypeValue>, ExpressionEvaluationError> {
+        let mut updated = row.clone();
+
+        for (column, expression) in self.assignments.iter() {
+            updated.insert(column.clone(), expression.resolve(row)?);
+        }
+
+        Ok(updated)
+    }
+}
+
+impl TryFrom<&Statement> for UpdateQuery {
+    type Error = StatementError;
+
+    fn try_from(value: &Statement) -> Result<Self, Self::Error> {
+        if let Update {
+            table,
+            assignments,
+            from,
+            selection,
+            returning: _,
+        } = value
+        {
+            if from.is_some() || !table.joins.is_empty() {
+                return Err(StatementError::NotImplementedError());
+            }
+
+            let assignments = assignments
+                .iter()
+                .map(|assignment| {
+                    let column = match assignment.id.last() {
+                        Some(ident) => ident.value.clone(),
+                        None => return Err(StatementError::NotImplementedError()),
+                    };
+
+                    Ok((column, Expression::try_from(&assignment.value)?))
+                })
+                .collect::<Result<Vec<(String, Expression)>, StatementError>>()?;
+
+            let constraints = match selection {
+                Some(expr) => Some(Expression::try_from(expr)?),
+                None => None,
+            };
+
+            Ok(UpdateQuery {
+                table_name: table_name(&table.relation)?,
+                assignments,
+                constraints,
+            })
+        } else {
+            Err(StatementError::NotImplementedError())
+        }
+    }
+}
@@ -1,63 +1,247 @@
-use db::data::Db;
+use turnip_rs::db::data::TypeValue;
+use turnip_rs::db::snapshot;
+use turnip_rs::db::wal::FsyncPolicy;
 // this is going to be a Read-Eval-Print-Loop for turnip, which will work by putting in
-use models::insert_query::InsertQuery;
-use models::select_query::SelectQuery;
-use runtime::TurnipRuntime;
+use turnip_rs::models::create_index_query::CreateIndexQuery;
+use turnip_rs::models::create_table_query::CreateTableQuery;
+use turnip_rs::models::delete_query::DeleteQuery;
+use turnip_rs::models::drop_view_query::DropViewQuery;
+use turnip_rs::models::insert_query::InsertQuery;
+use turnip_rs::models::select_query::SelectQuery;
+use turnip_rs::models::update_query::UpdateQuery;
+use turnip_rs::runtime::{load_node_id, PeerState, TurnipRuntime, TurnipRuntimeConfig};
 
-use postcard::{from_bytes, to_vec};
 use sqlparser::parser::Parser;
 use sqlparser::{
-    ast::Statement::{Insert, Query},
+    ast::Statement::{CreateIndex, CreateTable, Delete, Drop, Insert, Query, Update},
     dialect::GenericDialect,
 };
 
-use messaging::Message;
+use turnip_rs::messaging::{
+    next_correlation_id, select_reply, CorrelationId, Message, MessageKind,
+};
 
+use std::collections::HashMap;
 use std::io::{self, BufRead};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+
+// where the log, the snapshots and the id of this node are kept
+const SNAPSHOT_DIRECTORY: &str = "turnip_8080";
 
-mod db;
-mod messaging;
-mod models;
-mod runtime;
-mod server;
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60);
 
 #[tokio::main]
 async fn main() -> io::Result<()> {
     let stdin = io::stdin();
 
-    let mut db = Db::new();
+    // the data this node owns, its views and the selects other nodes have made against it,
+    // restored from the last snapshot and the log after it
+    let (db, select_index) = snapshot::restore(
+        SNAPSHOT_DIRECTORY,
+        format!("{SNAPSHOT_DIRECTORY}/wal.log"),
+        FsyncPolicy::Always,
+    )
+    .expect("Could not restore the db");
 
-    // the runtime
-    let mut runtime = TurnipRuntime::new("8080");
+    let db = Arc::new(Mutex::new(db));
+    let select_index = Arc::new(Mutex::new(select_index));
+
+    // the views that the results of our selects go into, by the id of the select
+    let pending_selects: Arc<Mutex<HashMap<CorrelationId, String>>> =
+        Arc::new(Mutex::new(HashMap::new()));
 
-    // select index
-    // let mut select_index_sender = create_select_index().expect("Could not start the select index.");
+    // the runtime
+    let mut runtime = TurnipRuntime::new(TurnipRuntimeConfig::new(
+        load_node_id(SNAPSHOT_DIRECTORY)?,
+        8080,
+    ));
 
     runtime.run();
 
+    // snapshots keep the log, and with it the time a restart takes, short
+    {
+        let db = db.clone();
+        let select_index = select_index.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SNAPSHOT_INTERVAL);
+            let mut snapshot_lsn = 0;
+
+            loop {
+                interval.tick().await;
+
+                let mut db = db.lock().expect("Db lock poisoned");
+
+                // nothing changed since the last snapshot
+                if db.last_lsn() == snapshot_lsn {
+                    continue;
+                }
+
+                let select_index = select_index.lock().expect("Select index lock poisoned");
+
+                match db.take_snapshot(SNAPSHOT_DIRECTORY, &select_index) {
+                    Ok(path) => {
+                        snapshot_lsn = db.last_lsn();
+                        println!("Took a snapshot: {:?}", path);
+                    }
+                    Err(e) => eprintln!("Error with taking a snapshot: {:?}", e),
+                }
+            }
+        });
+    }
+
     let messenger = runtime
         .get_messenger()
         .expect("Could not get the messenger from the runtime");
 
-    if let Ok(mut receiver) = runtime.get_receiver() {
+    // the changes we send to other nodes are written by one task, in the order they were made, so
+    // a node never gets the delete of a row before its insert
+    let (writes, mut queued) = mpsc::unbounded_channel::<(String, Message)>();
+
+    {
+        let messenger = messenger.clone();
+
+        tokio::spawn(async move {
+            while let Some((addr, message)) = queued.recv().await {
+                messenger.write(addr, &message).await;
+            }
+        });
+    }
+
+    if let Ok(mut subscription) = runtime.get_subscription(vec![MessageKind::Select]) {
+        let select_index = select_index.clone();
+        let db = db.clone();
+        let writes = writes.clone();
+
         tokio::spawn(async move {
-            while let Ok(msg) = receiver.recv().await {
+            while let Some(received) = subscription.recv().await {
+                if let Message::Select(id, select) = received.message {
+                    // the select is indexed while the data is still locked, so a change made to it
+                    // is either in the answer or sent to the node after the answer
+                    let db = db.lock().expect("Db lock poisoned");
+                    let mut select_index = select_index.lock().expect("Select index lock poisoned");
 
-                let m: Message = match from_bytes(&msg) {
-                    Ok(m) => m,
-                    Err(_e) => return,
-                };
+                    // the data we own that answers the select
+                    let rows = match db.query_data_for_view(&select) {
+                        Ok(rows) => rows,
+                        Err(e) => {
+                            write_to(
+                                &writes,
+                                vec![received.addr],
+                                Message::QueryError(id, e.to_string()),
+                            );
+                            continue;
+                        }
+                    };
+
+                    if let Err(e) = select_index.insert_select(&received.addr, select) {
+                        eprintln!("Error with indexing the select: {:?}", e);
+                    }
 
-                let out = match m {
-                    Message::Select(select) => {
+                    for message in select_reply(id, rows) {
+                        write_to(&writes, vec![received.addr.clone()], message);
+                    }
+                }
+            }
+        });
+    };
 
+    // dead nodes can't take the changes to the data they selected anymore
+    if let Ok(mut events) = runtime.get_membership_events() {
+        let select_index = select_index.clone();
+
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) if event.state == PeerState::Dead => select_index
+                        .lock()
+                        .expect("Select index lock poisoned")
+                        .remove_addr(&event.addr),
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(n)) => {
+                        eprintln!("Missed {n} membership events");
+                    }
+                    Err(RecvError::Closed) => return,
+                }
+            }
+        });
+    };
 
+    // What the nodes that own the data we selected send us: their answers to our selects and the
+    // changes to the data after. They are applied by one task in the order they came in, so the
+    // changes a node pushes after its answer are never applied before it.
+    if let Ok(mut subscription) = runtime.get_subscription(vec![
+        MessageKind::Insert,
+        MessageKind::Update,
+        MessageKind::Delete,
+        MessageKind::StartOfResults,
+        MessageKind::QueryResult,
+        MessageKind::EndOfResults,
+        MessageKind::QueryError,
+    ]) {
+        let db = db.clone();
+        let pending_selects = pending_selects.clone();
 
+        tokio::spawn(async move {
+            while let Some(received) = subscription.recv().await {
+                let source = received.addr;
+
+                let view_of = |id: CorrelationId| {
+                    let view = pending_selects
+                        .lock()
+                        .expect("Pending selects lock poisoned")
+                        .get(&id)
+                        .cloned();
+
+                    if view.is_none() {
+                        eprintln!("Received results for an unknown select: {id}");
+                    }
+
+                    view
+                };
+
+                let mut db = db.lock().expect("Db lock poisoned");
+
+                let views = match received.message {
+                    Message::Insert(insert) => db.materialize_insert(&source, &insert),
+                    Message::Update(table, changes) => {
+                        db.materialize_update(&source, &table, &changes)
+                    }
+                    Message::Delete(table, rows) => db.materialize_delete(&source, &table, &rows),
+                    // a node answering a select again, after a reconnect, replaces everything it
+                    // sent us for it before
+                    Message::StartOfResults(id) => match view_of(id) {
+                        Some(view) => db.clear_view_results(&view, &source).map(|_| vec![]),
+                        None => continue,
                     },
-                    _ => return,
+                    Message::QueryResult(id, rows) => match view_of(id) {
+                        Some(view) => db
+                            .insert_into_view(&view, &source, rows)
+                            .map(|_| vec![view]),
+                        None => continue,
+                    },
+                    Message::EndOfResults(id) => {
+                        println!("{source} has sent all results for select {id}");
+                        continue;
+                    }
+                    Message::QueryError(id, e) => {
+                        eprintln!("{source} could not answer select {id}: {e}");
+                        continue;
+                    }
+                    _ => continue,
                 };
 
-                println!("here: {:?}", out);
+                match views {
+                    Ok(views) => {
+                        for view in views {
+                            println!("View {view}: {:?}", db.get_table(&view));
+                        }
+                    }
+                    Err(e) => eprintln!("Error with applying the change to the views: {:?}", e),
+                }
             }
         });
     };
@@ -77,18 +261,35 @@ async fn main() -> io::Result<()> {
                     let select_query = SelectQuery::try_from(&*query.body);
 
                     match select_query {
-                        Ok(select) => match to_vec::<_, 32>(&Message::Select(select)) {
-                            Ok(result) => {
-                                // Writes the
-                                let cloned_messenger = messenger.clone();
-                                tokio::spawn(async move {
-                                    cloned_messenger.write_all(result.to_vec()).await
-                                });
+                        Ok(select) => {
+                            if let Err(e) = db
+                                .lock()
+                                .expect("Db lock poisoned")
+                                .create_view(select.clone())
+                            {
+                                eprintln!("Error with creating the view: {:?}", e);
+                                continue;
                             }
-                            Err(e) => {
-                                eprintln!("An Error ocurred trying to serialize data: {:?}", e);
+
+                            // remember which view the results of the select belong to, the
+                            // selects the view was made by before are done with
+                            let id = next_correlation_id();
+                            let replaced = forget_selects(&pending_selects, &select.into);
+
+                            pending_selects
+                                .lock()
+                                .expect("Pending selects lock poisoned")
+                                .insert(id, select.into.clone());
+
+                            // the old selects go first, so a select is never dropped after it
+                            // was made again
+                            for id in replaced {
+                                messenger.unselect(id).await;
                             }
-                        },
+
+                            // Writes the select to every connected node
+                            messenger.select(id, select).await;
+                        }
                         Err(e) => {
                             eprintln!("Error with getting the Statement: {:?}", e);
                         }
@@ -109,10 +310,150 @@ async fn main() -> io::Result<()> {
                 } => {
                     let insert_query = InsertQuery::try_from(statement);
 
-                    if let Ok(query) = insert_query {
-                        db.insert(query);
+                    if let Ok(mut query) = insert_query {
+                        // the db stays locked until the insert is queued for the interested
+                        // nodes, so a select answered meanwhile can't miss it
+                        let mut db = db.lock().expect("Db lock poisoned");
+
+                        // the other nodes don't know the schema, they get the columns spelled out
+                        if query.columns.is_empty() {
+                            if let Some(schema) = db.get_schema(&query.table_name) {
+                                query.columns = schema.column_names();
+                            }
+                        }
+
+                        if let Err(e) = db.insert(query.clone()) {
+                            eprintln!("Error with inserting the record: {:?}", e);
+                            continue;
+                        }
+
+                        // with insert, we are only interested in sharing data with known nodes that are interested in it.
+                        let addrs = match select_index
+                            .lock()
+                            .expect("Select index lock poisoned")
+                            .get_addr_for_insert(&query)
+                        {
+                            Ok(addrs) => addrs,
+                            Err(e) => {
+                                eprintln!("Error with finding the interested nodes: {:?}", e);
+                                vec![]
+                            }
+                        };
+
+                        write_to(&writes, addrs, Message::Insert(query));
                     }
                 }
+                CreateIndex { .. } => match CreateIndexQuery::try_from(statement) {
+                    Ok(query) => {
+                        let name = query.name.clone();
+
+                        match db.lock().expect("Db lock poisoned").create_index(query) {
+                            Ok(_) => println!("Created index {name}"),
+                            Err(e) => eprintln!("Error with creating the index: {:?}", e),
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("Error with getting the Statement: {:?}", e);
+                    }
+                },
+                CreateTable { .. } => match CreateTableQuery::try_from(statement) {
+                    Ok(query) => {
+                        let name = query.table_name.clone();
+
+                        match db.lock().expect("Db lock poisoned").create_table(query) {
+                            Ok(_) => println!("Created table {name}"),
+                            Err(e) => eprintln!("Error with creating the table: {:?}", e),
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("Error with getting the Statement: {:?}", e);
+                    }
+                },
+                Update { .. } => match UpdateQuery::try_from(statement) {
+                    Ok(query) => {
+                        // locked until the change is queued, like an insert
+                        let mut db = db.lock().expect("Db lock poisoned");
+
+                        let changes = match db.update(&query) {
+                            Ok(changes) => changes,
+                            Err(e) => {
+                                eprintln!("Error with updating the records: {:?}", e);
+                                continue;
+                            }
+                        };
+
+                        // nodes interested in the rows before or after the update
+                        let rows: Vec<HashMap<String, TypeValue>> = changes
+                            .iter()
+                            .flat_map(|(old, new)| [old.clone(), new.clone()])
+                            .collect();
+
+                        let addrs = select_index
+                            .lock()
+                            .expect("Select index lock poisoned")
+                            .get_addr_for_rows(&query.table_name, &rows)
+                            .unwrap_or_else(|e| {
+                                eprintln!("Error with finding the interested nodes: {:?}", e);
+                                vec![]
+                            });
+
+                        println!("Updated {} rows", changes.len());
+
+                        write_to(&writes, addrs, Message::Update(query.table_name, changes));
+                    }
+                    Err(e) => {
+                        eprintln!("Error with getting the Statement: {:?}", e);
+                    }
+                },
+                Delete { .. } => match DeleteQuery::try_from(statement) {
+                    Ok(query) => {
+                        // locked until the change is queued, like an insert
+                        let mut db = db.lock().expect("Db lock poisoned");
+
+                        let rows = match db.delete(&query) {
+                            Ok(rows) => rows,
+                            Err(e) => {
+                                eprintln!("Error with deleting the records: {:?}", e);
+                                continue;
+                            }
+                        };
+
+                        let addrs = select_index
+                            .lock()
+                            .expect("Select index lock poisoned")
+                            .get_addr_for_rows(&query.table_name, &rows)
+                            .unwrap_or_else(|e| {
+                                eprintln!("Error with finding the interested nodes: {:?}", e);
+                                vec![]
+                            });
+
+                        println!("Deleted {} rows", rows.len());
+
+                        write_to(&writes, addrs, Message::Delete(query.table_name, rows));
+                    }
+                    Err(e) => {
+                        eprintln!("Error with getting the Statement: {:?}", e);
+                    }
+                },
+                Drop { .. } => match DropViewQuery::try_from(statement) {
+                    Ok(query) => {
+                        let name = query.name.clone();
+
+                        if let Err(e) = db.lock().expect("Db lock poisoned").drop_view(query) {
+                            eprintln!("Error with dropping the view: {:?}", e);
+                            continue;
+                        }
+
+                        for id in forget_selects(&pending_selects, &name) {
+                            messenger.unselect(id).await;
+                        }
+
+                        println!("Dropped view {name}");
+                    }
+                    Err(e) => {
+                        eprintln!("Error with getting the Statement: {:?}", e);
+                    }
+                },
                 _ => {
                     println!("Found something else");
                 }
@@ -122,3 +463,38 @@ async fn main() -> io::Result<()> {
 
     Ok(())
 }
+
+// queues a message for each of the addresses, behind everything queued before it
+fn write_to(
+    writes: &mpsc::UnboundedSender<(String, Message)>,
+    addrs: Vec<String>,
+    message: Message,
+) {
+    for addr in addrs {
+        if let Err(e) = writes.send((addr, message.clone())) {
+            eprintln!("Error with queueing the write to {}", (e.0).0);
+        }
+    }
+}
+
+// forgets the selects whose results go into the view, returns their ids
+fn forget_selects(
+    pending_selects: &Mutex<HashMap<CorrelationId, String>>,
+    view: &str,
+) -> Vec<CorrelationId> {
+    let mut pending_selects = pending_selects
+        .lock()
+        .expect("Pending selects lock poisoned");
+
+    let ids: Vec<CorrelationId> = pending_selects
+        .iter()
+        .filter(|(_, into)| *into == view)
+        .map(|(id, _)| *id)
+        .collect();
+
+    for id in ids.iter() {
+        pending_selects.remove(id);
+    }
+
+    ids
+}
@@ -1,6 +1,6 @@
 // this is going to be a Read-Eval-Print-Loop for turnip, which will work by putting in
-use models::insert_query::InsertQuery;
-use models::select_query::SelectQuery;
+use turnip_rs::models::insert_query::InsertQuery;
+use turnip_rs::models::select_query::SelectQuery;
 
 use sqlparser::parser::Parser;
 use sqlparser::{
@@ -9,12 +9,6 @@ use sqlparser::{
 };
 use std::io::{self, BufRead};
 
-mod db;
-mod messaging;
-mod models;
-mod runtime;
-mod server;
-
 #[tokio::main]
 async fn main() -> io::Result<()> {
     let stdin = io::stdin();
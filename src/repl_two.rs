@@ -1,32 +1,44 @@
 // this is going to be a Read-Eval-Print-Loop for turnip, which will work by putting in
-use models::insert_query::InsertQuery;
-use models::select_query::SelectQuery;
-use runtime::TurnipRuntime;
+use turnip_rs::db::data::Db;
+use turnip_rs::db::wal::FsyncPolicy;
+use turnip_rs::models::create_index_query::CreateIndexQuery;
+use turnip_rs::models::create_table_query::CreateTableQuery;
+use turnip_rs::models::delete_query::DeleteQuery;
+use turnip_rs::models::drop_view_query::DropViewQuery;
+use turnip_rs::models::insert_query::InsertQuery;
+use turnip_rs::models::select_query::SelectQuery;
+use turnip_rs::models::update_query::UpdateQuery;
+use turnip_rs::runtime::{load_node_id, TurnipRuntime, TurnipRuntimeConfig};
 
-use postcard::to_vec;
 use sqlparser::parser::Parser;
 use sqlparser::{
-    ast::Statement::{Insert, Query},
+    ast::Statement::{CreateIndex, CreateTable, Delete, Drop, Insert, Query, Update},
     dialect::GenericDialect,
 };
+use std::collections::HashMap;
 use std::io::{self, BufRead};
+use std::sync::{Arc, Mutex};
 
-use messaging::Message;
-
-mod db;
-mod messaging;
-mod models;
-mod runtime;
-mod server;
+use turnip_rs::messaging::{next_correlation_id, CorrelationId, Message, MessageKind};
 
 #[tokio::main]
 async fn main() -> io::Result<()> {
     let stdin = io::stdin();
 
-    // the runtime
-    let mut runtime = TurnipRuntime::new("8082");
+    // holds the views of the selects made on this node
+    let db = Arc::new(Mutex::new(
+        Db::open("turnip_8082.wal", FsyncPolicy::Always).expect("Could not open the db"),
+    ));
+
+    // the views that the results of our selects go into, by the id of the select
+    let pending_selects: Arc<Mutex<HashMap<CorrelationId, String>>> =
+        Arc::new(Mutex::new(HashMap::new()));
 
-    runtime.add_connections(vec!["127.0.0.1:8080".to_string()]);
+    // the runtime
+    let mut runtime = TurnipRuntime::new(
+        TurnipRuntimeConfig::new(load_node_id("turnip_8082")?, 8082)
+            .peers(vec!["127.0.0.1:8080".to_string()]),
+    );
 
     runtime.run();
 
@@ -34,6 +46,88 @@ async fn main() -> io::Result<()> {
         .get_messenger()
         .expect("Could not get the messenger from the runtime");
 
+    // What the nodes that own the data we selected send us: their answers to our selects and the
+    // changes to the data after. They are applied by one task in the order they came in, so the
+    // changes a node pushes after its answer are never applied before it.
+    if let Ok(mut subscription) = runtime.get_subscription(vec![
+        MessageKind::Insert,
+        MessageKind::Update,
+        MessageKind::Delete,
+        MessageKind::StartOfResults,
+        MessageKind::QueryResult,
+        MessageKind::EndOfResults,
+        MessageKind::QueryError,
+    ]) {
+        let db = db.clone();
+        let pending_selects = pending_selects.clone();
+
+        tokio::spawn(async move {
+            while let Some(received) = subscription.recv().await {
+                println!(
+                    "Received {:?} from {}",
+                    received.message.kind(),
+                    received.addr
+                );
+
+                let source = received.addr;
+
+                let view_of = |id: CorrelationId| {
+                    let view = pending_selects
+                        .lock()
+                        .expect("Pending selects lock poisoned")
+                        .get(&id)
+                        .cloned();
+
+                    if view.is_none() {
+                        eprintln!("Received results for an unknown select: {id}");
+                    }
+
+                    view
+                };
+
+                let mut db = db.lock().expect("Db lock poisoned");
+
+                let views = match received.message {
+                    Message::Insert(insert) => db.materialize_insert(&source, &insert),
+                    Message::Update(table, changes) => {
+                        db.materialize_update(&source, &table, &changes)
+                    }
+                    Message::Delete(table, rows) => db.materialize_delete(&source, &table, &rows),
+                    // a node answering a select again, after a reconnect, replaces everything it
+                    // sent us for it before
+                    Message::StartOfResults(id) => match view_of(id) {
+                        Some(view) => db.clear_view_results(&view, &source).map(|_| vec![]),
+                        None => continue,
+                    },
+                    Message::QueryResult(id, rows) => match view_of(id) {
+                        Some(view) => db
+                            .insert_into_view(&view, &source, rows)
+                            .map(|_| vec![view]),
+                        None => continue,
+                    },
+                    Message::EndOfResults(id) => {
+                        println!("{source} has sent all results for select {id}");
+                        continue;
+                    }
+                    Message::QueryError(id, e) => {
+                        eprintln!("{source} could not answer select {id}: {e}");
+                        continue;
+                    }
+                    _ => continue,
+                };
+
+                match views {
+                    Ok(views) => {
+                        for view in views {
+                            println!("View {view}: {:?}", db.get_table(&view));
+                        }
+                    }
+                    Err(e) => eprintln!("Error with applying the change to the views: {:?}", e),
+                }
+            }
+        });
+    };
+
     // this is the command line
     while let Some(Ok(line)) = stdin.lock().lines().next() {
         let dialect = GenericDialect {};
@@ -49,18 +143,35 @@ async fn main() -> io::Result<()> {
                     let select_query = SelectQuery::try_from(&*query.body);
 
                     match select_query {
-                        Ok(select) => match to_vec::<_, 1048>(&Message::Select(select)) {
-                            Ok(result) => {
-                                println!("We are making a request");
-                                let cloned_messenger = messenger.clone();
-                                tokio::spawn(async move {
-                                    cloned_messenger.write_all(result.to_vec()).await
-                                });
+                        Ok(select) => {
+                            if let Err(e) = db
+                                .lock()
+                                .expect("Db lock poisoned")
+                                .create_view(select.clone())
+                            {
+                                eprintln!("Error with creating the view: {:?}", e);
+                                continue;
                             }
-                            Err(e) => {
-                                eprintln!("An Error ocurred trying to serialize data: {:?}", e);
+
+                            // remember which view the results of the select belong to, the
+                            // selects the view was made by before are done with
+                            let id = next_correlation_id();
+                            let replaced = forget_selects(&pending_selects, &select.into);
+
+                            pending_selects
+                                .lock()
+                                .expect("Pending selects lock poisoned")
+                                .insert(id, select.into.clone());
+
+                            println!("We are making a request");
+                            // the old selects go first, so a select is never dropped after it
+                            // was made again
+                            for id in replaced {
+                                messenger.unselect(id).await;
                             }
-                        },
+
+                            messenger.select(id, select).await;
+                        }
                         Err(e) => {
                             eprintln!("Error with getting the Statement: {:?}", e);
                         }
@@ -84,6 +195,45 @@ async fn main() -> io::Result<()> {
                     println!("Insert! {:?}", insert_query);
                     // with insert, we are only interested in sharing data with known nodes that are interested in it.
                 }
+                Update { .. } => {
+                    let update_query = UpdateQuery::try_from(statement);
+
+                    println!("Update! {:?}", update_query);
+                }
+                Delete { .. } => {
+                    let delete_query = DeleteQuery::try_from(statement);
+
+                    println!("Delete! {:?}", delete_query);
+                }
+                CreateIndex { .. } => {
+                    let create_index_query = CreateIndexQuery::try_from(statement);
+
+                    println!("Create index! {:?}", create_index_query);
+                }
+                CreateTable { .. } => {
+                    let create_table_query = CreateTableQuery::try_from(statement);
+
+                    println!("Create table! {:?}", create_table_query);
+                }
+                Drop { .. } => match DropViewQuery::try_from(statement) {
+                    Ok(query) => {
+                        let name = query.name.clone();
+
+                        if let Err(e) = db.lock().expect("Db lock poisoned").drop_view(query) {
+                            eprintln!("Error with dropping the view: {:?}", e);
+                            continue;
+                        }
+
+                        for id in forget_selects(&pending_selects, &name) {
+                            messenger.unselect(id).await;
+                        }
+
+                        println!("Dropped view {name}");
+                    }
+                    Err(e) => {
+                        eprintln!("Error with getting the Statement: {:?}", e);
+                    }
+                },
                 _ => {
                     println!("Found something else");
                 }
@@ -93,3 +243,25 @@ async fn main() -> io::Result<()> {
 
     Ok(())
 }
+
+// forgets the selects whose results go into the view, returns their ids
+fn forget_selects(
+    pending_selects: &Mutex<HashMap<CorrelationId, String>>,
+    view: &str,
+) -> Vec<CorrelationId> {
+    let mut pending_selects = pending_selects
+        .lock()
+        .expect("Pending selects lock poisoned");
+
+    let ids: Vec<CorrelationId> = pending_selects
+        .iter()
+        .filter(|(_, into)| *into == view)
+        .map(|(id, _)| *id)
+        .collect();
+
+    for id in ids.iter() {
+        pending_selects.remove(id);
+    }
+
+    ids
+}
@@ -0,0 +1,168 @@
+// How a runtime is set up. Starts from the defaults and is built up before the runtime is made
+// from it, i.e.
+//
+//     TurnipRuntimeConfig::new(load_node_id("turnip_8080")?, 8080)
+//         .peers(vec!["127.0.0.1:8081".to_string()])
+//         .gossip_interval(Duration::from_millis(500))
+use std::time::Duration;
+
+use crate::messaging::codec::DEFAULT_MAX_FRAME_SIZE;
+use crate::messaging::NodeId;
+
+use super::failure_detector::FailureDetectorConfig;
+use super::supervisor::ReconnectPolicy;
+
+// how often nodes gossip about the nodes they know of by default
+const DEFAULT_GOSSIP_INTERVAL: Duration = Duration::from_secs(1);
+
+// how many messages can be waiting to be written to a peer before it is taken to be hung
+const DEFAULT_PEER_QUEUE_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone)]
+pub struct TurnipRuntimeConfig {
+    pub(super) node_id: NodeId,
+    pub(super) bind_host: String,
+    pub(super) port: u16,
+    pub(super) advertised_addr: Option<String>,
+    pub(super) peers: Vec<String>,
+    pub(super) reconnect_policy: ReconnectPolicy,
+    pub(super) gossip_interval: Duration,
+    pub(super) max_peers: Option<usize>,
+    pub(super) failure_detector: FailureDetectorConfig,
+    pub(super) max_frame_size: usize,
+    pub(super) read_buffer_size: usize,
+    pub(super) peer_queue_capacity: usize,
+    pub(super) manager_capacity: usize,
+    pub(super) subscription_capacity: usize,
+}
+
+impl TurnipRuntimeConfig {
+    // The node id is what this node introduces itself to its peers with, the runtime knows its
+    // peers by theirs. It has to stay the same across restarts, load_node_id keeps one with the
+    // data of the node.
+    pub fn new(node_id: NodeId, port: u16) -> Self {
+        TurnipRuntimeConfig {
+            node_id,
+            bind_host: "127.0.0.1".to_string(),
+            port,
+            advertised_addr: None,
+            peers: vec![],
+            reconnect_policy: ReconnectPolicy::default(),
+            gossip_interval: DEFAULT_GOSSIP_INTERVAL,
+            max_peers: None,
+            failure_detector: FailureDetectorConfig::default(),
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            read_buffer_size: 1024,
+            peer_queue_capacity: DEFAULT_PEER_QUEUE_CAPACITY,
+            manager_capacity: 16,
+            subscription_capacity: 16,
+        }
+    }
+
+    // the host the runtime listens on for its peers, the local one by default
+    pub fn bind_host(mut self, bind_host: String) -> Self {
+        self.bind_host = bind_host;
+        self
+    }
+
+    // The address other nodes reach this node on, told to them by gossip. Defaults to the address
+    // the runtime listens on, which needs to be set when listening on every interface.
+    pub fn advertised_addr(mut self, advertised_addr: String) -> Self {
+        self.advertised_addr = Some(advertised_addr);
+        self
+    }
+
+    // the nodes to connect to, these are kept connected to and reconnected when their links drop
+    pub fn peers(mut self, peers: Vec<String>) -> Self {
+        self.peers = peers;
+        self
+    }
+
+    // how long to wait between attempts to connect to a peer
+    pub fn reconnect_policy(mut self, reconnect_policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = reconnect_policy;
+        self
+    }
+
+    // how often this node gossips about the nodes it knows of
+    pub fn gossip_interval(mut self, gossip_interval: Duration) -> Self {
+        self.gossip_interval = gossip_interval;
+        self
+    }
+
+    // The most nodes found by gossip this node connects to itself, including its peers. None, the
+    // default, connects to every node.
+    pub fn max_peers(mut self, max_peers: Option<usize>) -> Self {
+        self.max_peers = max_peers;
+        self
+    }
+
+    // how often peers are pinged and how long they can be quiet before they are suspected and
+    // then dropped as dead
+    pub fn failure_detector(mut self, failure_detector: FailureDetectorConfig) -> Self {
+        self.failure_detector = failure_detector;
+        self
+    }
+
+    // Maximum size of a single frame's payload, both for reading and writing. Peers sending
+    // frames larger than this are disconnected.
+    pub fn max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.max_frame_size = max_frame_size;
+        self
+    }
+
+    // how much is read off the socket of a peer at a time
+    pub fn read_buffer_size(mut self, read_buffer_size: usize) -> Self {
+        self.read_buffer_size = read_buffer_size;
+        self
+    }
+
+    // how many messages can be waiting to be written to a peer before it is taken to be hung
+    pub fn peer_queue_capacity(mut self, peer_queue_capacity: usize) -> Self {
+        self.peer_queue_capacity = peer_queue_capacity;
+        self
+    }
+
+    // how many messages, from the peers and the messengers, can be waiting on the runtime
+    pub fn manager_capacity(mut self, manager_capacity: usize) -> Self {
+        self.manager_capacity = manager_capacity;
+        self
+    }
+
+    // how many messages and membership events a subscriber can fall behind by before it misses
+    // some
+    pub fn subscription_capacity(mut self, subscription_capacity: usize) -> Self {
+        self.subscription_capacity = subscription_capacity;
+        self
+    }
+
+    pub fn bind_addr(&self) -> String {
+        format!("{}:{}", self.bind_host, self.port)
+    }
+
+    pub(super) fn listen_addr(&self) -> String {
+        self.advertised_addr
+            .clone()
+            .unwrap_or_else(|| self.bind_addr())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nodes_are_reached_on_the_bind_address_unless_told_otherwise() {
+        let config = TurnipRuntimeConfig::new("a".to_string(), 8080);
+
+        assert_eq!(config.bind_addr(), "127.0.0.1:8080");
+        assert_eq!(config.listen_addr(), "127.0.0.1:8080");
+
+        let config = config
+            .bind_host("0.0.0.0".to_string())
+            .advertised_addr("10.0.0.1:8080".to_string());
+
+        assert_eq!(config.bind_addr(), "0.0.0.0:8080");
+        assert_eq!(config.listen_addr(), "10.0.0.1:8080");
+    }
+}
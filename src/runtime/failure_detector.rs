@@ -0,0 +1,251 @@
+// Tells live peers from failed ones. Every link is pinged on every heartbeat and anything heard
+// over a link counts as a sign of life. A link that has been quiet for too long is suspected, and
+// once it has been quiet for longer still the peer is taken to be dead and the link is dropped.
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerState {
+    Alive,
+    // quiet for a while, it may just be slow
+    Suspect,
+    // gone, either quiet for too long or its link went down
+    Dead,
+}
+
+// A peer changing state, by its node id
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MembershipEvent {
+    pub addr: String,
+    pub state: PeerState,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailureDetectorConfig {
+    // how often every link is pinged
+    pub heartbeat_interval: Duration,
+    // how long a link can be quiet before the peer is suspected
+    pub suspect_after: Duration,
+    // how long a link can be quiet before the peer is taken to be dead
+    pub dead_after: Duration,
+}
+
+impl Default for FailureDetectorConfig {
+    fn default() -> Self {
+        FailureDetectorConfig {
+            heartbeat_interval: Duration::from_secs(1),
+            suspect_after: Duration::from_secs(3),
+            dead_after: Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FailureDetector {
+    config: FailureDetectorConfig,
+    // when we last heard from each link and the state of its peer
+    peers: HashMap<String, (Instant, PeerState)>,
+}
+
+impl FailureDetector {
+    pub fn new(config: FailureDetectorConfig) -> Self {
+        FailureDetector {
+            config,
+            peers: HashMap::new(),
+        }
+    }
+
+    pub fn connected(&mut self, addr: &str, now: Instant) -> MembershipEvent {
+        self.peers.insert(addr.to_string(), (now, PeerState::Alive));
+
+        MembershipEvent {
+            addr: addr.to_string(),
+            state: PeerState::Alive,
+        }
+    }
+
+    // we heard from the link, a suspected peer is alive after all
+    pub fn heard(&mut self, addr: &str, now: Instant) -> Option<MembershipEvent> {
+        let (last_heard, state) = self.peers.get_mut(addr)?;
+
+        *last_heard = now;
+
+        if *state == PeerState::Suspect {
+            *state = PeerState::Alive;
+
+            return Some(MembershipEvent {
+                addr: addr.to_string(),
+                state: PeerState::Alive,
+            });
+        }
+
+        None
+    }
+
+    // the link went down, the peer is dead unless it already was
+    pub fn disconnected(&mut self, addr: &str) -> Option<MembershipEvent> {
+        self.peers.remove(addr).map(|_| MembershipEvent {
+            addr: addr.to_string(),
+            state: PeerState::Dead,
+        })
+    }
+
+    // The peers that changed state because they have been quiet. Dead ones are forgotten, their
+    // links are for the runtime to drop.
+    pub fn check(&mut self, now: Instant) -> Vec<MembershipEvent> {
+        let mut events = vec![];
+
+        for (addr, (last_heard, state)) in self.peers.iter_mut() {
+            let quiet = now.saturating_duration_since(*last_heard);
+
+            let new_state = if quiet >= self.config.dead_after {
+                PeerState::Dead
+            } else if quiet >= self.config.suspect_after {
+                PeerState::Suspect
+            } else {
+                PeerState::Alive
+            };
+
+            if new_state != *state {
+                *state = new_state;
+
+                events.push(MembershipEvent {
+                    addr: addr.clone(),
+                    state: new_state,
+                });
+            }
+        }
+
+        self.peers.retain(|_, (_, state)| *state != PeerState::Dead);
+
+        events.sort_by(|a, b| a.addr.cmp(&b.addr));
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messaging::codec::{encode_frame, DEFAULT_MAX_FRAME_SIZE};
+    use crate::messaging::{Handshake, Message, PROTOCOL_VERSION};
+    use crate::runtime::{TurnipRuntime, TurnipRuntimeConfig};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn event(addr: &str, state: PeerState) -> MembershipEvent {
+        MembershipEvent {
+            addr: addr.to_string(),
+            state,
+        }
+    }
+
+    #[test]
+    fn quiet_peers_are_suspected_then_dead() {
+        let mut detector = FailureDetector::new(FailureDetectorConfig {
+            heartbeat_interval: Duration::from_secs(1),
+            suspect_after: Duration::from_secs(3),
+            dead_after: Duration::from_secs(10),
+        });
+
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+
+        assert_eq!(detector.connected("a", at(0)), event("a", PeerState::Alive));
+        detector.connected("b", at(0));
+
+        assert!(detector.check(at(2)).is_empty());
+        assert_eq!(detector.heard("b", at(2)), None);
+
+        assert_eq!(detector.check(at(4)), vec![event("a", PeerState::Suspect)]);
+        assert_eq!(
+            detector.heard("a", at(5)),
+            Some(event("a", PeerState::Alive))
+        );
+
+        assert_eq!(
+            detector.check(at(12)),
+            vec![event("a", PeerState::Suspect), event("b", PeerState::Dead)]
+        );
+
+        // dead peers are forgotten
+        assert_eq!(detector.heard("b", at(13)), None);
+        assert_eq!(detector.disconnected("b"), None);
+        assert_eq!(
+            detector.disconnected("a"),
+            Some(event("a", PeerState::Dead))
+        );
+        assert!(detector.check(at(30)).is_empty());
+    }
+
+    #[tokio::test]
+    async fn hung_peers_are_declared_dead() {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Could not bind");
+        let addr = listener.local_addr().expect("No address").to_string();
+
+        let mut runtime = TurnipRuntime::new(
+            TurnipRuntimeConfig::new("runtime".to_string(), 0)
+                .peers(vec![addr.clone()])
+                .failure_detector(FailureDetectorConfig {
+                    heartbeat_interval: Duration::from_millis(10),
+                    suspect_after: Duration::from_millis(50),
+                    dead_after: Duration::from_millis(150),
+                }),
+        );
+        runtime.run();
+
+        let mut events = runtime.get_membership_events().expect("No events");
+
+        // the peer accepts the link and reads what it is sent, but never says anything after its
+        // handshake
+        let (mut socket, _) = listener.accept().await.expect("Could not accept");
+
+        let handshake = Message::Handshake(Handshake {
+            node_id: "hung".to_string(),
+            listen_addr: addr,
+            protocol_version: PROTOCOL_VERSION,
+        });
+
+        socket
+            .write_all(
+                &encode_frame(
+                    &postcard::to_allocvec(&handshake).expect("Could not encode"),
+                    DEFAULT_MAX_FRAME_SIZE,
+                )
+                .expect("Could not frame"),
+            )
+            .await
+            .expect("Could not write");
+
+        tokio::spawn(async move {
+            let mut buf = vec![0; 1024];
+            while let Ok(n) = socket.read(&mut buf).await {
+                if n == 0 {
+                    return;
+                }
+            }
+        });
+
+        let mut states = vec![];
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while let Ok(event) = events.recv().await {
+                assert_eq!(event.addr, "hung");
+                states.push(event.state);
+
+                if event.state == PeerState::Dead {
+                    return;
+                }
+            }
+        })
+        .await
+        .expect("The peer was not declared dead");
+
+        // the link may have come up before we subscribed
+        assert!(
+            states.ends_with(&[PeerState::Suspect, PeerState::Dead]),
+            "{states:?}"
+        );
+    }
+}
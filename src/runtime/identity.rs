@@ -0,0 +1,378 @@
+// Tells the nodes on the other end of the links apart, by the id every node introduces itself
+// with. Both ends of a link start it with their handshake, until it has come in the link is
+// pending and nothing else is written to it. Two nodes dialing each other at the same time end up
+// with two links between them, the one dialed by the node with the smaller id is kept. Both nodes
+// come to the same decision on their own, so the other link is dropped at both ends.
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::messaging::{Handshake, NodeId, PROTOCOL_VERSION};
+
+// the file in the data directory of a node its id is kept in
+const NODE_ID_FILE: &str = "node_id";
+
+// a node id nothing else will have, the hasher of every RandomState is seeded differently
+fn new_node_id() -> NodeId {
+    let mut hasher = RandomState::new().build_hasher();
+
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0),
+    );
+
+    format!("{:016x}", hasher.finish())
+}
+
+// The id of the node keeping its data in the directory. One is made up the first time the node
+// starts and kept in the directory, so the node is known by the same id after a restart.
+pub fn load_node_id<P: AsRef<Path>>(dir: P) -> io::Result<NodeId> {
+    let dir = dir.as_ref();
+    let path = dir.join(NODE_ID_FILE);
+
+    match fs::read_to_string(&path) {
+        Ok(node_id) if !node_id.trim().is_empty() => return Ok(node_id.trim().to_string()),
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+
+    fs::create_dir_all(dir)?;
+
+    let node_id = new_node_id();
+
+    // written next to its final name first, so a crash never leaves half an id behind
+    let temporary = path.with_extension("tmp");
+
+    let mut file = File::create(&temporary)?;
+    file.write_all(node_id.as_bytes())?;
+    file.sync_all()?;
+
+    fs::rename(&temporary, &path)?;
+    File::open(dir)?.sync_all()?;
+
+    Ok(node_id)
+}
+
+// What to do with a link once the handshake of the node on the other end came in
+#[derive(Debug, PartialEq)]
+pub enum Resolution {
+    // the first link to the node
+    Connected(NodeId),
+    // the link takes the place of the one we had to the node, which is to be dropped
+    Replaced(NodeId),
+    // we have a better link to the node already, this one is to be dropped
+    Duplicate,
+    // we dialed ourselves
+    Ourselves,
+    Incompatible(u16),
+}
+
+#[derive(Debug)]
+pub struct Identities {
+    node_id: NodeId,
+    // when each pending link came up and whether we dialed it, by the key of the link
+    pending: HashMap<String, (Instant, bool)>,
+    // the node on the other end of every link in use, by the key of the link
+    nodes: HashMap<String, NodeId>,
+    // the key of the link in use to every node and whether we dialed it
+    links: HashMap<NodeId, (String, bool)>,
+    // the address we dialed every node on, even when the link we dialed is not the one in use
+    dialed: HashMap<NodeId, String>,
+}
+
+impl Identities {
+    pub fn new(node_id: NodeId) -> Self {
+        Identities {
+            node_id,
+            pending: HashMap::new(),
+            nodes: HashMap::new(),
+            links: HashMap::new(),
+            dialed: HashMap::new(),
+        }
+    }
+
+    // the handshake this node starts every link with
+    pub fn handshake(&self, listen_addr: String) -> Handshake {
+        Handshake {
+            node_id: self.node_id.clone(),
+            listen_addr,
+            protocol_version: PROTOCOL_VERSION,
+        }
+    }
+
+    // a link came up, dialed says whether we made it, i.e. it is keyed by the address we dialed
+    pub fn add_pending(&mut self, link: &str, dialed: bool, now: Instant) {
+        self.pending.insert(link.to_string(), (now, dialed));
+    }
+
+    // the node on the other end of a link in use
+    pub fn node(&self, link: &str) -> Option<&NodeId> {
+        self.nodes.get(link)
+    }
+
+    pub fn received(&mut self, link: &str, handshake: &Handshake) -> Resolution {
+        let dialed = self.pending.remove(link).is_some_and(|(_, dialed)| dialed);
+
+        if handshake.protocol_version != PROTOCOL_VERSION {
+            return Resolution::Incompatible(handshake.protocol_version);
+        }
+
+        if handshake.node_id == self.node_id {
+            return Resolution::Ourselves;
+        }
+
+        let node = handshake.node_id.clone();
+
+        if dialed {
+            self.dialed.insert(node.clone(), link.to_string());
+        }
+
+        let resolution = match self.links.get(&node) {
+            None => Resolution::Connected(node.clone()),
+            Some((_, in_use_dialed)) => {
+                let dialer = |dialed: bool| if dialed { &self.node_id } else { &node };
+
+                // a link dialed by the same node as the one in use is the newer of the two, the
+                // old one may well be half open
+                if dialer(dialed) <= dialer(*in_use_dialed) {
+                    Resolution::Replaced(node.clone())
+                } else {
+                    Resolution::Duplicate
+                }
+            }
+        };
+
+        if resolution != Resolution::Duplicate {
+            if let Some((old_link, _)) = self.links.insert(node.clone(), (link.to_string(), dialed))
+            {
+                self.nodes.remove(&old_link);
+            }
+
+            self.nodes.insert(link.to_string(), node);
+        }
+
+        resolution
+    }
+
+    // a pending link went down, returns whether it was pending
+    pub fn abandon(&mut self, link: &str) -> bool {
+        self.pending.remove(link).is_some()
+    }
+
+    // the pending links that have waited on their handshake for longer than the timeout
+    pub fn expired(&mut self, now: Instant, timeout: Duration) -> Vec<String> {
+        let mut expired: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, (since, _))| now.saturating_duration_since(*since) >= timeout)
+            .map(|(link, _)| link.clone())
+            .collect();
+
+        expired.sort();
+
+        for link in expired.iter() {
+            self.pending.remove(link);
+        }
+
+        expired
+    }
+
+    // the node went away, returns the address we dialed it on so it can be dialed again
+    pub fn disconnected(&mut self, node: &str) -> Option<String> {
+        if let Some((link, _)) = self.links.remove(node) {
+            self.nodes.remove(&link);
+        }
+
+        self.dialed.remove(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messaging::{Message, MessageKind};
+    use crate::models::insert_query::InsertQuery;
+    use crate::runtime::{MembershipEvent, PeerState, TurnipRuntime, TurnipRuntimeConfig};
+    use tokio::net::TcpListener;
+
+    fn handshake(node_id: &str) -> Handshake {
+        Identities::new(node_id.to_string()).handshake(format!("{node_id}:8080"))
+    }
+
+    #[test]
+    fn nodes_dialing_each_other_keep_the_same_link() {
+        let now = Instant::now();
+
+        // a dials b over one link while b dials a over another, the handshakes come in in a
+        // different order at either end
+        let mut a = Identities::new("a".to_string());
+        a.add_pending("b:8080", true, now);
+        a.add_pending("127.0.0.1:50001", false, now);
+
+        assert_eq!(
+            a.received("127.0.0.1:50001", &handshake("b")),
+            Resolution::Connected("b".to_string())
+        );
+        assert_eq!(
+            a.received("b:8080", &handshake("b")),
+            Resolution::Replaced("b".to_string())
+        );
+
+        let mut b = Identities::new("b".to_string());
+        b.add_pending("a:8080", true, now);
+        b.add_pending("127.0.0.1:50002", false, now);
+
+        assert_eq!(
+            b.received("127.0.0.1:50002", &handshake("a")),
+            Resolution::Connected("a".to_string())
+        );
+        assert_eq!(b.received("a:8080", &handshake("a")), Resolution::Duplicate);
+
+        // both kept the link a dialed
+        assert_eq!(a.node("b:8080"), Some(&"b".to_string()));
+        assert_eq!(a.node("127.0.0.1:50001"), None);
+        assert_eq!(b.node("127.0.0.1:50002"), Some(&"a".to_string()));
+        assert_eq!(b.node("a:8080"), None);
+
+        // either can dial the other again once it goes away
+        assert_eq!(a.disconnected("b"), Some("b:8080".to_string()));
+        assert_eq!(b.disconnected("a"), Some("a:8080".to_string()));
+        assert_eq!(b.node("127.0.0.1:50002"), None);
+    }
+
+    #[test]
+    fn handshakes_are_checked() {
+        let now = Instant::now();
+
+        let mut a = Identities::new("a".to_string());
+        a.add_pending("a:8080", true, now);
+        a.add_pending("c:8080", true, now);
+        a.add_pending("d:8080", true, now);
+
+        assert_eq!(a.received("a:8080", &handshake("a")), Resolution::Ourselves);
+        assert_eq!(
+            a.received(
+                "c:8080",
+                &Handshake {
+                    protocol_version: PROTOCOL_VERSION + 1,
+                    ..handshake("c")
+                }
+            ),
+            Resolution::Incompatible(PROTOCOL_VERSION + 1)
+        );
+
+        // d never says who it is
+        assert!(a.expired(now, Duration::from_secs(1)).is_empty());
+        assert_eq!(
+            a.expired(now + Duration::from_secs(1), Duration::from_secs(1)),
+            vec!["d:8080".to_string()]
+        );
+        assert!(!a.abandon("d:8080"));
+    }
+
+    #[test]
+    fn node_ids_are_kept_across_restarts() {
+        let dir = std::env::temp_dir().join(format!("turnip-node-id-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let node_id = load_node_id(&dir).expect("Could not load the node id");
+
+        assert!(!node_id.is_empty());
+        assert_eq!(
+            load_node_id(&dir).expect("Could not load the node id"),
+            node_id
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join(NODE_ID_FILE)).expect("Could not read"),
+            node_id
+        );
+
+        fs::remove_dir_all(&dir).expect("Could not remove the directory");
+    }
+
+    #[tokio::test]
+    async fn nodes_dialing_each_other_are_linked_once() {
+        let mut ports = vec![];
+
+        for _ in 0..2 {
+            let listener = TcpListener::bind("127.0.0.1:0")
+                .await
+                .expect("Could not bind");
+            ports.push(listener.local_addr().expect("No address").port());
+        }
+
+        let mut a = TurnipRuntime::new(
+            TurnipRuntimeConfig::new("a".to_string(), ports[0])
+                .peers(vec![format!("127.0.0.1:{}", ports[1])]),
+        );
+        a.run();
+
+        let mut b = TurnipRuntime::new(
+            TurnipRuntimeConfig::new("b".to_string(), ports[1])
+                .peers(vec![format!("127.0.0.1:{}", ports[0])]),
+        );
+        b.run();
+
+        let mut events = b.get_membership_events().expect("No events");
+        let mut inserts = b
+            .get_subscription(vec![MessageKind::Insert])
+            .expect("No subscription");
+        let messenger = a.get_messenger().expect("No messenger");
+
+        let insert = Message::Insert(InsertQuery {
+            table_name: "orders".to_string(),
+            columns: vec![],
+            rows: vec![],
+        });
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                messenger.write_all(&insert).await;
+
+                if let Ok(Some(received)) =
+                    tokio::time::timeout(Duration::from_millis(50), inserts.recv()).await
+                {
+                    return received;
+                }
+            }
+        })
+        .await
+        .expect("The nodes did not link up");
+
+        // let the links settle, a message written to both links would arrive twice
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        while let Ok(Some(_)) =
+            tokio::time::timeout(Duration::from_millis(10), inserts.recv()).await
+        {}
+
+        messenger.write_all(&insert).await;
+
+        let received = inserts.recv().await.expect("No insert");
+        assert_eq!(received.addr, "a");
+
+        assert!(
+            tokio::time::timeout(Duration::from_millis(200), inserts.recv())
+                .await
+                .is_err(),
+            "The insert came in twice"
+        );
+
+        // b only ever saw a come up
+        while let Ok(event) = events.try_recv() {
+            assert_eq!(
+                event,
+                MembershipEvent {
+                    addr: "a".to_string(),
+                    state: PeerState::Alive
+                }
+            );
+        }
+    }
+}
@@ -0,0 +1,293 @@
+// The connection manager, the one task that owns the links to the peers. Everything the runtime
+// does goes through it as a TcpStreamMessage: links coming up and going down, messages read off
+// them, messages to write to them and the ticks of the periodic work.
+use std::collections::HashMap;
+use std::time::Instant;
+
+use tokio::net::TcpStream;
+use tokio::sync::{broadcast, mpsc};
+
+use crate::messaging::{Handshake, Message, ReceivedMessage, PROTOCOL_VERSION};
+use crate::models::tcp_stream_message::TcpStreamMessage;
+use crate::models::tcp_stream_message::TcpStreamMessage::{
+    Connect, Disconnect, Heartbeat, Read, Subscribe, Tick, Unsubscribe, Write, WriteAll,
+};
+
+use super::config::TurnipRuntimeConfig;
+use super::failure_detector::{FailureDetector, MembershipEvent, PeerState};
+use super::identity::{Identities, Resolution};
+use super::membership::Membership;
+use super::supervisor::Supervisor;
+use super::{handle_connection, write, write_to_all, StreamMap};
+
+pub struct ConnectionManager {
+    config: TurnipRuntimeConfig,
+    tx: mpsc::Sender<TcpStreamMessage>,
+    received: broadcast::Sender<ReceivedMessage>,
+    events: broadcast::Sender<MembershipEvent>,
+    // the links to the nodes, by their node id
+    stream_map: StreamMap,
+    // the links still waiting on the handshake of the node on the other end
+    pending: StreamMap,
+    supervisor: Supervisor,
+    membership: Membership,
+    detector: FailureDetector,
+    identities: Identities,
+}
+
+impl ConnectionManager {
+    pub fn new(
+        config: TurnipRuntimeConfig,
+        tx: mpsc::Sender<TcpStreamMessage>,
+        received: broadcast::Sender<ReceivedMessage>,
+        events: broadcast::Sender<MembershipEvent>,
+    ) -> Self {
+        ConnectionManager {
+            supervisor: Supervisor::new(config.peers.clone(), config.reconnect_policy.clone()),
+            membership: Membership::new(config.listen_addr(), config.max_peers),
+            detector: FailureDetector::new(config.failure_detector.clone()),
+            identities: Identities::new(config.node_id.clone()),
+            config,
+            tx,
+            received,
+            events,
+            stream_map: HashMap::new(),
+            pending: HashMap::new(),
+        }
+    }
+
+    pub async fn run(mut self, mut rx: mpsc::Receiver<TcpStreamMessage>) {
+        self.supervisor.start(&self.tx);
+
+        while let Some(msg) = rx.recv().await {
+            let gone = match msg {
+                Connect(link, socket) => self.connect(link, socket),
+                Disconnect(link) => self.disconnect(link),
+                Tick => self.membership.gossip_to(&self.stream_map, None),
+                Heartbeat => self.heartbeat(),
+                Subscribe(id, msg) => {
+                    // a select of ours, kept to be sent to every peer connecting later on
+                    self.supervisor.add_select(id, msg.clone());
+
+                    write_to_all(&self.stream_map, msg)
+                }
+                Unsubscribe(id) => {
+                    self.supervisor.remove_select(id);
+
+                    vec![]
+                }
+                Write(addr, msg) => {
+                    // implementation for writing to another socket
+                    // we would only write to another socket if:
+                    // 1) we want to send them metadata based on a received request or
+                    // 2) they have specified interest in a collection that we are interested in
+                    // 3) we own data that another process is interested in
+                    if write(&self.stream_map, &addr, msg) {
+                        vec![]
+                    } else {
+                        vec![addr]
+                    }
+                }
+                WriteAll(msg) => {
+                    // we want to write all when we make a query(such as 'SELECT first_name, last_name from customer where id = 1;')
+                    // this will broadcast to everyone that we are interested in some subset of data.
+                    write_to_all(&self.stream_map, msg)
+                }
+                Read(link, message) => match *message {
+                    Message::Handshake(handshake) => self.handshake(link, handshake),
+                    message => self.read(link, message),
+                },
+            };
+
+            // nodes whose link went down, or that are dead or not keeping up with us
+            for addr in gone {
+                self.drop_link(&addr);
+            }
+        }
+    }
+
+    fn connect(&mut self, link: String, socket: TcpStream) -> Vec<String> {
+        // links are only used once the node on the other end has told us who it is, until then
+        // nothing but our own handshake is written to them
+        handle_connection(
+            &mut self.pending,
+            socket,
+            link.clone(),
+            self.tx.clone(),
+            &self.config,
+        );
+
+        self.identities
+            .add_pending(&link, self.supervisor.is_peer(&link), Instant::now());
+
+        let handshake = Message::Handshake(
+            self.identities
+                .handshake(self.membership.advertised_addr().to_string()),
+        );
+
+        match postcard::to_allocvec(&handshake) {
+            Ok(handshake) => {
+                write(&self.pending, &link, handshake);
+            }
+            Err(e) => eprintln!("Error with encoding the handshake: {:?}", e),
+        };
+
+        vec![]
+    }
+
+    fn disconnect(&mut self, link: String) -> Vec<String> {
+        if self.pending.remove(&link).is_some() {
+            self.identities.abandon(&link);
+            self.supervisor.disconnected(&link, &self.tx);
+        }
+
+        // links that lost out to another link to the same node are not in use
+        self.identities.node(&link).cloned().into_iter().collect()
+    }
+
+    fn heartbeat(&mut self) -> Vec<String> {
+        let now = Instant::now();
+
+        // nodes that never say who they are are given up on like quiet ones
+        for link in self
+            .identities
+            .expired(now, self.config.failure_detector.dead_after)
+        {
+            if let Some((_, handle)) = self.pending.remove(&link) {
+                handle.abort();
+            }
+
+            self.supervisor.disconnected(&link, &self.tx);
+        }
+
+        let mut gone = match postcard::to_allocvec(&Message::Ping) {
+            Ok(ping) => write_to_all(&self.stream_map, ping),
+            Err(e) => {
+                eprintln!("Error with encoding the ping: {:?}", e);
+                vec![]
+            }
+        };
+
+        for event in self.detector.check(now) {
+            if event.state == PeerState::Dead {
+                gone.push(event.addr.clone());
+            }
+
+            self.publish(event);
+        }
+
+        gone
+    }
+
+    fn handshake(&mut self, link: String, handshake: Handshake) -> Vec<String> {
+        // a node only introduces itself once per link
+        let Some(connection) = self.pending.remove(&link) else {
+            return vec![];
+        };
+
+        match self.identities.received(&link, &handshake) {
+            Resolution::Connected(node) => {
+                self.stream_map.insert(node.clone(), connection);
+
+                let event = self.detector.connected(&node, Instant::now());
+                self.publish(event);
+
+                self.membership.connected(&node, &handshake.listen_addr);
+
+                let mut gone = self.supervisor.connected(&self.stream_map, &node);
+                gone.extend(self.membership.gossip_to(&self.stream_map, Some(&node)));
+                gone
+            }
+            Resolution::Replaced(node) => {
+                if let Some((_, handle)) = self.stream_map.insert(node.clone(), connection) {
+                    handle.abort();
+                }
+
+                // whatever was queued on the old link went down with it, the node is caught up
+                // on the new one like it just connected
+                let mut gone = self.supervisor.connected(&self.stream_map, &node);
+                gone.extend(self.membership.gossip_to(&self.stream_map, Some(&node)));
+                gone
+            }
+            Resolution::Duplicate | Resolution::Ourselves => {
+                connection.1.abort();
+
+                vec![]
+            }
+            Resolution::Incompatible(version) => {
+                eprintln!(
+                    "{link} speaks version {version} of the protocol rather than {PROTOCOL_VERSION}"
+                );
+
+                connection.1.abort();
+                self.supervisor.disconnected(&link, &self.tx);
+
+                vec![]
+            }
+        }
+    }
+
+    fn read(&mut self, link: String, message: Message) -> Vec<String> {
+        let Some(addr) = self.identities.node(&link).cloned() else {
+            eprintln!("Dropping a message from {link}, the link is not in use");
+            return vec![];
+        };
+
+        // implementation for reading from a specific socket
+        // When we read from other sockets, that means that either they:
+        // gossiping about the other nodes in the landscape, which we handle here, or
+        // are making a query(either telling us about an insert or a giving us a select)
+        // anything heard over the link means the peer is alive
+        if let Some(event) = self.detector.heard(&addr, Instant::now()) {
+            self.publish(event);
+        }
+
+        match message {
+            Message::Ping => {}
+            Message::Gossip(sender, peers) => {
+                for peer in
+                    self.membership
+                        .received(&addr, sender, peers, self.supervisor.peer_count())
+                {
+                    self.supervisor.add_peer(peer, &self.tx);
+                }
+            }
+            message => {
+                if let Err(e) = self.received.send(ReceivedMessage { addr, message }) {
+                    eprintln!("Errror with broadcasting the read: {:?}", e);
+                }
+            }
+        }
+
+        vec![]
+    }
+
+    // Drops the link to the node, if it is still there. The task of the link may be stuck writing
+    // to a hung peer, so it is stopped rather than left to notice the link is gone.
+    fn drop_link(&mut self, addr: &str) {
+        let Some((_, handle)) = self.stream_map.remove(addr) else {
+            return;
+        };
+
+        handle.abort();
+
+        // the node is dead, only configured peers are dialed again
+        if let Some(listen_addr) = self.membership.disconnected(addr) {
+            self.supervisor.remove_peer(&listen_addr);
+        }
+
+        // nodes we dialed ourselves are dialed again
+        if let Some(dialed) = self.identities.disconnected(addr) {
+            self.supervisor.disconnected(&dialed, &self.tx);
+        }
+
+        if let Some(event) = self.detector.disconnected(addr) {
+            self.publish(event);
+        }
+    }
+
+    fn publish(&self, event: MembershipEvent) {
+        // nobody listening is fine
+        let _ = self.events.send(event);
+    }
+}
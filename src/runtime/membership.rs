@@ -0,0 +1,271 @@
+// Peer discovery by gossip. Every node periodically tells the nodes it has links to the address it
+// listens on and the addresses of every node it knows of. A node joining only needs the address of
+// one seed, it learns about the rest of the cluster from the seed's gossip and connects to them.
+use std::collections::{BTreeSet, HashMap};
+
+use crate::messaging::{Message, NodeId};
+
+use super::{write, write_to_all, StreamMap};
+
+#[derive(Debug)]
+pub struct Membership {
+    // the address the other nodes reach this node on
+    advertised_addr: String,
+    // the listen addresses of every other node we have heard of
+    known: BTreeSet<String>,
+    // the listen address of every node we have a link to, by node id
+    links: HashMap<NodeId, String>,
+    // the most nodes this node connects to itself, None to connect to every node (a full mesh)
+    max_peers: Option<usize>,
+}
+
+impl Membership {
+    pub fn new(advertised_addr: String, max_peers: Option<usize>) -> Self {
+        Membership {
+            advertised_addr,
+            known: BTreeSet::new(),
+            links: HashMap::new(),
+            max_peers,
+        }
+    }
+
+    // a node came up, with the listen address it told us about in its handshake
+    pub fn connected(&mut self, node: &str, listen_addr: &str) {
+        self.links.insert(node.to_string(), listen_addr.to_string());
+        self.known.insert(listen_addr.to_string());
+    }
+
+    // A node is gone. Its address isn't gossiped anymore, nodes that still reach it will tell us
+    // about it again. Returns the address it listened on.
+    pub fn disconnected(&mut self, node: &str) -> Option<String> {
+        let listen_addr = self.links.remove(node)?;
+
+        self.known.remove(&listen_addr);
+
+        Some(listen_addr)
+    }
+
+    pub fn advertised_addr(&self) -> &str {
+        &self.advertised_addr
+    }
+
+    // the gossip this node sends: its own address and every node it knows of
+    pub fn gossip(&self) -> Message {
+        Message::Gossip(
+            self.advertised_addr.clone(),
+            self.known.iter().cloned().collect(),
+        )
+    }
+
+    // The gossip of a node. Returns the nodes we just learned about that we should connect to,
+    // dialing is the number of nodes we already connect to ourselves.
+    pub fn received(
+        &mut self,
+        node: &str,
+        sender: String,
+        peers: Vec<String>,
+        dialing: usize,
+    ) -> Vec<String> {
+        self.links.insert(node.to_string(), sender.clone());
+
+        let mut new_peers = vec![];
+
+        for peer in std::iter::once(sender).chain(peers) {
+            if peer != self.advertised_addr && self.known.insert(peer.clone()) {
+                new_peers.push(peer);
+            }
+        }
+
+        // nodes with a link to us already don't need one from us as well
+        new_peers.retain(|peer| !self.links.values().any(|linked| linked == peer));
+
+        if let Some(max_peers) = self.max_peers {
+            new_peers.truncate(max_peers.saturating_sub(dialing));
+        }
+
+        new_peers
+    }
+
+    // Sends our gossip to the node, or every node for None. Returns the nodes that could not take
+    // it.
+    pub fn gossip_to(&self, stream_map: &StreamMap, node: Option<&str>) -> Vec<String> {
+        let gossip = match postcard::to_allocvec(&self.gossip()) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                eprintln!("Error with encoding the gossip: {:?}", e);
+                return vec![];
+            }
+        };
+
+        match node {
+            Some(node) => {
+                if write(stream_map, node, gossip) {
+                    vec![]
+                } else {
+                    vec![node.to_string()]
+                }
+            }
+            None => write_to_all(stream_map, gossip),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messaging::MessageKind;
+    use crate::models::insert_query::InsertQuery;
+    use crate::runtime::{TurnipRuntime, TurnipRuntimeConfig};
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn gossip_spreads_the_nodes_known() {
+        let mut membership = Membership::new("127.0.0.1:1".to_string(), None);
+
+        membership.connected("b", "127.0.0.1:2");
+
+        // a node connected to us tells us about itself, us and another node
+        assert_eq!(
+            membership.received(
+                "c",
+                "127.0.0.1:3".to_string(),
+                vec!["127.0.0.1:1".to_string(), "127.0.0.1:4".to_string()],
+                1,
+            ),
+            vec!["127.0.0.1:4".to_string()]
+        );
+
+        assert_eq!(
+            membership.gossip(),
+            Message::Gossip(
+                "127.0.0.1:1".to_string(),
+                vec![
+                    "127.0.0.1:2".to_string(),
+                    "127.0.0.1:3".to_string(),
+                    "127.0.0.1:4".to_string()
+                ]
+            )
+        );
+
+        // nothing new, nothing to connect to
+        assert!(membership
+            .received(
+                "c",
+                "127.0.0.1:3".to_string(),
+                vec!["127.0.0.1:4".to_string()],
+                2
+            )
+            .is_empty());
+
+        // the fan-out caps the nodes we connect to
+        let mut membership = Membership::new("127.0.0.1:1".to_string(), Some(2));
+
+        assert_eq!(
+            membership.received(
+                "b",
+                "127.0.0.1:2".to_string(),
+                vec!["127.0.0.1:3".to_string(), "127.0.0.1:4".to_string()],
+                1,
+            ),
+            vec!["127.0.0.1:3".to_string()]
+        );
+    }
+
+    #[test]
+    fn dead_nodes_are_not_gossiped() {
+        let mut membership = Membership::new("127.0.0.1:1".to_string(), None);
+
+        membership.connected("b", "127.0.0.1:2");
+        membership.received(
+            "c",
+            "127.0.0.1:3".to_string(),
+            vec!["127.0.0.1:2".to_string()],
+            0,
+        );
+
+        assert_eq!(
+            membership.disconnected("b"),
+            Some("127.0.0.1:2".to_string())
+        );
+        assert_eq!(membership.disconnected("b"), None);
+
+        assert_eq!(
+            membership.gossip(),
+            Message::Gossip("127.0.0.1:1".to_string(), vec!["127.0.0.1:3".to_string()])
+        );
+
+        // a node that still reaches it tells us about it again
+        assert_eq!(
+            membership.received(
+                "c",
+                "127.0.0.1:3".to_string(),
+                vec!["127.0.0.1:2".to_string()],
+                0,
+            ),
+            vec!["127.0.0.1:2".to_string()]
+        );
+    }
+
+    // a port nothing listens on yet
+    async fn free_port() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Could not bind");
+
+        listener.local_addr().expect("No address").port()
+    }
+
+    #[tokio::test]
+    async fn nodes_joining_through_a_seed_find_each_other() {
+        let ports = [free_port().await, free_port().await, free_port().await];
+
+        let runtimes: Vec<TurnipRuntime> = ports
+            .iter()
+            .enumerate()
+            .map(|(i, port)| {
+                let mut config = TurnipRuntimeConfig::new(format!("node-{i}"), *port)
+                    .gossip_interval(Duration::from_millis(20));
+
+                // the first node is the seed of the others
+                if i > 0 {
+                    config = config.peers(vec![format!("127.0.0.1:{}", ports[0])]);
+                }
+
+                let mut runtime = TurnipRuntime::new(config);
+                runtime.run();
+                runtime
+            })
+            .collect();
+
+        let [_, second, third] = &mut runtimes.try_into().unwrap_or_else(|_| unreachable!());
+
+        let mut inserts = second
+            .get_subscription(vec![MessageKind::Insert])
+            .expect("No subscription");
+        let messenger = third.get_messenger().expect("No messenger");
+
+        let insert = Message::Insert(InsertQuery {
+            table_name: "orders".to_string(),
+            columns: vec![],
+            rows: vec![],
+        });
+
+        // the third node only reaches the second once it has learned about it from the seed
+        let received = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                messenger.write_all(&insert).await;
+
+                if let Ok(Some(received)) =
+                    tokio::time::timeout(Duration::from_millis(50), inserts.recv()).await
+                {
+                    return received;
+                }
+            }
+        })
+        .await
+        .expect("The nodes did not find each other");
+
+        assert_eq!(received.message, insert);
+    }
+}
@@ -1,4 +1,8 @@
+use crate::db::data::TypeValue;
+use crate::messaging::{select_reply, CorrelationId, Message};
+use crate::models::select_query::SelectQuery;
 use crate::models::tcp_stream_message::TcpStreamMessage;
+use std::collections::HashMap;
 use tokio::sync::mpsc;
 
 // use crate::TcpStreamMessage::{Connect, Disconnect, Read, Write};
@@ -13,7 +17,15 @@ impl TurnipMessenger {
         TurnipMessenger { tx }
     }
 
-    pub async fn write(&self, addr: String, message: Vec<u8>) {
+    pub async fn write(&self, addr: String, message: &Message) {
+        let message = match postcard::to_allocvec(message) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                eprintln!("An Error ocurred trying to serialize data: {:?}", e);
+                return;
+            }
+        };
+
         match self.tx.send(TcpStreamMessage::Write(addr, message)).await {
             Ok(_r) => {}
             Err(e) => {
@@ -22,7 +34,15 @@ impl TurnipMessenger {
         };
     }
 
-    pub async fn write_all(&self, message: Vec<u8>) {
+    pub async fn write_all(&self, message: &Message) {
+        let message = match postcard::to_allocvec(message) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                eprintln!("An Error ocurred trying to serialize data: {:?}", e);
+                return;
+            }
+        };
+
         match self.tx.send(TcpStreamMessage::WriteAll(message)).await {
             Ok(_r) => {}
             Err(e) => {
@@ -30,7 +50,96 @@ impl TurnipMessenger {
             }
         };
     }
+
+    // Makes a select on every peer. The runtime keeps it, and sends it again to peers that connect
+    // (or reconnect) later on, so their changes keep coming.
+    pub async fn select(&self, id: CorrelationId, select: SelectQuery) {
+        let message = match postcard::to_allocvec(&Message::Select(id, select)) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                eprintln!("An Error ocurred trying to serialize data: {:?}", e);
+                return;
+            }
+        };
+
+        match self.tx.send(TcpStreamMessage::Subscribe(id, message)).await {
+            Ok(_r) => {}
+            Err(e) => {
+                eprintln!("Error with Writing: {:?}", e);
+            }
+        };
+    }
+
+    // Drops a select made with select, it isn't sent to the peers connecting later on anymore. The
+    // peers that have it keep pushing their changes, which no view takes anymore.
+    pub async fn unselect(&self, id: CorrelationId) {
+        if let Err(e) = self.tx.send(TcpStreamMessage::Unsubscribe(id)).await {
+            eprintln!("Error with Writing: {:?}", e);
+        }
+    }
+
+    // answers a select made by the node at addr, in batches of rows followed by the end of the results
+    pub async fn reply_to_select(
+        &self,
+        addr: String,
+        id: CorrelationId,
+        rows: Vec<HashMap<String, TypeValue>>,
+    ) {
+        for message in select_reply(id, rows) {
+            self.write(addr.clone(), &message).await;
+        }
+    }
+
+    pub async fn reply_error(&self, addr: String, id: CorrelationId, error: String) {
+        self.write(addr, &Message::QueryError(id, error)).await;
+    }
 }
 
 unsafe impl Send for TurnipMessenger {}
 unsafe impl Sync for TurnipMessenger {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::number_value::NumberValueType;
+    use crate::messaging::RESULT_BATCH_SIZE;
+
+    #[tokio::test]
+    async fn reply_to_select_sends_batches_and_end() {
+        let (tx, mut rx) = mpsc::channel::<TcpStreamMessage>(16);
+
+        let messenger = TurnipMessenger::new(tx);
+
+        let rows: Vec<HashMap<String, TypeValue>> = (0..RESULT_BATCH_SIZE + 1)
+            .map(|i| {
+                HashMap::from([(
+                    "id".to_string(),
+                    TypeValue::NumberValueType(NumberValueType { value: i as f64 }),
+                )])
+            })
+            .collect();
+
+        messenger
+            .reply_to_select("127.0.0.1:8082".to_string(), 7, rows.clone())
+            .await;
+
+        drop(messenger);
+
+        let mut messages = vec![];
+
+        while let Some(TcpStreamMessage::Write(addr, bytes)) = rx.recv().await {
+            assert_eq!(addr, "127.0.0.1:8082");
+            messages.push(postcard::from_bytes::<Message>(&bytes).expect("Could not decode"));
+        }
+
+        assert_eq!(
+            messages,
+            vec![
+                Message::StartOfResults(7),
+                Message::QueryResult(7, rows[..RESULT_BATCH_SIZE].to_vec()),
+                Message::QueryResult(7, rows[RESULT_BATCH_SIZE..].to_vec()),
+                Message::EndOfResults(7),
+            ]
+        );
+    }
+}
@@ -6,129 +6,116 @@ use tokio::sync::{broadcast, mpsc};
 use tokio::task::JoinHandle;
 
 use std::collections::HashMap;
+use std::time::Duration;
 
+use crate::messaging::codec::{encode_frame, FrameDecoder};
+use crate::messaging::subscription::MessageSubscription;
+use crate::messaging::{Message, MessageKind, ReceivedMessage};
 use crate::models::tcp_stream_message::TcpStreamMessage;
-use crate::models::tcp_stream_message::TcpStreamMessage::{
-    Connect, Disconnect, Read, Write, WriteAll,
-};
+use crate::models::tcp_stream_message::TcpStreamMessage::{Heartbeat, Tick};
 use crate::server::create_server;
+pub use config::TurnipRuntimeConfig;
 use error::TurnipRuntimeError;
-use messenger::TurnipMessenger;
+pub use failure_detector::{FailureDetectorConfig, MembershipEvent, PeerState};
+pub use identity::load_node_id;
+use manager::ConnectionManager;
+pub use messenger::TurnipMessenger;
+pub use supervisor::ReconnectPolicy;
 
+mod config;
 mod error;
+mod failure_detector;
+mod identity;
+mod manager;
+mod membership;
 mod messenger;
+mod supervisor;
+
+// the channel to the task writing to each connected peer, by the key the runtime knows the peer by
+pub type StreamMap = HashMap<String, (mpsc::Sender<Vec<u8>>, JoinHandle<()>)>;
 
 pub struct TurnipRuntime {
-    port: String,
+    config: TurnipRuntimeConfig,
     tx: Option<mpsc::Sender<TcpStreamMessage>>,
-    broadcast_tx: Option<broadcast::Sender<Vec<u8>>>,
-    init_connections: Vec<String>,
+    broadcast_tx: Option<broadcast::Sender<ReceivedMessage>>,
+    membership_tx: Option<broadcast::Sender<MembershipEvent>>,
+}
+
+// The tasks of a running runtime, aborting both stops it. The server stops by itself once it can't
+// accept connections anymore, i.e. when the port is taken.
+pub struct TurnipRuntimeHandles {
+    pub manager: JoinHandle<()>,
+    pub server: JoinHandle<()>,
 }
 
 impl TurnipRuntime {
-    pub fn new(port: &str) -> Self {
+    pub fn new(config: TurnipRuntimeConfig) -> Self {
         TurnipRuntime {
-            port: port.to_string(),
+            config,
             tx: None::<mpsc::Sender<TcpStreamMessage>>,
-            init_connections: vec![],
-            broadcast_tx: None::<broadcast::Sender<Vec<u8>>>,
+            broadcast_tx: None::<broadcast::Sender<ReceivedMessage>>,
+            membership_tx: None,
         }
     }
 
-    // Method to implement connections as a client on this node,
-    // this needs to be added before "run"
-    pub fn add_connections(&mut self, addr: Vec<String>) -> &Self {
-        self.init_connections = addr;
-        self
+    pub fn node_id(&self) -> &str {
+        &self.config.node_id
     }
 
     pub fn is_initialized(&self) -> bool {
         self.tx.is_some()
     }
 
-    pub fn run(&mut self) {
-        // TODO: think about capacity below
-        let (broadcast_tx, _) = broadcast::channel::<Vec<u8>>(16);
+    // starts listening for and connecting to peers, the runtime can be used once this has been called
+    pub fn run(&mut self) -> TurnipRuntimeHandles {
+        let config = self.config.clone();
 
-        let broadcast_tx_clone = broadcast_tx.clone();
+        let (broadcast_tx, _) = broadcast::channel::<ReceivedMessage>(config.subscription_capacity);
 
-        self.broadcast_tx = Some(broadcast_tx);
+        let (membership_tx, _) =
+            broadcast::channel::<MembershipEvent>(config.subscription_capacity);
 
         // tcp stream channel
-        let (tx, mut rx1) = mpsc::channel::<TcpStreamMessage>(16);
+        let (tx, rx) = mpsc::channel::<TcpStreamMessage>(config.manager_capacity);
 
-        let thread_tx = tx.clone();
+        spawn_ticks(tx.clone(), config.gossip_interval, || Tick);
+        spawn_ticks(
+            tx.clone(),
+            config.failure_detector.heartbeat_interval,
+            || Heartbeat,
+        );
 
-        let connections = self.init_connections.clone();
-
-        // data structure for handling the tcpstreams
-        tokio::spawn(async move {
-            let mut stream_map: HashMap<String, (mpsc::Sender<Vec<u8>>, JoinHandle<()>)> =
-                HashMap::new();
-
-            // TODO: over here, we connect to all of the given ip's given
-            for addr in connections.iter() {
-                if let Ok(socket) = TcpStream::connect(addr).await {
-                    handle_connection(&mut stream_map, socket, addr.clone(), thread_tx.clone());
-                }
-            }
+        let manager = ConnectionManager::new(
+            config.clone(),
+            tx.clone(),
+            broadcast_tx.clone(),
+            membership_tx.clone(),
+        );
 
-            while let Some(msg) = rx1.recv().await {
-                match msg {
-                    Connect(addr, socket) => {
-                        handle_connection(&mut stream_map, socket, addr, thread_tx.clone());
-                    }
-                    Disconnect(addr) => {
-                        println!("You've just disconnected with Address: {addr}");
+        let manager = tokio::spawn(manager.run(rx));
 
-                        // this should run drop on the socket and handle supposedly
-                        stream_map.remove(&addr);
-                    }
-                    Write(addr, msg) => {
-                        // implementation for writing to another socket
-                        // we would only write to another socket if:
-                        // 1) we want to send them metadata based on a received request or
-                        // 2) they have specified interest in a collection that we are interested in
-                        // 3) we own data that another process is interested in
-                        write(&mut stream_map, addr, msg).await;
-                    }
-                    WriteAll(msg) => {
-                        // we want to write all when we make a query(such as 'SELECT first_name, last_name from customer where id = 1;')
-                        // this will broadcast to everyone that we are interested in some subset of data.
+        let server_tx = tx.clone();
 
-                        println!("Writing to all: {:?}", msg);
-                        write_to_all(&mut stream_map, msg).await;
-                    }
-                    Read(_addr, msg) => {
-                        // implementation for reading from a specific socket
-                        // When we read from other sockets, that means that either they:
-                        // sending a metadata request(like other ip addresses in the landscape) or
-                        // are making a query(either telling us about an insert or a giving us a select)
-                        match broadcast_tx_clone.send(msg) {
-                            Ok(_) => {}
-                            Err(e) => {
-                                eprintln!("Errror with broadcasting the read: {:?}", e)
-                            }
-                        };
-                    }
-                }
+        let server = tokio::spawn(async move {
+            if let Err(e) = create_server(config.bind_addr(), server_tx).await {
+                eprintln!("Error with creating server: {:?}", e);
             }
         });
 
-        let tx_clone = tx.clone();
-
         self.tx = Some(tx);
+        self.broadcast_tx = Some(broadcast_tx);
+        self.membership_tx = Some(membership_tx);
 
-        let port = self.port.clone();
+        TurnipRuntimeHandles { manager, server }
+    }
 
-        tokio::spawn(async move {
-            match create_server(format!("127.0.0.1:{}", port), tx_clone).await {
-                Ok(_r) => {}
-                Err(e) => {
-                    eprintln!("Error with creating server: {:?}", e);
-                }
-            };
-        });
+    // runs the runtime until its server stops, for a node doing nothing but relaying between peers
+    pub async fn run_blocking(&mut self) {
+        let handles = self.run();
+
+        if let Err(e) = handles.server.await {
+            eprintln!("Error with running the server: {:?}", e);
+        }
     }
 
     pub fn get_messenger(&mut self) -> Result<TurnipMessenger, TurnipRuntimeError> {
@@ -139,106 +126,79 @@ impl TurnipRuntime {
         Ok(TurnipMessenger::new(self.tx.as_ref().unwrap().clone()))
     }
 
-    pub fn get_receiver(&mut self) -> Result<broadcast::Receiver<Vec<u8>>, TurnipRuntimeError> {
+    // subscription to every message received from other nodes
+    pub fn get_receiver(&mut self) -> Result<MessageSubscription, TurnipRuntimeError> {
         if let Some(tx) = self.broadcast_tx.as_ref() {
-            Ok(tx.subscribe())
+            Ok(MessageSubscription::new(tx.subscribe(), None))
         } else {
             Err(TurnipRuntimeError::NotIntializedError())
         }
     }
 
-    pub async fn run_blocking(&mut self) {
-        let (broadcast_tx, _) = broadcast::channel::<Vec<u8>>(16);
-
-        let broadcast_tx_clone = broadcast_tx.clone();
-
-        self.broadcast_tx = Some(broadcast_tx);
-
-        // tcp stream channel
-        let (tx, mut rx1) = mpsc::channel::<TcpStreamMessage>(16);
-
-        let connections = self.init_connections.clone();
-
-        let thread_tx = tx.clone();
-
-        // data structure for handling the tcpstreams
-        tokio::spawn(async move {
-            let mut stream_map: HashMap<String, (mpsc::Sender<Vec<u8>>, JoinHandle<()>)> =
-                HashMap::new();
-
-            for addr in connections.iter() {
-                if let Ok(socket) = TcpStream::connect(addr).await {
-                    handle_connection(&mut stream_map, socket, addr.clone(), thread_tx.clone());
-                }
-            }
-
-            while let Some(msg) = rx1.recv().await {
-                match msg {
-                    Connect(addr, socket) => {
-                        handle_connection(&mut stream_map, socket, addr, thread_tx.clone());
-                    }
-                    Disconnect(addr) => {
-                        println!("You've just disconnected with Address: {addr}");
+    // subscription to only the given kinds of messages received from other nodes
+    pub fn get_subscription(
+        &mut self,
+        kinds: Vec<MessageKind>,
+    ) -> Result<MessageSubscription, TurnipRuntimeError> {
+        if let Some(tx) = self.broadcast_tx.as_ref() {
+            Ok(MessageSubscription::new(tx.subscribe(), Some(kinds)))
+        } else {
+            Err(TurnipRuntimeError::NotIntializedError())
+        }
+    }
 
-                        // this should run drop on the socket and handle supposedly
-                        stream_map.remove(&addr);
-                    }
-                    Write(addr, msg) => {
-                        // implementation for writing to another socket
-                        // we would only write to another socket if:
-                        // 1) we want to send them metadata based on a received request or
-                        // 2) they have specified interest in a collection that we are interested in
-                        // 3) we own data that another process is interested in
-                        write(&mut stream_map, addr, msg).await;
-                    }
-                    WriteAll(msg) => {
-                        // we want to write all when we make a query(such as 'SELECT first_name, last_name from customer where id = 1;')
-                        // this will broadcast to everyone that we are interested in some subset of data.
-                        write_to_all(&mut stream_map, msg).await;
-                    }
-                    Read(_addr, msg) => {
-                        // implementation for reading from a specific socket
-                        // When we read from other sockets, that means that either they:
-                        // sending a metadata request(like other ip addresses in the landscape) or
-                        // are making a query(either telling us about an insert or a giving us a select)
-                        match broadcast_tx_clone.send(msg) {
-                            Ok(_) => {}
-                            Err(e) => {
-                                eprintln!("Errror with broadcasting the read: {:?}", e)
-                            }
-                        };
-                    }
-                }
-            }
-        });
+    // subscription to the peers coming up, being suspected and dying
+    pub fn get_membership_events(
+        &mut self,
+    ) -> Result<broadcast::Receiver<MembershipEvent>, TurnipRuntimeError> {
+        if let Some(tx) = self.membership_tx.as_ref() {
+            Ok(tx.subscribe())
+        } else {
+            Err(TurnipRuntimeError::NotIntializedError())
+        }
+    }
+}
 
-        let tx_clone = tx.clone();
+// asks the connection manager to do some periodic work every interval, until it has shut down
+fn spawn_ticks(
+    tx: mpsc::Sender<TcpStreamMessage>,
+    interval: Duration,
+    tick: fn() -> TcpStreamMessage,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
 
-        self.tx = Some(tx);
+        loop {
+            interval.tick().await;
 
-        match create_server(format!("127.0.0.1:{}", self.port), tx_clone).await {
-            Ok(_r) => {}
-            Err(e) => {
-                eprintln!("Error with creating server: {:?}", e);
+            if tx.send(tick()).await.is_err() {
+                return;
             }
-        };
-    }
+        }
+    });
 }
 
 pub fn handle_connection(
-    stream_map: &mut HashMap<String, (mpsc::Sender<Vec<u8>>, JoinHandle<()>)>,
+    stream_map: &mut StreamMap,
     mut socket: TcpStream,
     addr: String,
     tx: mpsc::Sender<TcpStreamMessage>,
+    config: &TurnipRuntimeConfig,
 ) {
     let reader_tx = tx;
 
+    let max_frame_size = config.max_frame_size;
+
+    let read_buffer_size = config.read_buffer_size;
+
     let address = addr.clone();
 
-    let (tx, mut rx) = mpsc::channel::<Vec<u8>>(16);
+    let (tx, mut rx) = mpsc::channel::<Vec<u8>>(config.peer_queue_capacity);
 
     let handle = tokio::spawn(async move {
-        let mut buf = vec![0; 1024];
+        let mut buf = vec![0; read_buffer_size];
+
+        let mut decoder = FrameDecoder::new(max_frame_size);
 
         loop {
             select! {
@@ -247,30 +207,55 @@ pub fn handle_connection(
                     match val{
                         Ok(n) => {
                             if n == 0 {
-                                match reader_tx
-                                    .send(TcpStreamMessage::Disconnect(address.clone()))
-                                    .await
-                                {
-                                    Ok(_r) => {}
-                                    Err(e) => {
-                                        eprintln!("Error with sending disconnect: {:?}", e);
-                                    }
-                                };
+                                if let Err(e) = decoder.finish() {
+                                    eprintln!("Error with reading from {address}: {:?}", e);
+                                }
+
+                                send_disconnect(&reader_tx, &address).await;
                                 return;
                             }
 
-                            match reader_tx
-                            .send(TcpStreamMessage::Read(address.clone(), buf.clone()))
-                            .await
-                            {
-                                Ok(_r) => {}
-                                Err(e) => {
-                                    eprintln!("Error with sending disconnect: {:?}", e);
+                            decoder.extend(&buf[..n]);
+
+                            // a single read can complete any number of frames
+                            loop {
+                                match decoder.next_frame() {
+                                    Ok(Some(frame)) => {
+                                        // decode once here, so nothing past the connection deals in raw bytes
+                                        let message = match postcard::from_bytes::<Message>(&frame) {
+                                            Ok(message) => message,
+                                            Err(e) => {
+                                                eprintln!("Error with decoding a message from {address}: {:?}", e);
+                                                continue;
+                                            }
+                                        };
+
+                                        match reader_tx
+                                        .send(TcpStreamMessage::Read(address.clone(), Box::new(message)))
+                                        .await
+                                        {
+                                            Ok(_r) => {}
+                                            Err(e) => {
+                                                eprintln!("Error with sending the read from {address}: {:?}", e);
+                                            }
+                                        };
+                                    }
+                                    Ok(None) => break,
+                                    Err(e) => {
+                                        // the stream can not be resynchronized after a bad header
+                                        eprintln!("Error with reading from {address}: {:?}", e);
+
+                                        send_disconnect(&reader_tx, &address).await;
+                                        return;
+                                    }
                                 }
-                            };
+                            }
                         },
-                        Err(_e) => {
-                            eprintln!("Failed to write to the socket");
+                        Err(e) => {
+                            eprintln!("Failed to read from {address}: {:?}", e);
+
+                            send_disconnect(&reader_tx, &address).await;
+                            return;
                         }
                     }
                 },
@@ -278,67 +263,110 @@ pub fn handle_connection(
                 val = rx.recv() => {
                     match val {
                         Some(v) => {
-                            println!("Received {:?}", v);
-                            match socket.write_all(&v).await {
-                                Ok(_v) => {
-
-                                },
+                            let frame = match encode_frame(&v, max_frame_size) {
+                                Ok(frame) => frame,
                                 Err(e) => {
-                                    eprintln!("Error: {:?}",e);
+                                    eprintln!("Error with framing a message to {address}: {:?}", e);
+                                    continue;
                                 }
                             };
+
+                            // the socket is gone, the link is dropped like after a failed read
+                            if let Err(e) = socket.write_all(&frame).await {
+                                eprintln!("Error with writing to {address}: {:?}", e);
+
+                                send_disconnect(&reader_tx, &address).await;
+                                return;
+                            }
                         },
                         None => {
-                            eprintln!("Error when trying to write to socket");
+                            // the connection has been removed from the stream map
+                            return;
                         }
                     }
                 }
             }
-
-            println!("Looped here");
         }
     });
 
     stream_map.insert(addr, (tx, handle));
 }
 
-pub async fn write_to_all(
-    stream_map: &mut HashMap<String, (mpsc::Sender<Vec<u8>>, JoinHandle<()>)>,
-    msg: Vec<u8>,
-) {
-    let keys: Vec<String> = stream_map.keys().map(|v| v.to_string()).collect();
-
-    println!("here are the keys: {:?}", keys);
+async fn send_disconnect(tx: &mpsc::Sender<TcpStreamMessage>, addr: &str) {
+    match tx
+        .send(TcpStreamMessage::Disconnect(addr.to_string()))
+        .await
+    {
+        Ok(_r) => {}
+        Err(e) => {
+            eprintln!("Error with sending the disconnect of {addr}: {:?}", e);
+        }
+    };
+}
 
-    for key in keys {
-        println!("Sending to {key}");
+// Queues the message for every peer without waiting on any of them. Returns the peers that could
+// not take it, their links are gone or they have stopped reading what we send them.
+pub fn write_to_all(stream_map: &StreamMap, msg: Vec<u8>) -> Vec<String> {
+    stream_map
+        .keys()
+        .filter(|key| !write(stream_map, key, msg.clone()))
+        .cloned()
+        .collect()
+}
 
-        if let Some((socket, _)) = stream_map.get_mut(&key) {
-            // TODO: error handling here
-            match socket.send(msg.clone()).await {
-                Ok(_r) => {}
-                Err(e) => {
-                    eprintln!("Error with Writing all: {:?}", e);
-                }
-            };
-        } else {
-            println!("Failed to get the addr from socket");
+// queues the message for the peer, false if it could not take it
+pub fn write(stream_map: &StreamMap, key: &str, msg: Vec<u8>) -> bool {
+    let Some((tx, _)) = stream_map.get(key) else {
+        return false;
+    };
+
+    match tx.try_send(msg) {
+        Ok(_r) => true,
+        Err(e) => {
+            eprintln!("Error with writing to {key}: {e}");
+            false
         }
     }
 }
 
-pub async fn write(
-    stream_map: &mut HashMap<String, (mpsc::Sender<Vec<u8>>, JoinHandle<()>)>,
-    key: String,
-    msg: Vec<u8>,
-) {
-    if let Some((tx, _)) = stream_map.get_mut(&key) {
-        // TODO: error handling here
-        match tx.send(msg).await {
-            Ok(_r) => {}
-            Err(e) => {
-                eprintln!("Error with Writing all: {:?}", e);
-            }
-        };
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn full_queues_are_not_waited_on() {
+        let mut stream_map: StreamMap = HashMap::new();
+
+        // a peer that never reads
+        let (hung_tx, _hung_rx) = mpsc::channel(1);
+        let (tx, mut rx) = mpsc::channel(1);
+
+        stream_map.insert("hung".to_string(), (hung_tx, tokio::spawn(async {})));
+        stream_map.insert("fine".to_string(), (tx, tokio::spawn(async {})));
+
+        assert!(write_to_all(&stream_map, vec![1]).is_empty());
+        assert_eq!(rx.recv().await, Some(vec![1]));
+
+        assert_eq!(write_to_all(&stream_map, vec![2]), vec!["hung".to_string()]);
+        assert_eq!(rx.recv().await, Some(vec![2]));
+
+        assert!(!write(&stream_map, "gone", vec![3]));
+    }
+
+    #[tokio::test]
+    async fn run_blocking_returns_once_the_server_stops() {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Could not bind");
+        let port = listener.local_addr().expect("No address").port();
+
+        let mut runtime = TurnipRuntime::new(TurnipRuntimeConfig::new("runtime".to_string(), port));
+
+        tokio::time::timeout(Duration::from_secs(5), runtime.run_blocking())
+            .await
+            .expect("The runtime kept running on a port that is taken");
+
+        assert!(runtime.is_initialized());
     }
 }
@@ -0,0 +1,448 @@
+// Keeps the links to the configured peers up. Every configured peer gets a task that connects to
+// it, retrying with exponential backoff and jitter, which is started again whenever the link
+// drops. Every link that comes up is sent the selects this node has active, so the peer keeps
+// pushing us its changes after a network blip.
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::messaging::CorrelationId;
+use crate::models::tcp_stream_message::TcpStreamMessage;
+
+use super::{write, StreamMap};
+
+// How long to wait between the attempts to connect to a peer
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconnectPolicy {
+    // the wait after the first failed attempt, doubled after every attempt after that
+    pub initial_backoff: Duration,
+    // the longest wait between two attempts
+    pub max_backoff: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        ReconnectPolicy {
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    // The wait before the given retry (0 based). Half of it is random, so nodes that lost their
+    // links at the same time don't all come back at the same time.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff);
+
+        let half = backoff / 2;
+
+        half + half.mul_f64(random_fraction())
+    }
+}
+
+// a random number in [0, 1), the hasher of every RandomState is seeded differently
+fn random_fraction() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(0);
+
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[derive(Debug)]
+pub struct Supervisor {
+    // the configured peers we keep connected to, by the address we connect to
+    peers: Vec<String>,
+    // the peers found by gossip, with the task connecting to them. unlike the configured ones they
+    // are given up on once they are dead
+    discovered: HashMap<String, JoinHandle<()>>,
+    policy: ReconnectPolicy,
+    // the encoded selects this node has made, sent again to every link that comes up
+    active_selects: BTreeMap<CorrelationId, Vec<u8>>,
+}
+
+impl Supervisor {
+    pub fn new(peers: Vec<String>, policy: ReconnectPolicy) -> Self {
+        Supervisor {
+            peers,
+            discovered: HashMap::new(),
+            policy,
+            active_selects: BTreeMap::new(),
+        }
+    }
+
+    // starts connecting to every configured peer
+    pub fn start(&self, tx: &mpsc::Sender<TcpStreamMessage>) {
+        for addr in self.peers.iter() {
+            spawn_connect(addr.clone(), tx.clone(), self.policy.clone(), false);
+        }
+    }
+
+    pub fn is_peer(&self, addr: &str) -> bool {
+        self.peers.iter().any(|peer| peer == addr) || self.discovered.contains_key(addr)
+    }
+
+    pub fn peer_count(&self) -> usize {
+        self.peers.len() + self.discovered.len()
+    }
+
+    // starts keeping a peer found after the start connected
+    pub fn add_peer(&mut self, addr: String, tx: &mpsc::Sender<TcpStreamMessage>) {
+        if !self.is_peer(&addr) {
+            let connecting = spawn_connect(addr.clone(), tx.clone(), self.policy.clone(), false);
+            self.discovered.insert(addr, connecting);
+        }
+    }
+
+    // stops connecting to a peer found by gossip, configured peers are kept
+    pub fn remove_peer(&mut self, addr: &str) {
+        if let Some(connecting) = self.discovered.remove(addr) {
+            connecting.abort();
+        }
+    }
+
+    pub fn add_select(&mut self, id: CorrelationId, select: Vec<u8>) {
+        self.active_selects.insert(id, select);
+    }
+
+    pub fn remove_select(&mut self, id: CorrelationId) {
+        self.active_selects.remove(&id);
+    }
+
+    // A node came up, it is told about the selects we have made. Returns the node if it could not
+    // take them.
+    pub fn connected(&self, stream_map: &StreamMap, node: &str) -> Vec<String> {
+        for select in self.active_selects.values() {
+            if !write(stream_map, node, select.clone()) {
+                return vec![node.to_string()];
+            }
+        }
+
+        vec![]
+    }
+
+    // a link went down, links to peers we keep are reconnected
+    pub fn disconnected(&mut self, addr: &str, tx: &mpsc::Sender<TcpStreamMessage>) {
+        if self.peers.iter().any(|peer| peer == addr) {
+            spawn_connect(addr.to_string(), tx.clone(), self.policy.clone(), true);
+        } else if let Some(connecting) = self.discovered.get_mut(addr) {
+            *connecting = spawn_connect(addr.to_string(), tx.clone(), self.policy.clone(), true);
+        }
+    }
+}
+
+// Connects to the peer until it works, then hands the socket to the runtime. A link that just went
+// down is not retried right away, a peer that drops every link would otherwise be hammered.
+fn spawn_connect(
+    addr: String,
+    tx: mpsc::Sender<TcpStreamMessage>,
+    policy: ReconnectPolicy,
+    wait_first: bool,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut attempt = 0;
+
+        if wait_first {
+            tokio::time::sleep(policy.backoff(attempt)).await;
+            attempt += 1;
+        }
+
+        loop {
+            // the runtime has shut down
+            if tx.is_closed() {
+                return;
+            }
+
+            match TcpStream::connect(&addr).await {
+                Ok(socket) => {
+                    if let Err(e) = tx.send(TcpStreamMessage::Connect(addr, socket)).await {
+                        eprintln!("Error with handing over the connection: {:?}", e);
+                    }
+
+                    return;
+                }
+                Err(e) => {
+                    let backoff = policy.backoff(attempt);
+
+                    eprintln!("Could not connect to {addr}, retrying in {backoff:?}: {e}");
+
+                    tokio::time::sleep(backoff).await;
+                    attempt = attempt.saturating_add(1);
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messaging::codec::{encode_frame, FrameDecoder, DEFAULT_MAX_FRAME_SIZE};
+    use crate::messaging::{Handshake, Message, PROTOCOL_VERSION};
+    use crate::models::projection::ProjectionItem;
+    use crate::models::select_query::SelectQuery;
+    use crate::runtime::{TurnipRuntime, TurnipRuntimeConfig};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn backoff_grows_up_to_the_max_with_jitter() {
+        let policy = ReconnectPolicy {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+        };
+
+        for (attempt, full) in [
+            (0, 100),
+            (1, 200),
+            (2, 400),
+            (3, 800),
+            (4, 1000),
+            (40, 1000),
+        ] {
+            let backoff = policy.backoff(attempt);
+            let full = Duration::from_millis(full);
+
+            assert!(
+                backoff >= full / 2 && backoff <= full,
+                "{attempt}: {backoff:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn only_discovered_peers_are_removed() {
+        let (tx, _rx) = mpsc::channel::<TcpStreamMessage>(16);
+
+        let mut supervisor =
+            Supervisor::new(vec!["127.0.0.1:1".to_string()], ReconnectPolicy::default());
+
+        supervisor.add_peer("127.0.0.1:2".to_string(), &tx);
+        assert_eq!(supervisor.peer_count(), 2);
+
+        supervisor.remove_peer("127.0.0.1:1");
+        supervisor.remove_peer("127.0.0.1:2");
+
+        assert!(supervisor.is_peer("127.0.0.1:1"));
+        assert!(!supervisor.is_peer("127.0.0.1:2"));
+        assert_eq!(supervisor.peer_count(), 1);
+    }
+
+    #[test]
+    fn dropped_selects_are_not_sent_again() {
+        let mut supervisor = Supervisor::new(vec![], ReconnectPolicy::default());
+
+        supervisor.add_select(1, vec![1]);
+        supervisor.add_select(2, vec![2]);
+        supervisor.remove_select(1);
+
+        assert_eq!(supervisor.active_selects, BTreeMap::from([(2, vec![2])]));
+    }
+
+    // reads frames off the socket until one decodes into a message
+    async fn read_message(socket: &mut TcpStream) -> Message {
+        let mut decoder = FrameDecoder::default();
+        let mut buf = vec![0; 1024];
+
+        loop {
+            if let Some(frame) = decoder.next_frame().expect("Bad frame") {
+                return postcard::from_bytes(&frame).expect("Could not decode");
+            }
+
+            let n = socket.read(&mut buf).await.expect("Could not read");
+            assert!(n > 0, "The connection was closed");
+
+            decoder.extend(&buf[..n]);
+        }
+    }
+
+    #[tokio::test]
+    async fn dropped_links_are_reconnected_and_get_the_selects_again() {
+        // the peer only starts listening after the runtime first tries to connect to it
+        let addr = {
+            let listener = TcpListener::bind("127.0.0.1:0")
+                .await
+                .expect("Could not bind");
+            listener.local_addr().expect("No address").to_string()
+        };
+
+        let mut runtime = TurnipRuntime::new(
+            TurnipRuntimeConfig::new("runtime".to_string(), 0)
+                .peers(vec![addr.clone()])
+                .reconnect_policy(ReconnectPolicy {
+                    initial_backoff: Duration::from_millis(10),
+                    max_backoff: Duration::from_millis(50),
+                }),
+        );
+        runtime.run();
+
+        let select = SelectQuery {
+            into: "customer_cache".to_string(),
+            projection: vec![ProjectionItem::Wildcard],
+            from: "customer".to_string(),
+            constraints: None,
+            aggregates: vec![],
+            group_by: vec![],
+            join: None,
+        };
+
+        runtime
+            .get_messenger()
+            .expect("No messenger")
+            .select(7, select.clone())
+            .await;
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+
+        let listener = TcpListener::bind(&addr).await.expect("Could not bind");
+
+        let timeout = Duration::from_secs(5);
+
+        for _ in 0..2 {
+            let (mut socket, _) = tokio::time::timeout(timeout, listener.accept())
+                .await
+                .expect("The runtime did not connect")
+                .expect("Could not accept");
+
+            // the runtime only sends the select once we have said who we are
+            assert!(matches!(
+                tokio::time::timeout(timeout, read_message(&mut socket))
+                    .await
+                    .expect("The handshake was not sent"),
+                Message::Handshake(_)
+            ));
+
+            send_handshake(&mut socket, "peer", &addr).await;
+
+            assert_eq!(
+                tokio::time::timeout(timeout, read_message(&mut socket))
+                    .await
+                    .expect("The select was not sent"),
+                Message::Select(7, select.clone())
+            );
+
+            // the link drops, the runtime should come back
+            drop(socket);
+        }
+    }
+
+    async fn send_handshake(socket: &mut TcpStream, node_id: &str, listen_addr: &str) {
+        let handshake = Message::Handshake(Handshake {
+            node_id: node_id.to_string(),
+            listen_addr: listen_addr.to_string(),
+            protocol_version: PROTOCOL_VERSION,
+        });
+
+        socket
+            .write_all(
+                &encode_frame(
+                    &postcard::to_allocvec(&handshake).expect("Could not encode"),
+                    DEFAULT_MAX_FRAME_SIZE,
+                )
+                .expect("Could not frame"),
+            )
+            .await
+            .expect("Could not write");
+    }
+
+    #[tokio::test]
+    async fn selects_are_sent_again_over_a_link_that_replaces_another() {
+        let mut addrs = vec![];
+
+        for _ in 0..2 {
+            let listener = TcpListener::bind("127.0.0.1:0")
+                .await
+                .expect("Could not bind");
+            addrs.push(listener.local_addr().expect("No address"));
+        }
+
+        let (runtime_addr, peer_addr) = (addrs[0], addrs[1].to_string());
+
+        // the peer only starts listening once it has dialed the runtime itself
+        let mut runtime = TurnipRuntime::new(
+            TurnipRuntimeConfig::new("a".to_string(), runtime_addr.port())
+                .peers(vec![peer_addr.clone()])
+                .reconnect_policy(ReconnectPolicy {
+                    initial_backoff: Duration::from_millis(10),
+                    max_backoff: Duration::from_millis(50),
+                }),
+        );
+        runtime.run();
+
+        let select = SelectQuery {
+            into: "customer_cache".to_string(),
+            projection: vec![ProjectionItem::Wildcard],
+            from: "customer".to_string(),
+            constraints: None,
+            aggregates: vec![],
+            group_by: vec![],
+            join: None,
+        };
+
+        runtime
+            .get_messenger()
+            .expect("No messenger")
+            .select(7, select.clone())
+            .await;
+
+        let timeout = Duration::from_secs(5);
+
+        // the peer dials the runtime first, that link is in use until the runtime's own comes up
+        let mut dialed = tokio::time::timeout(timeout, async {
+            loop {
+                match TcpStream::connect(runtime_addr).await {
+                    Ok(socket) => return socket,
+                    Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+                }
+            }
+        })
+        .await
+        .expect("The runtime did not listen");
+
+        assert!(matches!(
+            tokio::time::timeout(timeout, read_message(&mut dialed))
+                .await
+                .expect("The handshake was not sent"),
+            Message::Handshake(_)
+        ));
+        send_handshake(&mut dialed, "peer", &peer_addr).await;
+
+        assert_eq!(
+            tokio::time::timeout(timeout, read_message(&mut dialed))
+                .await
+                .expect("The select was not sent"),
+            Message::Select(7, select.clone())
+        );
+
+        // the link the runtime dials takes over, a has the smaller id
+        let listener = TcpListener::bind(&peer_addr).await.expect("Could not bind");
+
+        let (mut accepted, _) = tokio::time::timeout(timeout, listener.accept())
+            .await
+            .expect("The runtime did not connect")
+            .expect("Could not accept");
+
+        assert!(matches!(
+            tokio::time::timeout(timeout, read_message(&mut accepted))
+                .await
+                .expect("The handshake was not sent"),
+            Message::Handshake(_)
+        ));
+        send_handshake(&mut accepted, "peer", &peer_addr).await;
+
+        assert_eq!(
+            tokio::time::timeout(timeout, read_message(&mut accepted))
+                .await
+                .expect("The select was not sent over the new link"),
+            Message::Select(7, select)
+        );
+    }
+}